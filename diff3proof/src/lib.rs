@@ -7,13 +7,16 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use clap::Parser;
+use clap::{builder::ValueHint, Parser};
 use diffenator3_lib::{
+    config::parse_with_config,
     dfont::{shared_axes, DFont},
     html::{gen_html, template_engine},
+    render::wordlists::longest_sampletext,
+    Error,
 };
 use env_logger::Env;
-use google_fonts_languages::{SampleTextProto, LANGUAGES, SCRIPTS};
+use google_fonts_languages::{LANGUAGES, SCRIPTS};
 use serde_json::json;
 
 #[derive(Parser, Debug, clap::ValueEnum, Clone, PartialEq)]
@@ -28,11 +31,11 @@ pub enum SampleMode {
 #[command(version, about = "Create before/after HTML proofs of fonts", long_about = None)]
 pub struct Cli {
     /// Output directory for HTML
-    #[clap(long = "output", default_value = "out")]
+    #[clap(long = "output", default_value = "out", value_hint = ValueHint::DirPath)]
     pub output: String,
 
     /// Directory for custom templates
-    #[clap(long = "templates")]
+    #[clap(long = "templates", value_hint = ValueHint::DirPath)]
     pub templates: Option<String>,
 
     /// Update diffenator3's stock templates
@@ -47,6 +50,10 @@ pub struct Cli {
     #[clap(long = "sample-mode", default_value = "context")]
     pub sample_mode: SampleMode,
 
+    /// Configuration file [default: diffenator3.toml in this or a parent directory]
+    #[clap(long = "config")]
+    pub config: Option<PathBuf>,
+
     /// The first font file to compare
     pub font1: PathBuf,
     /// The second font file to compare
@@ -54,21 +61,31 @@ pub struct Cli {
 }
 
 /// Entry point for the diff3proof CLI. Call this from main().
+///
+/// Options may also be set in the `[diff3proof]` table of a configuration file.
 pub fn cli_main() {
-    let cli = Cli::parse();
+    let (cli, _) = parse_with_config::<Cli>(Some("diff3proof"), &[]);
     env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
-    run(&cli);
+    if let Err(e) = run(&cli) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 }
 
-pub fn run(cli: &Cli) {
-    let font_binary_a = std::fs::read(&cli.font1).expect("Couldn't open file");
+fn load_font(path: &Path) -> Result<DFont, Error> {
+    let binary = std::fs::read(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    DFont::new(&binary)
+}
 
-    let tera = template_engine(cli.templates.as_ref(), cli.update_templates);
-    let font_a = DFont::new(&font_binary_a);
+pub fn run(cli: &Cli) -> Result<(), Error> {
+    let tera = template_engine(cli.templates.as_ref(), cli.update_templates)?;
+    let font_a = load_font(&cli.font1)?;
 
     let (shared_codepoints, axes, instances) = if let Some(font2) = &cli.font2 {
-        let font_binary_b = std::fs::read(font2).expect("Couldn't open file");
-        let font_b = DFont::new(&font_binary_b);
+        let font_b = load_font(font2)?;
 
         let shared_codepoints: HashSet<u32> = font_a
             .codepoints
@@ -106,34 +123,12 @@ pub fn run(cli: &Cli) {
         &cli.font1,
         cli.font2.as_ref().unwrap_or(&cli.font1),
         Path::new(&cli.output),
-        tera,
+        &tera,
         "diff3proof.html",
         &variables.into(),
         "diff3proof.html",
         cli.point_size,
-    );
-}
-
-fn longest_sampletext(st: &SampleTextProto) -> &str {
-    if let Some(text) = &st.specimen_16 {
-        return text;
-    }
-    if let Some(text) = &st.specimen_21 {
-        return text;
-    }
-    if let Some(text) = &st.specimen_32 {
-        return text;
-    }
-    if let Some(text) = &st.specimen_36 {
-        return text;
-    }
-    if let Some(text) = &st.specimen_48 {
-        return text;
-    }
-    if let Some(text) = &st.tester {
-        return text;
-    }
-    ""
+    )
 }
 
 fn language_sample_texts(codepoints: &HashSet<u32>) -> HashMap<String, Vec<(String, String)>> {
@@ -1,9 +1,29 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use clap::{builder::ArgAction, Parser};
+use crate::{
+    ignore::IgnoreRules, render_report::RenderReportArgs, reporters::markdown::MarkdownOptions,
+};
+use clap::{
+    builder::{ArgAction, ValueHint},
+    error::ErrorKind,
+    Args, CommandFactory, Parser, Subcommand,
+};
+use diffenator3_lib::config::parse_with_config;
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Number of worker processes. Defaults to the number of logical CPUs.
     #[clap(short = 'J', long)]
     pub jobs: Option<usize>,
@@ -40,6 +60,14 @@ pub struct Cli {
     #[clap(long = "words", overrides_with = "words", help_heading = Some("Tests to run"))]
     pub _no_words: bool,
 
+    /// Don't show diffs in font and glyph metrics
+    #[clap(long = "no-metrics", action = ArgAction::SetFalse, help_heading = Some("Tests to run"))]
+    pub metrics: bool,
+
+    /// Show diffs in font and glyph metrics [default]
+    #[clap(long = "metrics", overrides_with = "metrics", help_heading = Some("Tests to run"))]
+    pub _no_metrics: bool,
+
     /// Don't show language support differences
     #[clap(long = "no-languages", action = ArgAction::SetFalse, help_heading = Some("Tests to run"))]
     pub languages: bool,
@@ -48,44 +76,33 @@ pub struct Cli {
     #[clap(long = "languages", overrides_with = "languages", help_heading = Some("Tests to run"))]
     pub _no_languages: bool,
 
+    /// Look for glyphs which don't interpolate linearly between masters
+    #[clap(long = "interpolation", help_heading = Some("Tests to run"))]
+    pub interpolation: bool,
+
+    /// Gather every change to each glyph into a dossier, for JSON and HTML reports
+    #[clap(long = "dossiers", help_heading = Some("Tests to run"))]
+    pub dossiers: bool,
+
+    /// Test words from each language's sample text, shaped with its OpenType language system
+    #[clap(long = "words-by-language", help_heading = Some("Tests to run"))]
+    pub words_by_language: bool,
+
     /// Custom word list files for testing
+    ///
+    /// One word per line. Directives such as `#features: +smcp,-liga`,
+    /// `#lang: SRB`, `#script: Cyrl` and `#dir: rtl` set how the following
+    /// lines are shaped; other lines starting with `#` are comments.
     #[clap(long = "custom-wordlists", help_heading = Some("Tests to run"))]
     pub custom_wordlists: Vec<PathBuf>,
 
-    /// Show diffs as JSON
-    #[clap(long = "json", help_heading = Some("Report format"))]
-    pub json: bool,
-    /// Show diffs as HTML
-    #[clap(long = "html", help_heading = Some("Report format"))]
-    pub html: bool,
-    /// If an entry is absent in one font, show the data anyway
-    #[clap(long = "no-succinct", action = ArgAction::SetFalse, help_heading = Some("Report format"))]
-    pub succinct: bool,
-
-    /// If an entry is absent in one font, just report it as absent
-    #[clap(long = "succinct", overrides_with = "succinct", help_heading = Some("Report format"))]
-    pub _no_succinct: bool,
+    #[clap(flatten)]
+    pub format: ReportFormat,
 
     /// Maximum number of changes to report before giving up
     #[clap(long = "max-changes", default_value = "128", help_heading = Some("Report format"))]
     pub max_changes: usize,
 
-    /// Indent JSON
-    #[clap(long = "pretty", requires = "json", help_heading = Some("Report format"))]
-    pub pretty: bool,
-
-    /// Output directory for HTML
-    #[clap(long = "output", default_value = "out", requires = "html", help_heading = Some("Report format"))]
-    pub output: String,
-
-    /// Directory for custom templates
-    #[clap(long = "templates", requires = "html", help_heading = Some("Report format"))]
-    pub templates: Option<String>,
-
-    /// Update diffenator3's stock templates
-    #[clap(long = "update-templates", requires = "html", help_heading = Some("Report format"))]
-    pub update_templates: bool,
-
     /// Location in user space, in the form axis=123,other=456 (may be repeated)
     #[clap(long = "location", help_heading = "Locations to test")]
     pub location: Vec<String>,
@@ -95,6 +112,9 @@ pub struct Cli {
     /// Masters (as detected from the gvar table)
     #[clap(long = "masters", help_heading = "Locations to test")]
     pub masters: bool,
+    /// Combinations of the axis values in the STAT table, named from the table
+    #[clap(long = "stat", help_heading = "Locations to test")]
+    pub stat: bool,
     /// Cross-product (use min/default/max of all axes)
     #[clap(long = "cross-product", help_heading = "Locations to test")]
     pub cross_product: bool,
@@ -105,17 +125,242 @@ pub struct Cli {
         default_value = "1"
     )]
     pub splits: usize,
+    /// Search the designspace for the worst differences, testing at most BUDGET locations
+    #[clap(
+        long = "adaptive",
+        value_name = "BUDGET",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help_heading = "Locations to test"
+    )]
+    pub adaptive: Option<usize>,
+    /// Map an axis of the first font onto the second, as FROM[:MIN:MAX]=TO[:MIN:MAX] (may be repeated)
+    ///
+    /// Locations are given in the first font's axes. `XOPQ=XOPA` renames
+    /// an axis; `wdth:75:100=wdth:62.5:100` maps one range onto another.
+    #[clap(long = "map-axis", help_heading = "Locations to test")]
+    pub map_axis: Vec<String>,
+    /// Value for an axis which only one font has, in the form axis=123 (may be repeated)
+    #[clap(long = "pin-axis", help_heading = "Locations to test")]
+    pub pin_axis: Vec<String>,
+    /// Location (axis=123,other=456) or instance of the first font, to compare with --location-b
+    ///
+    /// Each font is moved to its own position, so the same variable font can
+    /// be given twice to compare two of its locations.
+    #[clap(
+        long = "location-a",
+        requires = "location_b",
+        help_heading = "Locations to test"
+    )]
+    pub location_a: Option<String>,
+    /// Location (axis=123,other=456) or instance of the second font, to compare with --location-a
+    #[clap(
+        long = "location-b",
+        requires = "location_a",
+        help_heading = "Locations to test"
+    )]
+    pub location_b: Option<String>,
 
     /// Don't try to match glyph names between fonts
     #[clap(long = "no-match", help_heading = Some("Report format"))]
     pub no_match: bool,
 
-    /// Show diffs as JSON
-    #[clap(long = "quiet")]
+    /// Exit with an error if any encoded glyphs were removed
+    #[clap(long = "fail-on-cmap-removals", help_heading = "Gating")]
+    pub fail_on_cmap_removals: bool,
+
+    /// Exit with an error if any word differs by more than this many pixels
+    #[clap(
+        long = "fail-on-word-diff",
+        value_name = "PIXELS",
+        help_heading = "Gating"
+    )]
+    pub fail_on_word_diff: Option<usize>,
+
+    /// Exit with an error if any kerning value changes by more than this many units
+    #[clap(
+        long = "fail-on-kern-change",
+        value_name = "UNITS",
+        help_heading = "Gating"
+    )]
+    pub fail_on_kern_change: Option<f64>,
+
+    /// Exit with an error if the support level of any language drops
+    #[clap(long = "fail-on-language-drop", help_heading = "Gating")]
+    pub fail_on_language_drop: bool,
+
+    /// Exit with an error if any table changes, other than those allowed
+    #[clap(long = "fail-on-table-changes", help_heading = "Gating")]
+    pub fail_on_table_changes: bool,
+
+    /// Tables which may change without failing (comma-separated, may be repeated)
+    #[clap(
+        long = "allow-table-changes",
+        value_name = "TABLES",
+        value_delimiter = ',',
+        requires = "fail_on_table_changes",
+        help_heading = "Gating"
+    )]
+    pub allow_table_changes: Vec<String>,
+
+    /// Only show errors, not progress
+    #[clap(long = "quiet", global = true)]
     pub quiet: bool,
 
-    /// The first font file to compare
-    pub font1: PathBuf,
-    /// The second font file to compare
-    pub font2: PathBuf,
+    /// File of known differences to leave out of the report (may be repeated)
+    #[clap(long = "ignore-file", help_heading = Some("Report format"))]
+    pub ignore_file: Vec<PathBuf>,
+
+    /// Save the whole report as a snapshot, for use with --baseline later
+    #[clap(long = "save-snapshot", value_name = "FILE", value_hint = ValueHint::FilePath, help_heading = Some("Report format"))]
+    pub save_snapshot: Option<PathBuf>,
+
+    /// Only report differences which are not in this snapshot
+    #[clap(long = "baseline", value_name = "FILE", value_hint = ValueHint::FilePath, help_heading = Some("Report format"))]
+    pub baseline: Option<PathBuf>,
+
+    /// Configuration file [default: diffenator3.toml in this or a parent directory]
+    #[clap(long = "config", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Word difference thresholds per script (ISO 15924 tag), from the configuration file
+    #[clap(skip)]
+    pub thresholds: HashMap<String, usize>,
+
+    /// Differences to leave out of the report, from the configuration file
+    #[clap(skip)]
+    pub ignore: IgnoreRules,
+
+    /// The first font file to compare, or a directory of fonts
+    ///
+    /// If one argument is a directory of static fonts and the other a
+    /// variable font, each static font is compared with the variable font
+    /// at its matching instance.
+    #[clap(required = true)]
+    pub font1: Option<PathBuf>,
+    /// The second font file to compare, or a directory of fonts
+    #[clap(required = true)]
+    pub font2: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show a report saved with --json or --save-snapshot again
+    RenderReport(RenderReportArgs),
+}
+
+impl Cli {
+    /// Parse the command line, along with a `diffenator3.toml` configuration file
+    ///
+    /// Besides the command line options, the file may contain a
+    /// `[thresholds]` table of word difference thresholds per script and
+    /// an `[ignore]` table of [IgnoreRules], which are combined with those
+    /// from any `--ignore-file`. Its `[diff3proof]` table is
+    /// read by `diff3proof`.
+    pub fn parse_with_config() -> Self {
+        let (mut cli, extras) =
+            parse_with_config::<Cli>(None, &["thresholds", "ignore", "diff3proof"]);
+        if let Some(thresholds) = extras.get("thresholds") {
+            cli.thresholds = thresholds.clone().try_into().unwrap_or_else(|e| {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!("Couldn't parse [thresholds]: {}", e),
+                    )
+                    .exit()
+            });
+        }
+        if let Some(ignore) = extras.get("ignore") {
+            cli.ignore = ignore.clone().try_into().unwrap_or_else(|e| {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!("Couldn't parse [ignore]: {}", e),
+                    )
+                    .exit()
+            });
+        }
+        for path in cli.ignore_file.iter() {
+            let rules = IgnoreRules::from_file(path)
+                .unwrap_or_else(|e| Cli::command().error(ErrorKind::Io, e).exit());
+            cli.ignore.extend(rules);
+        }
+        cli.ignore
+            .compile()
+            .unwrap_or_else(|e| Cli::command().error(ErrorKind::InvalidValue, e).exit());
+        cli
+    }
+
+    /// The first font or directory to compare; only absent with a subcommand
+    pub fn font1(&self) -> &Path {
+        self.font1.as_deref().expect("font1 is required")
+    }
+
+    /// The second font or directory to compare; only absent with a subcommand
+    pub fn font2(&self) -> &Path {
+        self.font2.as_deref().expect("font2 is required")
+    }
+}
+
+/// Options choosing how the report is shown, shared with `render-report`
+#[derive(Args, Debug)]
+pub struct ReportFormat {
+    /// Show diffs as JSON
+    #[clap(long = "json", help_heading = Some("Report format"))]
+    pub json: bool,
+    /// Show diffs as HTML
+    #[clap(long = "html", help_heading = Some("Report format"))]
+    pub html: bool,
+    /// Show diffs as Markdown, for pull request comments
+    #[clap(long = "markdown", help_heading = Some("Report format"))]
+    pub markdown: bool,
+    /// Show diffs as JUnit XML, for test dashboards
+    #[clap(long = "junit", help_heading = Some("Report format"))]
+    pub junit: bool,
+    /// Show diffs as SARIF, for code scanning dashboards
+    #[clap(long = "sarif", help_heading = Some("Report format"))]
+    pub sarif: bool,
+    /// Number of glyph and word differences to show per script and location in Markdown
+    #[clap(long = "markdown-top", default_value = "10", requires = "markdown", help_heading = Some("Report format"))]
+    pub markdown_top: usize,
+    /// Maximum length of the Markdown report, in bytes
+    #[clap(long = "markdown-budget", default_value = "60000", requires = "markdown", help_heading = Some("Report format"))]
+    pub markdown_budget: usize,
+    /// If an entry is absent in one font, show the data anyway
+    #[clap(long = "no-succinct", action = ArgAction::SetFalse, help_heading = Some("Report format"))]
+    pub succinct: bool,
+
+    /// If an entry is absent in one font, just report it as absent
+    #[clap(long = "succinct", overrides_with = "succinct", help_heading = Some("Report format"))]
+    pub _no_succinct: bool,
+
+    /// Indent JSON
+    #[clap(long = "pretty", requires = "json", help_heading = Some("Report format"))]
+    pub pretty: bool,
+
+    /// Output directory for HTML
+    #[clap(long = "output", default_value = "out", requires = "html", value_hint = ValueHint::DirPath, help_heading = Some("Report format"))]
+    pub output: String,
+
+    /// Directory for custom templates
+    #[clap(long = "templates", requires = "html", value_hint = ValueHint::DirPath, help_heading = Some("Report format"))]
+    pub templates: Option<String>,
+
+    /// List each differing glyph and word once, with the locations where it differs
+    #[clap(long = "by-glyph", help_heading = Some("Report format"))]
+    pub by_glyph: bool,
+
+    /// Update diffenator3's stock templates
+    #[clap(long = "update-templates", requires = "html", help_heading = Some("Report format"))]
+    pub update_templates: bool,
+}
+
+impl ReportFormat {
+    /// Options for the Markdown reporter
+    pub fn markdown_options(&self) -> MarkdownOptions {
+        MarkdownOptions {
+            top: self.markdown_top,
+            budget: self.markdown_budget,
+            succinct: self.succinct,
+        }
+    }
 }
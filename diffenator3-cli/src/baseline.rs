@@ -0,0 +1,256 @@
+//! Comparing a new report against a snapshot of an earlier one
+//!
+//! A snapshot saved with `--save-snapshot` records the whole [Report] for
+//! one comparison, in the same versioned format as `--json`. Passing it
+//! back with `--baseline` leaves only the differences which are new since
+//! the snapshot was taken: differences which appear unchanged in the
+//! baseline are dropped, and counted in the report's list of suppressed
+//! differences.
+use crate::{ignore::count_differences, reporters::Report};
+use serde_json::Value;
+use ttj::jsondiff::Substantial;
+
+/// Remove from `report` the differences which also appear in `baseline`
+///
+/// Each removed difference is counted under `rule` in the report's
+/// suppressed differences.
+pub fn apply_baseline(report: &mut Report, baseline: &Report, rule: &str) {
+    let mut count = 0;
+
+    for (ours, theirs) in [
+        (&mut report.tables, &baseline.tables),
+        (&mut report.kerns, &baseline.kerns),
+    ] {
+        if let (Some(ours_diff), Some(theirs_diff)) = (ours.as_mut(), theirs.as_ref()) {
+            count += subtract(ours_diff, theirs_diff);
+            if !ours_diff.is_something() {
+                *ours = None;
+            }
+        }
+    }
+
+    if let (Some(ours), Some(theirs)) = (report.cmap_diff.as_mut(), baseline.cmap_diff.as_ref()) {
+        for (glyphs, known) in [
+            (&mut ours.missing, &theirs.missing),
+            (&mut ours.new, &theirs.new),
+        ] {
+            let before = glyphs.len();
+            glyphs.retain(|glyph| !known.iter().any(|k| k.string == glyph.string));
+            count += before - glyphs.len();
+        }
+    }
+
+    let before = report.instances.len();
+    report
+        .instances
+        .retain(|change| !baseline.instances.contains(change));
+    count += before - report.instances.len();
+
+    let before = report.palettes.len();
+    report
+        .palettes
+        .retain(|change| !baseline.palettes.contains(change));
+    count += before - report.palettes.len();
+
+    if let (Some(ours), Some(theirs)) = (
+        report.interpolation.as_mut(),
+        baseline.interpolation.as_ref(),
+    ) {
+        for (kinks, known) in [
+            (&mut ours.font_a, &theirs.font_a),
+            (&mut ours.font_b, &theirs.font_b),
+        ] {
+            let before = kinks.len();
+            kinks.retain(|kink| {
+                !known.iter().any(|k| {
+                    k.glyph == kink.glyph
+                        && k.location == kink.location
+                        && k.deviation == kink.deviation
+                })
+            });
+            count += before - kinks.len();
+        }
+    }
+
+    for location in report.locations.iter_mut() {
+        let Some(known) = baseline
+            .locations
+            .iter()
+            .find(|l| l.location == location.location)
+        else {
+            continue;
+        };
+        let before = location.glyphs.len();
+        location.glyphs.retain(|glyph| {
+            !known.glyphs.iter().any(|k| {
+                k.string == glyph.string
+                    && k.palette == glyph.palette
+                    && k.differing_pixels == glyph.differing_pixels
+            })
+        });
+        count += before - location.glyphs.len();
+
+        let before = location.metrics.len();
+        location.metrics.retain(|metric| {
+            !known.metrics.iter().any(|k| {
+                k.metric == metric.metric
+                    && k.glyph == metric.glyph
+                    && k.value_a == metric.value_a
+                    && k.value_b == metric.value_b
+            })
+        });
+        count += before - location.metrics.len();
+
+        for (script, differences) in location.words.iter_mut() {
+            let Some(known_differences) = known.words.get(script) else {
+                continue;
+            };
+            let before = differences.len();
+            differences.retain(|difference| {
+                !known_differences.iter().any(|k| {
+                    k.word == difference.word
+                        && k.lang == difference.lang
+                        && k.ot_features == difference.ot_features
+                        && k.palette == difference.palette
+                        && k.buffer_a == difference.buffer_a
+                        && k.buffer_b == difference.buffer_b
+                        && k.differing_pixels == difference.differing_pixels
+                })
+            });
+            count += before - differences.len();
+        }
+        location
+            .words
+            .retain(|_, differences| !differences.is_empty());
+    }
+    if report.locations.len() > 1 {
+        report.locations.retain(|l| l.is_some());
+    }
+
+    for dossier in report.dossiers.iter_mut() {
+        let Some(known) = baseline.dossiers.iter().find(|d| d.glyph == dossier.glyph) else {
+            continue;
+        };
+        let before = dossier.changes.len() + dossier.renders.len();
+        dossier
+            .changes
+            .retain(|change| !known.changes.contains(change));
+        dossier
+            .renders
+            .retain(|render| !known.renders.contains(render));
+        count += before - dossier.changes.len() - dossier.renders.len();
+    }
+    report
+        .dossiers
+        .retain(|dossier| !dossier.changes.is_empty() || !dossier.renders.is_empty());
+
+    if let (Some(ours), Some(theirs)) = (report.languages.as_mut(), baseline.languages.as_ref()) {
+        // Languages whose support changed in the same way are dropped
+        // altogether, so they no longer count towards the support levels.
+        ours.retain(|language, diff| {
+            let known = diff.level_a != diff.level_b
+                && theirs.get(language).is_some_and(|known| {
+                    known.level_a == diff.level_a && known.level_b == diff.level_b
+                });
+            if known {
+                count += 1;
+            }
+            !known
+        });
+    }
+
+    if count > 0 {
+        *report.suppressed.entry(rule.to_string()).or_default() += count;
+    }
+}
+
+/// Remove the leaves of `ours` which are identical in `theirs`
+///
+/// Returns the number of leaves removed, and prunes any objects left empty.
+fn subtract(ours: &mut Value, theirs: &Value) -> usize {
+    let (Some(ours), Some(theirs)) = (ours.as_object_mut(), theirs.as_object()) else {
+        return 0;
+    };
+    let mut count = 0;
+    let keys: Vec<String> = ours.keys().cloned().collect();
+    for key in keys {
+        let Some(their_value) = theirs.get(&key) else {
+            continue;
+        };
+        let our_value = ours.get_mut(&key).unwrap();
+        if our_value == their_value {
+            count += count_differences(our_value);
+            ours.remove(&key);
+        } else if our_value.is_object() {
+            count += subtract(our_value, their_value);
+            if !our_value.is_something() {
+                ours.remove(&key);
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn subtract_removes_identical_leaves() {
+        let mut ours = json!({
+            "head": {"fontRevision": [1.0, 1.1], "modified": ["a", "b"]},
+            "name": {"1": ["Foo", "Bar"]},
+            "OS/2": {"usWeightClass": [400, 500]},
+        });
+        let theirs = json!({
+            "head": {"fontRevision": [1.0, 1.1], "modified": ["a", "c"]},
+            "name": {"1": ["Foo", "Bar"]},
+        });
+        assert_eq!(subtract(&mut ours, &theirs), 2);
+        assert_eq!(
+            ours,
+            json!({
+                "head": {"modified": ["a", "b"]},
+                "OS/2": {"usWeightClass": [400, 500]},
+            })
+        );
+    }
+
+    #[test]
+    fn subtract_counts_every_leaf_of_a_removed_object() {
+        let mut ours = json!({"name": {"1": ["Foo", "Bar"], "2": ["Regular", "Bold"]}});
+        let theirs = ours.clone();
+        assert_eq!(subtract(&mut ours, &theirs), 2);
+        assert!(!ours.is_something());
+    }
+
+    #[test]
+    fn subtract_keeps_changed_values() {
+        // The same change to a different value is still news
+        let mut ours = json!({"kern": [-20, -30]});
+        assert_eq!(subtract(&mut ours, &json!({"kern": [-20, -25]})), 0);
+        assert_eq!(ours, json!({"kern": [-20, -30]}));
+        // So is anything when the baseline isn't an object
+        assert_eq!(subtract(&mut ours, &json!("error")), 0);
+        assert_eq!(subtract(&mut json!([1, 2]), &json!([1, 2])), 0);
+    }
+
+    #[test]
+    fn baseline_differences_are_suppressed() {
+        let mut report = Report {
+            tables: Some(json!({"head": {"fontRevision": [1.0, 1.1]}})),
+            kerns: Some(json!({"A/V": [-50, -60], "T/o": [-20, -22]})),
+            ..Default::default()
+        };
+        let baseline = Report {
+            tables: report.tables.clone(),
+            kerns: Some(json!({"A/V": [-50, -60]})),
+            ..Default::default()
+        };
+        apply_baseline(&mut report, &baseline, "baseline old.json");
+        assert!(report.tables.is_none());
+        assert_eq!(report.kerns, Some(json!({"T/o": [-20, -22]})));
+        assert_eq!(report.suppressed["baseline old.json"], 2);
+    }
+}
@@ -0,0 +1,211 @@
+//! Comparison of two directories of fonts
+//!
+//! Fonts are paired between the directories by filename; fonts left over are
+//! then paired by PostScript name, and finally by family and subfamily name.
+use crate::{
+    args::Cli,
+    compare_fonts, fail, gates, load_font,
+    reporters::{self, BatchReport, PairReport, PairedBy, Report, TestsRun},
+};
+use diffenator3_lib::{dfont::DFont, html::Tera, render::wordlists::CustomWordList};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::path::{Path, PathBuf};
+
+const FONT_EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+
+/// A font from each directory, and how they were paired
+type FontPair = (PathBuf, PathBuf, PairedBy);
+
+/// What we need to know about a font to pair it
+struct FontInfo {
+    path: PathBuf,
+    filename: String,
+    postscript_name: Option<String>,
+    family_and_style: (String, String),
+}
+
+impl FontInfo {
+    fn new(path: PathBuf) -> Result<Self, String> {
+        let binary = std::fs::read(&path).map_err(|e| e.to_string())?;
+        let font = DFont::new(&binary).map_err(|e| e.to_string())?;
+        Ok(FontInfo {
+            filename: path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            postscript_name: font.postscript_name(),
+            family_and_style: font.typographic_family_and_style(),
+            path,
+        })
+    }
+
+    fn key(&self, paired_by: PairedBy) -> Option<String> {
+        match paired_by {
+            PairedBy::Filename => Some(self.filename.clone()),
+            PairedBy::PostscriptName => self.postscript_name.clone(),
+            PairedBy::FamilyAndSubfamily => Some(format!(
+                "{} {}",
+                self.family_and_style.0, self.family_and_style.1
+            )),
+            // Only used when matching static fonts to a variable font
+            PairedBy::InstanceName | PairedBy::WeightAndWidth | PairedBy::Stat => None,
+        }
+    }
+}
+
+/// Find the font files in a directory, sorted by filename
+pub(crate) fn font_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| fail(format!("Couldn't read directory {}: {}", dir.display(), e)))
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| FONT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Find the fonts in a directory, sorted by filename
+fn fonts_in_directory(dir: &Path) -> Vec<FontInfo> {
+    font_paths(dir)
+        .into_iter()
+        .filter_map(|path| match FontInfo::new(path.clone()) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                log::warn!("Skipping {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Pair up fonts from two directories
+///
+/// Returns the pairs, followed by the unpaired fonts from each side.
+fn pair_fonts(
+    mut fonts_a: Vec<FontInfo>,
+    mut fonts_b: Vec<FontInfo>,
+) -> (Vec<FontPair>, Vec<PathBuf>, Vec<PathBuf>) {
+    let mut pairs = vec![];
+    for paired_by in [
+        PairedBy::Filename,
+        PairedBy::PostscriptName,
+        PairedBy::FamilyAndSubfamily,
+    ] {
+        let mut unpaired_a = vec![];
+        for font_a in fonts_a.into_iter() {
+            let matching = font_a.key(paired_by).and_then(|key| {
+                fonts_b
+                    .iter()
+                    .position(|font_b| font_b.key(paired_by).as_ref() == Some(&key))
+            });
+            if let Some(index) = matching {
+                let font_b = fonts_b.remove(index);
+                pairs.push((font_a.path, font_b.path, paired_by));
+            } else {
+                unpaired_a.push(font_a);
+            }
+        }
+        fonts_a = unpaired_a;
+    }
+    (
+        pairs,
+        fonts_a.into_iter().map(|f| f.path).collect(),
+        fonts_b.into_iter().map(|f| f.path).collect(),
+    )
+}
+
+/// Compare every pair of fonts in the two directories given on the command line
+pub fn run(cli: &Cli, tera: Option<Tera>, custom_wordlist_inputs: &[CustomWordList]) {
+    let (pairs, unpaired_a, unpaired_b) = pair_fonts(
+        fonts_in_directory(cli.font1()),
+        fonts_in_directory(cli.font2()),
+    );
+    log::info!(
+        "Comparing {} pairs of fonts ({} + {} unpaired)",
+        pairs.len(),
+        unpaired_a.len(),
+        unpaired_b.len()
+    );
+
+    let pairs: Vec<PairReport> = pairs
+        .into_par_iter()
+        .map(|(font_a, font_b, paired_by)| {
+            log::info!("Comparing {} with {}", font_a.display(), font_b.display());
+            let report = match (load_font(&font_a), load_font(&font_b)) {
+                (Ok(dfont_a), Ok(dfont_b)) => {
+                    compare_fonts(cli, dfont_a, dfont_b, custom_wordlist_inputs, None)
+                }
+                // The fonts were read when pairing them, but may have changed since
+                (a, b) => Report {
+                    errors: [a.err(), b.err()].into_iter().flatten().collect(),
+                    ..Default::default()
+                },
+            };
+            PairReport {
+                summary: report.summary(),
+                failures: gates::check(cli, &report),
+                font_a,
+                font_b,
+                paired_by,
+                location: None,
+                report,
+            }
+        })
+        .collect();
+    report(
+        cli,
+        tera,
+        BatchReport {
+            pairs,
+            unpaired_a,
+            unpaired_b,
+            ..Default::default()
+        },
+    );
+}
+
+/// Show the report of a batch comparison, and exit if any gating rule tripped
+pub(crate) fn report(cli: &Cli, tera: Option<Tera>, mut batch: BatchReport) {
+    let failures: Vec<String> = batch
+        .pairs
+        .iter()
+        .flat_map(|pair| {
+            pair.failures.iter().map(|failure| {
+                format!(
+                    "{} vs {}: {}",
+                    pair.font_a.display(),
+                    pair.font_b.display(),
+                    failure
+                )
+            })
+        })
+        .collect();
+    if cli.format.by_glyph {
+        for pair in batch.pairs.iter_mut() {
+            pair.report.group();
+        }
+    }
+
+    if cli.format.html {
+        reporters::html::report_batch(Path::new(&cli.format.output), &tera.unwrap(), &batch)
+            .unwrap_or_else(|e| fail(e));
+    } else if cli.format.json {
+        reporters::json::report_batch(&batch, cli.format.pretty);
+    } else if cli.format.markdown {
+        reporters::markdown::report_batch(&batch, &cli.format.markdown_options());
+    } else if cli.format.junit {
+        reporters::junit::report_batch(&batch, &TestsRun::from_cli(cli));
+    } else if cli.format.sarif {
+        reporters::sarif::report_batch(&batch, &TestsRun::from_cli(cli));
+    } else {
+        reporters::text::report_batch(batch, cli.format.succinct);
+    }
+
+    gates::exit_on_failure(failures);
+}
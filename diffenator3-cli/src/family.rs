@@ -0,0 +1,122 @@
+//! Comparison of a variable font with a family of static fonts
+//!
+//! Each static font in the directory is matched to a location in the
+//! variable font (see [match_static]) and compared with the variable font
+//! there. The results are reported in the same way as a comparison of two
+//! directories, with the variable font standing in for every font on its
+//! side.
+use crate::{
+    args::Cli,
+    batch::{self, font_paths},
+    compare_fonts, fail, gates, load_font,
+    reporters::{BatchReport, PairReport},
+};
+use diffenator3_lib::{
+    family::match_static, html::Tera, render::wordlists::CustomWordList, setting::Setting,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Compare the variable font given on the command line with each static font
+/// in the directory given on the command line
+pub fn run(cli: &Cli, tera: Option<Tera>, custom_wordlist_inputs: &[CustomWordList]) {
+    // Keep the order of the command line, so that "before" and "after" mean
+    // the same thing as they do for two fonts
+    let statics_first = cli.font1().is_dir();
+    let (directory, variable_path) = if statics_first {
+        (cli.font1(), cli.font2())
+    } else {
+        (cli.font2(), cli.font1())
+    };
+    let variable = load_font(variable_path).unwrap_or_else(|e| fail(e));
+    if !variable.is_variable() {
+        fail(format!(
+            "{} is not a variable font; to compare it with a directory of fonts, give another directory",
+            variable_path.display()
+        ));
+    }
+
+    let mut matched = vec![];
+    let mut unmatched = vec![];
+    for path in font_paths(directory) {
+        let static_font = match load_font(&path) {
+            Ok(font) => font,
+            Err(e) => {
+                log::warn!("Skipping {}", e);
+                continue;
+            }
+        };
+        match match_static(&variable, &static_font) {
+            Some((setting, matched_by)) => matched.push((path, static_font, setting, matched_by)),
+            None => unmatched.push(path),
+        }
+    }
+    let unmatched_instances = variable
+        .instances()
+        .into_iter()
+        .filter(|instance| {
+            !matched
+                .iter()
+                .any(|(_, _, setting, _)| *setting == Setting::Instance(instance.clone()))
+        })
+        .collect();
+    log::info!(
+        "Comparing {} static fonts with {} ({} unmatched)",
+        matched.len(),
+        variable_path.display(),
+        unmatched.len()
+    );
+
+    let pairs: Vec<PairReport> = matched
+        .into_par_iter()
+        .map(|(static_path, static_font, setting, matched_by)| {
+            log::info!(
+                "Comparing {} with {} at {}",
+                static_path.display(),
+                variable_path.display(),
+                setting
+            );
+            let (font_a, font_b, path_a, path_b) = if statics_first {
+                (
+                    static_font,
+                    variable.clone(),
+                    static_path,
+                    variable_path.to_path_buf(),
+                )
+            } else {
+                (
+                    variable.clone(),
+                    static_font,
+                    variable_path.to_path_buf(),
+                    static_path,
+                )
+            };
+            let report = compare_fonts(cli, font_a, font_b, custom_wordlist_inputs, Some(&setting));
+            PairReport {
+                summary: report.summary(),
+                failures: gates::check(cli, &report),
+                font_a: path_a,
+                font_b: path_b,
+                paired_by: matched_by.into(),
+                location: Some(setting.name()),
+                report,
+            }
+        })
+        .collect();
+
+    let (unpaired_a, unpaired_b) = if statics_first {
+        (unmatched, vec![])
+    } else {
+        (vec![], unmatched)
+    };
+    batch::report(
+        cli,
+        tera,
+        BatchReport {
+            pairs,
+            unpaired_a,
+            unpaired_b,
+            variable_font: Some(variable_path.to_path_buf()),
+            unmatched_instances,
+        },
+    );
+}
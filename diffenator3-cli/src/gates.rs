@@ -0,0 +1,337 @@
+//! Gating rules for continuous integration
+//!
+//! Each rule inspects the finished report and, if it finds a regression,
+//! returns a one-line description of what went wrong.
+use crate::{args::Cli, reporters::Report};
+use serde_json::Value;
+
+/// Exit code used when a gating rule fails
+pub const GATE_FAILURE_EXIT_CODE: i32 = 1;
+
+// Ordered from strongest to weakest support, so a larger index is a drop
+const SUPPORT_LEVELS: [&str; 5] = ["Complete", "Supported", "Incomplete", "Unsupported", "None"];
+
+/// Check the report against the gating rules requested on the command line
+///
+/// Returns a description of each rule which tripped.
+pub fn check(cli: &Cli, report: &Report) -> Vec<String> {
+    let mut failures = vec![];
+    if cli.fail_on_cmap_removals {
+        failures.extend(check_cmap_removals(report));
+    }
+    if let Some(threshold) = cli.fail_on_word_diff {
+        failures.extend(check_word_diff(report, threshold));
+    }
+    if let Some(threshold) = cli.fail_on_kern_change {
+        failures.extend(check_kern_change(report, threshold));
+    }
+    if cli.fail_on_language_drop {
+        failures.extend(check_language_drop(report));
+    }
+    if cli.fail_on_table_changes {
+        failures.extend(check_table_changes(report, &cli.allow_table_changes));
+    }
+    failures
+}
+
+/// Print any tripped rules and exit with [GATE_FAILURE_EXIT_CODE]
+pub fn exit_on_failure(failures: Vec<String>) {
+    if failures.is_empty() {
+        return;
+    }
+    for failure in failures {
+        eprintln!("FAILED {}", failure);
+    }
+    std::process::exit(GATE_FAILURE_EXIT_CODE);
+}
+
+fn check_cmap_removals(report: &Report) -> Option<String> {
+    let missing = &report.cmap_diff.as_ref()?.missing;
+    let first = missing.first()?;
+    Some(format!(
+        "cmap removals: {} encoded glyph(s) removed, including {}",
+        missing.len(),
+        first
+    ))
+}
+
+fn check_word_diff(report: &Report, threshold: usize) -> Option<String> {
+    let (location, script, worst) = report
+        .locations
+        .iter()
+        .flat_map(|loc| {
+            loc.words.iter().flat_map(move |(script, diffs)| {
+                diffs.iter().map(move |diff| (&loc.location, script, diff))
+            })
+        })
+        .max_by_key(|(_, _, diff)| diff.differing_pixels)?;
+    if worst.differing_pixels <= threshold {
+        return None;
+    }
+    Some(format!(
+        "word diff: '{}' ({}) at {} differs by {} pixels (limit {})",
+        worst.word, script, location, worst.differing_pixels, threshold
+    ))
+}
+
+fn check_kern_change(report: &Report, threshold: f64) -> Option<String> {
+    let kerns = report.kerns.as_ref()?.as_object()?;
+    if let Some(error) = kerns.get("error").and_then(|e| e.as_str()) {
+        return Some(format!("kern change: {}", error));
+    }
+    let (pair, change) = kerns
+        .iter()
+        .map(|(pair, diff)| (pair, kern_change(diff)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    if change <= threshold {
+        return None;
+    }
+    Some(format!(
+        "kern change: {} changed by {} units (limit {})",
+        pair, change, threshold
+    ))
+}
+
+/// The largest change in any numeric value within a kerning diff
+fn kern_change(diff: &Value) -> f64 {
+    match diff {
+        Value::Array(lr) if lr.len() == 2 => value_change(&lr[0], &lr[1]),
+        Value::Object(fields) => fields.values().map(kern_change).fold(0.0, f64::max),
+        _ => 0.0,
+    }
+}
+
+// Missing values count as zero, so an added or removed pair is as large
+// as its biggest value.
+fn value_change(a: &Value, b: &Value) -> f64 {
+    match (a, b) {
+        (Value::Object(l), Value::Object(r)) => l
+            .keys()
+            .chain(r.keys())
+            .map(|key| {
+                value_change(
+                    l.get(key).unwrap_or(&Value::Null),
+                    r.get(key).unwrap_or(&Value::Null),
+                )
+            })
+            .fold(0.0, f64::max),
+        (Value::Object(_), _) => value_change(a, &Value::Object(Default::default())),
+        (_, Value::Object(_)) => value_change(&Value::Object(Default::default()), b),
+        _ => (a.as_f64().unwrap_or(0.0) - b.as_f64().unwrap_or(0.0)).abs(),
+    }
+}
+
+fn check_language_drop(report: &Report) -> Option<String> {
+    let rank = |level: &str| SUPPORT_LEVELS.iter().position(|l| *l == level);
+    let dropped: Vec<_> = report
+        .languages
+        .as_ref()?
+        .iter()
+        .filter(
+            |(_, diff)| match (rank(&diff.level_a), rank(&diff.level_b)) {
+                (Some(a), Some(b)) => b > a,
+                _ => false,
+            },
+        )
+        .collect();
+    let (language, diff) = dropped.first()?;
+    Some(format!(
+        "language drop: {} language(s) lost support, including {} ({} => {})",
+        dropped.len(),
+        language,
+        diff.level_a,
+        diff.level_b
+    ))
+}
+
+fn check_table_changes(report: &Report, allowed: &[String]) -> Option<String> {
+    let changed: Vec<&String> = report
+        .tables
+        .as_ref()?
+        .as_object()?
+        .keys()
+        .filter(|table| !allowed.contains(table))
+        .collect();
+    if changed.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "table changes: {} changed outside the allow-list",
+        changed
+            .iter()
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diffenator3_lib::{
+        report::LocationResult,
+        structs::{CmapDiff, EncodedGlyph},
+    };
+    use serde_json::json;
+
+    fn with_languages(levels: &[(&str, &str, &str)]) -> Report {
+        let languages = levels
+            .iter()
+            .map(|(language, a, b)| {
+                let diff = json!({
+                    "level_a": a, "score_a": 1.0, "fixes_a": 0,
+                    "level_b": b, "score_b": 1.0, "fixes_b": 0,
+                });
+                (language.to_string(), serde_json::from_value(diff).unwrap())
+            })
+            .collect();
+        Report {
+            languages: Some(languages),
+            ..Default::default()
+        }
+    }
+
+    fn with_word_diff(differing_pixels: usize) -> Report {
+        let diff = json!({"word": "hamburgefonstiv", "buffer_a": "", "differing_pixels": differing_pixels});
+        let location = LocationResult {
+            location: "Default".to_string(),
+            words: [(
+                "Latin".to_string(),
+                vec![serde_json::from_value(diff).unwrap()],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        Report {
+            locations: vec![location],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn support_levels_drop_from_strongest_to_weakest() {
+        for (i, stronger) in SUPPORT_LEVELS.iter().enumerate() {
+            for weaker in SUPPORT_LEVELS.iter().skip(i + 1) {
+                let report = with_languages(&[("en_Latn", stronger, weaker)]);
+                assert!(check_language_drop(&report).is_some());
+                let report = with_languages(&[("en_Latn", weaker, stronger)]);
+                assert!(check_language_drop(&report).is_none());
+            }
+        }
+        let report = with_languages(&[("en_Latn", "Supported", "Supported")]);
+        assert!(check_language_drop(&report).is_none());
+    }
+
+    #[test]
+    fn unknown_support_levels_never_drop() {
+        let report = with_languages(&[
+            ("en_Latn", "Complete", "Indeterminate"),
+            ("fr_Latn", "Indeterminate", "None"),
+        ]);
+        assert!(check_language_drop(&report).is_none());
+    }
+
+    #[test]
+    fn language_drop_counts_every_language() {
+        let report = with_languages(&[
+            ("en_Latn", "Complete", "None"),
+            ("fr_Latn", "Complete", "Complete"),
+            ("de_Latn", "Supported", "Incomplete"),
+        ]);
+        assert_eq!(
+            check_language_drop(&report).unwrap(),
+            "language drop: 2 language(s) lost support, including de_Latn (Supported => Incomplete)"
+        );
+    }
+
+    #[test]
+    fn value_change_measures_the_largest_field() {
+        assert_eq!(value_change(&json!(-20), &json!(-25)), 5.0);
+        assert_eq!(
+            value_change(&json!({"x": 10, "y": 3}), &json!({"x": 12, "y": -3})),
+            6.0
+        );
+        // Added and removed values count from zero
+        assert_eq!(value_change(&Value::Null, &json!(-30)), 30.0);
+        assert_eq!(value_change(&json!({"x": 40}), &Value::Null), 40.0);
+        assert_eq!(value_change(&json!({"x": 4}), &json!({"y": -7})), 7.0);
+        assert_eq!(value_change(&json!("a"), &json!("b")), 0.0);
+    }
+
+    #[test]
+    fn kern_change_looks_inside_each_pair() {
+        assert_eq!(kern_change(&json!([-20, -50])), 30.0);
+        assert_eq!(
+            kern_change(&json!({"DFLT/dflt": [-20, -22], "latn/TRK": [{"x": 5}, null]})),
+            5.0
+        );
+        assert_eq!(kern_change(&json!(-20)), 0.0);
+    }
+
+    #[test]
+    fn kern_change_fails_above_the_threshold() {
+        let report = Report {
+            kerns: Some(json!({"A/V": [-50, -60], "T/o": [-20, -22]})),
+            ..Default::default()
+        };
+        assert!(check_kern_change(&report, 10.0).is_none());
+        assert_eq!(
+            check_kern_change(&report, 9.5).unwrap(),
+            "kern change: A/V changed by 10 units (limit 9.5)"
+        );
+        let report = Report {
+            kerns: Some(json!({"error": "Couldn't read GPOS"})),
+            ..Default::default()
+        };
+        assert!(check_kern_change(&report, 100.0).is_some());
+        assert!(check_kern_change(&Report::default(), 0.0).is_none());
+    }
+
+    #[test]
+    fn word_diff_fails_above_the_threshold() {
+        let report = with_word_diff(50);
+        assert!(check_word_diff(&report, 50).is_none());
+        assert_eq!(
+            check_word_diff(&report, 49).unwrap(),
+            "word diff: 'hamburgefonstiv' (Latin) at Default differs by 50 pixels (limit 49)"
+        );
+        assert!(check_word_diff(&Report::default(), 0).is_none());
+    }
+
+    #[test]
+    fn cmap_removals_fail() {
+        let glyph = |string: &str| EncodedGlyph {
+            string: string.to_string(),
+            name: None,
+        };
+        let mut report = Report {
+            cmap_diff: Some(CmapDiff {
+                missing: vec![],
+                new: vec![glyph("a")],
+            }),
+            ..Default::default()
+        };
+        assert!(check_cmap_removals(&report).is_none());
+        report.cmap_diff.as_mut().unwrap().missing = vec![glyph("b"), glyph("c")];
+        assert!(check_cmap_removals(&report)
+            .unwrap()
+            .starts_with("cmap removals: 2 encoded glyph(s) removed"));
+    }
+
+    #[test]
+    fn table_changes_fail_outside_the_allow_list() {
+        let report = Report {
+            tables: Some(json!({"head": {}, "name": {}})),
+            ..Default::default()
+        };
+        let allowed = |tables: &[&str]| tables.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert!(check_table_changes(&report, &allowed(&["head", "name"])).is_none());
+        assert_eq!(
+            check_table_changes(&report, &allowed(&["head"])).unwrap(),
+            "table changes: name changed outside the allow-list"
+        );
+        assert!(check_table_changes(&report, &[])
+            .unwrap()
+            .contains("head, name"));
+    }
+}
@@ -0,0 +1,336 @@
+//! Differences which should be left out of the report
+//!
+//! Once a change has been reviewed and accepted, it can be listed in an
+//! ignore file (or the `[ignore]` table of the configuration file) so that
+//! it is not reported again:
+//!
+//! ```toml
+//! # Table diff paths; a bare table tag ignores the whole table, `*` matches any key
+//! tables = ["head.modified", "OS/2.achVendID", "hmtx.*"]
+//! # Glyphs, by character, codepoint, Unicode name or glyph name
+//! glyphs = ["g", "U+0067", "LATIN SMALL LETTER G", "g.alt"]
+//! # Words, by regular expression
+//! words = ["^ffi", "ﷺ"]
+//! # Kerning pairs
+//! kerns = ["A/V", "T/o"]
+//! ```
+//!
+//! Each suppressed difference is counted against the rule which matched it.
+use crate::reporters::LocationResult;
+use diffenator3_lib::dfont::DFont;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use skrifa::MetadataProvider;
+use std::{collections::BTreeMap, path::Path};
+use ttj::{jsondiff::Substantial, namemap::NameMap};
+
+/// Number of differences suppressed by each rule
+pub type Suppressed = BTreeMap<String, usize>;
+
+/// Rules from an ignore file or the `[ignore]` section of the configuration file
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreRules {
+    /// Paths in the table diff whose differences should not be reported
+    #[serde(default)]
+    pub tables: Vec<String>,
+    /// Glyphs whose differences should not be reported
+    #[serde(default)]
+    pub glyphs: Vec<String>,
+    /// Regular expressions matching words whose differences should not be reported
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Kerning pairs (`left/right`) whose differences should not be reported
+    #[serde(default)]
+    pub kerns: Vec<String>,
+    #[serde(skip)]
+    word_regexes: Vec<Regex>,
+}
+
+impl IgnoreRules {
+    /// Read rules from an ignore file
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))
+    }
+
+    /// Add the rules from another set
+    pub fn extend(&mut self, other: IgnoreRules) {
+        self.tables.extend(other.tables);
+        self.glyphs.extend(other.glyphs);
+        self.words.extend(other.words);
+        self.kerns.extend(other.kerns);
+    }
+
+    /// Compile the word rules; this must be called before applying them
+    pub fn compile(&mut self) -> Result<(), String> {
+        self.word_regexes = self
+            .words
+            .iter()
+            .map(|word| Regex::new(word).map_err(|e| format!("Bad word rule '{}': {}", word, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// Remove ignored paths from a table diff
+    pub fn apply_to_tables(&self, table_diff: &mut Value, suppressed: &mut Suppressed) {
+        for rule in self.tables.iter() {
+            let path: Vec<&str> = rule.split('.').collect();
+            let count = remove_path(table_diff, &path);
+            if count > 0 {
+                *suppressed.entry(format!("table {}", rule)).or_default() += count;
+            }
+        }
+    }
+
+    /// Remove ignored pairs from a kerning diff
+    pub fn apply_to_kerns(&self, kern_diff: &mut Value, suppressed: &mut Suppressed) {
+        let Some(kerns) = kern_diff.as_object_mut() else {
+            return;
+        };
+        for rule in self.kerns.iter() {
+            if kerns.remove(rule).is_some() {
+                *suppressed.entry(format!("kern {}", rule)).or_default() += 1;
+            }
+        }
+    }
+
+    /// Remove ignored glyphs and words from the results at a location
+    ///
+    /// Glyph names are looked up in `font`.
+    pub fn apply_to_location(
+        &self,
+        location: &mut LocationResult,
+        font: &DFont,
+        suppressed: &mut Suppressed,
+    ) {
+        if !self.glyphs.is_empty() && !location.glyphs.is_empty() {
+            let fontref = font.fontref();
+            let charmap = fontref.charmap();
+            let names = NameMap::new(&fontref);
+            location.glyphs.retain(|glyph| {
+                let glyph_name = glyph
+                    .string
+                    .chars()
+                    .next()
+                    .and_then(|c| charmap.map(c))
+                    .map(|gid| names.get(gid));
+                let rule = self.glyphs.iter().find(|rule| {
+                    **rule == glyph.string
+                        || rule.eq_ignore_ascii_case(&glyph.unicode)
+                        || rule.eq_ignore_ascii_case(&glyph.name)
+                        || Some(*rule) == glyph_name.as_ref()
+                });
+                if let Some(rule) = rule {
+                    *suppressed.entry(format!("glyph {}", rule)).or_default() += 1;
+                }
+                rule.is_none()
+            });
+        }
+
+        for differences in location.words.values_mut() {
+            differences.retain(|difference| {
+                let rule = self
+                    .word_regexes
+                    .iter()
+                    .find(|re| re.is_match(&difference.word));
+                if let Some(rule) = rule {
+                    *suppressed
+                        .entry(format!("word /{}/", rule.as_str()))
+                        .or_default() += 1;
+                }
+                rule.is_none()
+            });
+        }
+        location
+            .words
+            .retain(|_, differences| !differences.is_empty());
+    }
+}
+
+/// Remove the value at `path` (where `*` matches any key) from a diff
+///
+/// Returns the number of differences removed, and prunes any objects
+/// left empty.
+fn remove_path(diff: &mut Value, path: &[&str]) -> usize {
+    let (Some(key), Some(object)) = (path.first(), diff.as_object_mut()) else {
+        return 0;
+    };
+    let keys: Vec<String> = if *key == "*" {
+        object.keys().cloned().collect()
+    } else if object.contains_key(*key) {
+        vec![key.to_string()]
+    } else if object.contains_key(&snake_case(key)) {
+        // Allow field names as spelt in the OpenType spec, e.g. `achVendID`
+        vec![snake_case(key)]
+    } else {
+        vec![]
+    };
+    let mut count = 0;
+    for key in keys {
+        if path.len() == 1 {
+            count += object.remove(&key).map_or(0, |v| count_differences(&v));
+        } else if let Some(child) = object.get_mut(&key) {
+            count += remove_path(child, &path[1..]);
+            if !child.is_something() {
+                object.remove(&key);
+            }
+        }
+    }
+    count
+}
+
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            result.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+/// Count the leaf differences (`[before, after]` pairs) in a diff
+pub fn count_differences(diff: &Value) -> usize {
+    match diff {
+        Value::Object(fields) => fields.values().map(count_differences).sum(),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(toml: &str) -> IgnoreRules {
+        let mut rules: IgnoreRules = toml::from_str(toml).unwrap();
+        rules.compile().unwrap();
+        rules
+    }
+
+    fn words(words: &[&str]) -> LocationResult {
+        let differences = words
+            .iter()
+            .map(|word| {
+                serde_json::from_value(
+                    json!({"word": word, "buffer_a": "", "differing_pixels": 10}),
+                )
+                .unwrap()
+            })
+            .collect();
+        LocationResult {
+            words: [("Latin".to_string(), differences)].into(),
+            ..Default::default()
+        }
+    }
+
+    fn remaining_words(location: &LocationResult) -> Vec<&str> {
+        location
+            .words
+            .values()
+            .flatten()
+            .map(|difference| difference.word.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn snake_case_follows_the_table_diff() {
+        assert_eq!(snake_case("achVendID"), "ach_vend_id");
+        assert_eq!(snake_case("usWeightClass"), "us_weight_class");
+        assert_eq!(snake_case("yStrikeoutSize"), "y_strikeout_size");
+        assert_eq!(snake_case("ulUnicodeRange1"), "ul_unicode_range1");
+        assert_eq!(snake_case("modified"), "modified");
+    }
+
+    #[test]
+    fn remove_path_removes_and_prunes() {
+        let mut diff = json!({
+            "head": {"modified": [1, 2], "fontRevision": [1.0, 1.1]},
+            "OS/2": {"ach_vend_id": ["NONE", "GOOG"]},
+        });
+        assert_eq!(remove_path(&mut diff, &["head", "modified"]), 1);
+        assert_eq!(remove_path(&mut diff, &["head", "modified"]), 0);
+        // Field names may be given as in the OpenType spec
+        assert_eq!(remove_path(&mut diff, &["OS/2", "achVendID"]), 1);
+        assert_eq!(diff, json!({"head": {"fontRevision": [1.0, 1.1]}}));
+        assert_eq!(remove_path(&mut diff, &["head"]), 1);
+        assert_eq!(diff, json!({}));
+    }
+
+    #[test]
+    fn remove_path_matches_any_key_with_a_star() {
+        let mut diff = json!({
+            "hmtx": {"a": {"advance": [500, 510]}, "b": {"advance": [600, 610], "lsb": [10, 20]}},
+        });
+        assert_eq!(remove_path(&mut diff, &["hmtx", "*", "lsb"]), 1);
+        assert_eq!(remove_path(&mut diff, &["hmtx", "*"]), 2);
+        assert_eq!(diff, json!({}));
+        assert_eq!(remove_path(&mut json!([1, 2]), &["*"]), 0);
+    }
+
+    #[test]
+    fn table_and_kern_rules_are_counted() {
+        let rules = rules(
+            r#"
+            tables = ["head.modified", "OS/2.achVendID", "GDEF"]
+            kerns = ["A/V"]
+            "#,
+        );
+        let mut suppressed = Suppressed::new();
+        let mut tables = json!({
+            "head": {"modified": [1, 2]},
+            "OS/2": {"ach_vend_id": ["NONE", "GOOG"]},
+            "name": {"1": ["Foo", "Bar"]},
+        });
+        rules.apply_to_tables(&mut tables, &mut suppressed);
+        assert_eq!(tables, json!({"name": {"1": ["Foo", "Bar"]}}));
+        let mut kerns = json!({"A/V": [-50, -60], "T/o": [-20, -22]});
+        rules.apply_to_kerns(&mut kerns, &mut suppressed);
+        assert_eq!(kerns, json!({"T/o": [-20, -22]}));
+        assert_eq!(
+            suppressed,
+            Suppressed::from([
+                ("table head.modified".to_string(), 1),
+                ("table OS/2.achVendID".to_string(), 1),
+                ("kern A/V".to_string(), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn word_rules_are_regular_expressions() {
+        let data = std::fs::read("../diffenator3-lib/test-data/Nabla-subset.ttf").unwrap();
+        let font = DFont::new(&data).unwrap();
+        let mut suppressed = Suppressed::new();
+
+        // Unanchored, a rule matches anywhere in a word...
+        let mut location = words(&["a.b", "axb", "xa.by", "ab"]);
+        rules(r#"words = ["a.b"]"#).apply_to_location(&mut location, &font, &mut suppressed);
+        assert_eq!(remaining_words(&location), vec!["ab"]);
+        assert_eq!(suppressed["word /a.b/"], 3);
+
+        // ...so an exact word must be anchored and escaped
+        let mut location = words(&["a.b", "axb", "xa.by", "ab"]);
+        rules(r#"words = ['^a\.b$']"#).apply_to_location(&mut location, &font, &mut suppressed);
+        assert_eq!(remaining_words(&location), vec!["axb", "xa.by", "ab"]);
+
+        let mut location = words(&["ab"]);
+        rules(r#"words = ["^ab$"]"#).apply_to_location(&mut location, &font, &mut suppressed);
+        assert!(location.words.is_empty());
+    }
+
+    #[test]
+    fn bad_word_rules_are_errors() {
+        let mut rules: IgnoreRules = toml::from_str(r#"words = ["(unclosed"]"#).unwrap();
+        assert!(rules
+            .compile()
+            .unwrap_err()
+            .starts_with("Bad word rule '(unclosed'"));
+    }
+}
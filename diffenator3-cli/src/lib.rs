@@ -1,4 +1,2 @@
 #[cfg(feature = "typescript")]
-pub mod languages;
-#[cfg(feature = "typescript")]
 pub mod reporters;
@@ -6,35 +6,35 @@
 ///
 /// Additionally, it can compare kerning table information and binary tables.
 mod args;
-mod languages;
+mod baseline;
+mod batch;
+mod family;
+mod gates;
+mod ignore;
+mod progress;
+mod render_report;
 mod reporters;
 use crate::{
-    args::Cli,
-    reporters::{LocationResult, Report},
+    args::{Cli, Command},
+    baseline::apply_baseline,
+    progress::ProgressReporter,
+    reporters::{Report, TestsRun},
 };
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory};
 use diffenator3_lib::{
+    comparison::Comparison,
     dfont::DFont,
+    dossier::glyph_dossiers,
     html::template_engine,
-    render::{
-        encodedglyphs::{modified_encoded_glyphs, CmapDiff},
-        test_font_words,
-    },
-    setting::{parse_location, Setting},
-    WordList,
+    render::wordlists::{parse_custom_wordlist, CustomWordList},
+    setting::{parse_axis_mapping, parse_location, parse_setting, AxisMap, Setting},
 };
 use env_logger::Env;
-use indexmap::IndexSet;
-use itertools::Itertools;
-use skrifa::{MetadataProvider, Tag};
-use std::{
-    collections::{HashMap, HashSet},
-    path::Path,
-};
-use ttj::{jsondiff::Substantial, kern_diff, table_diff};
+use std::path::Path;
+use ttj::jsondiff::Substantial;
 
 fn main() {
-    let mut cli = Cli::parse();
+    let mut cli = Cli::parse_with_config();
     env_logger::Builder::from_env(Env::default().default_filter_or(if cli.quiet {
         "error"
     } else {
@@ -42,6 +42,15 @@ fn main() {
     }))
     .init();
 
+    if let Some(Command::RenderReport(args)) = cli.command.as_ref() {
+        render_report::run(args);
+        return;
+    }
+    // Options given before a subcommand's name make it a font path instead
+    if cli.font1() == Path::new("render-report") && !cli.font1().exists() {
+        fail("options for render-report go after it: diffenator3 render-report [OPTIONS] <REPORT>");
+    }
+
     if let Some(threads) = cli.jobs {
         rayon::ThreadPoolBuilder::new()
             .num_threads(threads)
@@ -49,229 +58,239 @@ fn main() {
             .expect("Could not set thread count");
     }
 
-    let font_binary_a = std::fs::read(&cli.font1).expect("Couldn't open file");
-    let font_binary_b = std::fs::read(&cli.font2).expect("Couldn't open file");
+    let tera = cli.format.html.then(|| {
+        template_engine(cli.format.templates.as_ref(), cli.format.update_templates)
+            .unwrap_or_else(|e| fail(e))
+    });
 
-    let tera = cli
-        .html
-        .then(|| template_engine(cli.templates.as_ref(), cli.update_templates));
-
-    let mut font_a = DFont::new(&font_binary_a);
-    let mut font_b = DFont::new(&font_binary_b);
-
-    let mut result = Report::default();
-
-    let custom_wordlist_inputs: Vec<WordList> = cli
+    let custom_wordlist_inputs: Vec<CustomWordList> = cli
         .custom_wordlists
         .iter()
-        .map(|path| {
-            let data = std::fs::read_to_string(path).expect("Couldn't read custom wordlist");
+        .flat_map(|path| {
+            let data = std::fs::read_to_string(path).unwrap_or_else(|e| {
+                fail(format!(
+                    "Couldn't read custom wordlist {}: {}",
+                    path.display(),
+                    e
+                ))
+            });
             let name: String = path
                 .file_name()
                 .and_then(|s| s.to_str())
                 .unwrap_or("custom")
                 .to_string();
-            WordList::define(name, data.lines().map(String::from))
+            parse_custom_wordlist(&name, &data).unwrap_or_else(|e| {
+                fail(format!(
+                    "Couldn't parse custom wordlist {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
         })
         .collect();
 
-    // Location-independent tests
-    if cli.tables {
-        log::info!("Diffing binary tables");
-        let table_diff = table_diff(
-            &font_a.fontref(),
-            &font_b.fontref(),
-            cli.max_changes,
-            cli.no_match,
-        );
-        if table_diff.is_something() {
-            result.tables = Some(table_diff);
-        }
+    // If there are no instances, locations, masters, STAT locations,
+    // cross-products, sampling or per-font locations, we set instances to "*"
+    if cli.instance.is_empty()
+        && cli.location.is_empty()
+        && !cli.masters
+        && !cli.stat
+        && !cli.cross_product
+        && cli.adaptive.is_none()
+        && cli.location_a.is_none()
+    {
+        cli.instance.push("*".to_string());
     }
-    if cli.kerns {
-        log::info!("Diffing kerning");
-        let kern_diff = kern_diff(
-            &font_a.fontref(),
-            &font_b.fontref(),
-            cli.max_changes,
-            cli.no_match,
-        );
-        if kern_diff.is_something() {
-            result.kerns = Some(kern_diff);
+
+    if cli.font1().is_dir() || cli.font2().is_dir() {
+        if cli.save_snapshot.is_some() || cli.baseline.is_some() {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--save-snapshot and --baseline can only be used to compare two fonts",
+                )
+                .exit();
         }
-    }
-    if cli.glyphs {
-        result.cmap_diff = Some(CmapDiff::new(&font_a, &font_b));
-    }
-    if cli.languages {
-        log::info!("Diffing language support");
-        result.languages = Some(languages::diff_languages(&font_a, &font_b));
+        if cli.font1().is_dir() && cli.font2().is_dir() {
+            batch::run(&cli, tera, &custom_wordlist_inputs);
+        } else {
+            family::run(&cli, tera, &custom_wordlist_inputs);
+        }
+        return;
     }
 
-    if cli.glyphs || cli.words {
-        // If there are no instances, location or cross-products, we set instances to "*"
-        if cli.instance.is_empty() && cli.location.is_empty() && !cli.masters && !cli.cross_product
-        {
-            cli.instance.push("*".to_string());
-        }
-        // Location-specific tests
-        let settings: Vec<Setting> = generate_settings(&cli, &font_a, &font_b);
+    let font_a = load_font(cli.font1()).unwrap_or_else(|e| fail(e));
+    let font_b = load_font(cli.font2()).unwrap_or_else(|e| fail(e));
+    let mut result = compare_fonts(&cli, font_a, font_b, &custom_wordlist_inputs, None);
+    result.font_a = Some(cli.font1().display().to_string());
+    result.font_b = Some(cli.font2().display().to_string());
 
-        result.locations = settings
-            .into_iter()
-            .map(|setting| {
-                log::info!("Testing {}", setting.name());
-                if let Err(e) = setting.set_on_fonts(&mut font_a, &mut font_b) {
-                    LocationResult::from_error(setting.name(), e)
-                } else {
-                    test_at_location(
-                        &font_a,
-                        setting.name(),
-                        &cli,
-                        &font_b,
-                        &custom_wordlist_inputs,
-                    )
-                }
-            })
-            .collect();
+    if let Some(path) = cli.save_snapshot.as_ref() {
+        result.save(path).unwrap_or_else(|e| fail(e));
+    }
+    if let Some(path) = cli.baseline.as_ref() {
+        let baseline = Report::load(path).unwrap_or_else(|e| fail(e));
+        apply_baseline(
+            &mut result,
+            &baseline,
+            &format!("baseline {}", path.display()),
+        );
+    }
 
-        // If there's more than one, filter out the boring ones
-        if result.locations.len() > 1 {
-            result.locations.retain(|l| l.is_some());
-        }
+    let failures = gates::check(&cli, &result);
+    if cli.format.by_glyph {
+        result.group();
     }
+
     // Report back
-    if cli.html {
+    if cli.format.html {
         reporters::html::report(
-            &cli.font1,
-            &cli.font2,
-            Path::new(&cli.output),
-            tera.unwrap(),
+            cli.font1(),
+            cli.font2(),
+            Path::new(&cli.format.output),
+            &tera.unwrap(),
             &result,
-        );
-    } else if cli.json {
-        reporters::json::report(result, cli.pretty);
+        )
+        .unwrap_or_else(|e| fail(e));
+    } else if cli.format.json {
+        reporters::json::report(result, cli.format.pretty);
+    } else if cli.format.markdown {
+        reporters::markdown::report(&result, &cli.format.markdown_options());
+    } else if cli.format.junit {
+        reporters::junit::report(&result, &TestsRun::from_cli(&cli));
+    } else if cli.format.sarif {
+        reporters::sarif::report(&result, cli.font2(), &TestsRun::from_cli(&cli));
     } else {
-        reporters::text::report(result, cli.succinct);
+        reporters::text::report(result, cli.format.succinct);
     }
+
+    gates::exit_on_failure(failures);
 }
 
-fn test_at_location(
-    font_a: &DFont,
-    loc_name: String,
-    cli: &Cli,
-    font_b: &DFont,
-    wordlists: &[WordList],
-) -> LocationResult {
-    let mut this_location_value = LocationResult::default();
-    let loc_coords: HashMap<String, f32> = font_a
-        .location
-        .iter()
-        .map(|v| (v.selector.to_string(), v.value))
-        .collect();
-    this_location_value.location = loc_name;
-    this_location_value.coords = loc_coords;
+/// Read a font file
+fn load_font(path: &Path) -> Result<DFont, String> {
+    let binary = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    DFont::new(&binary).map_err(|e| format!("{}: {}", path.display(), e))
+}
 
-    if cli.glyphs {
-        this_location_value.glyphs = modified_encoded_glyphs(font_a, font_b);
-    }
-    if cli.words {
-        this_location_value.words = test_font_words(font_a, font_b, wordlists);
-    }
-    this_location_value
+/// Exit with an error message, in the same style as clap's own errors
+fn fail(message: impl std::fmt::Display) -> ! {
+    Cli::command().error(ErrorKind::Io, message).exit()
 }
 
-// Parse the various location-relation options and return a list of "settings", which
-// are locations to set the fonts to.
-fn generate_settings(args: &Cli, font_a: &DFont, font_b: &DFont) -> Vec<Setting> {
-    let mut settings = vec![];
-    for instance in &args.instance {
-        if instance == "*" {
-            // Add the union of instances from both fonts
-            let mut instances: IndexSet<String> = font_a.instances().into_iter().collect();
-            instances.extend(font_b.instances().into_iter());
-            settings.extend(instances.into_iter().map(Setting::from_instance));
-        } else {
-            settings.push(Setting::from_instance(instance.clone()));
-        }
-    }
+/// Parse a location or instance name given for one font
+fn parse_setting_arg(setting: &str) -> Setting {
+    parse_setting(setting).unwrap_or_else(|e| invalid_location(setting, e))
+}
 
-    for location in &args.location {
-        let loc = parse_location(location).expect("Couldn't parse location");
-        settings.push(Setting::from_setting(loc));
+fn invalid_location(location: &str, error: String) -> ! {
+    Cli::command()
+        .error(
+            ErrorKind::ValueValidation,
+            format!("Couldn't parse location {}: {}", location, error),
+        )
+        .exit()
+}
+
+/// The axis map given on the command line
+fn axis_map(cli: &Cli) -> AxisMap {
+    AxisMap {
+        mappings: cli
+            .map_axis
+            .iter()
+            .map(|mapping| {
+                parse_axis_mapping(mapping)
+                    .unwrap_or_else(|e| Cli::command().error(ErrorKind::ValueValidation, e).exit())
+            })
+            .collect(),
+        pins: cli
+            .pin_axis
+            .iter()
+            .flat_map(|pin| parse_location(pin).unwrap_or_else(|e| invalid_location(pin, e)))
+            .collect(),
     }
+}
 
-    if args.masters {
-        if let Ok(masters) = font_a.masters() {
-            settings.push(Setting::Default);
-            for master in masters.into_iter() {
-                let setting = Setting::from_setting(master);
-                settings.push(setting);
+/// Run all the requested tests on a pair of fonts
+///
+/// The fonts are compared at the locations given on the command line, or
+/// only at `setting` if there is one.
+fn compare_fonts(
+    cli: &Cli,
+    font_a: DFont,
+    font_b: DFont,
+    custom_wordlist_inputs: &[CustomWordList],
+    setting: Option<&Setting>,
+) -> Report {
+    let progress = ProgressReporter::default();
+    let mut comparison = Comparison::new(font_a, font_b)
+        .tables(cli.tables)
+        .kerns(cli.kerns)
+        .glyphs(cli.glyphs)
+        .words(cli.words)
+        .metrics(cli.metrics)
+        .languages(cli.languages)
+        .interpolation(cli.interpolation)
+        .words_by_language(cli.words_by_language)
+        .max_changes(cli.max_changes)
+        .no_match(cli.no_match)
+        .custom_wordlists(custom_wordlist_inputs)
+        .thresholds(cli.thresholds.clone())
+        .axis_map(axis_map(cli))
+        .on_progress(|p| progress.report(p));
+    match setting {
+        Some(setting) => comparison = comparison.setting(setting.clone()),
+        None => {
+            comparison = comparison.masters(cli.masters).stat(cli.stat);
+            for instance in &cli.instance {
+                comparison = comparison.instance(instance);
+            }
+            for location in &cli.location {
+                let location =
+                    parse_location(location).unwrap_or_else(|e| invalid_location(location, e));
+                comparison = comparison.location(location);
+            }
+            if cli.cross_product {
+                comparison = comparison.cross_product(cli.splits);
+            }
+            if let Some(budget) = cli.adaptive {
+                comparison = comparison.adaptive(budget);
+            }
+            if let (Some(location_a), Some(location_b)) = (&cli.location_a, &cli.location_b) {
+                comparison = comparison.setting(Setting::Split(
+                    Box::new(parse_setting_arg(location_a)),
+                    Box::new(parse_setting_arg(location_b)),
+                ));
             }
         }
     }
 
-    if args.cross_product {
-        let mut axes: HashSet<Tag> = font_a.fontref().axes().iter().map(|a| a.tag()).collect();
-        axes.extend(font_b.fontref().axes().iter().map(|a| a.tag()));
-        let axes_min_max = axes
-            .iter()
-            .map(|tag| {
-                let a = font_a.fontref().axes().iter().find(|a| a.tag() == *tag);
-                let b = font_b.fontref().axes().iter().find(|a| a.tag() == *tag);
-                let a_extents = a.map(|a| (a.min_value(), a.default_value(), a.max_value()));
-                let b_extents = b.map(|a| (a.min_value(), a.default_value(), a.max_value()));
-                match (a_extents, b_extents) {
-                    (Some((a_min, a_default, a_max)), Some((b_min, _b_default, b_max))) => {
-                        (*tag, (a_min.min(b_min), a_default, a_max.max(b_max)))
-                    }
-                    (Some((a_min, a_default, a_max)), None) => (*tag, (a_min, a_default, a_max)),
-                    (None, Some((b_min, b_default, b_max))) => (*tag, (b_min, b_default, b_max)),
-                    (None, None) => panic!("Couldn't find axis"),
-                }
-            })
-            .collect::<HashMap<Tag, (f32, f32, f32)>>();
-        let mut per_axis_splits: Vec<Vec<(Tag, f32)>> = vec![];
+    let mut result = comparison.run();
 
-        for (axis, tuple) in axes_min_max.into_iter() {
-            per_axis_splits.push(split_axis(&axis, tuple, args.splits))
+    // Leave out known differences
+    if let Some(mut table_diff) = result.tables.take() {
+        cli.ignore
+            .apply_to_tables(&mut table_diff, &mut result.suppressed);
+        if table_diff.is_something() {
+            result.tables = Some(table_diff);
         }
-        per_axis_splits.dedup();
-        // Find the cartesian product of all axis/value iterators
-        for locations in per_axis_splits.into_iter().multi_cartesian_product() {
-            settings.push(Setting::from_setting(
-                locations
-                    .into_iter()
-                    .map(|(a, v)| skrifa::setting::Setting::new(a, v))
-                    .collect(),
-            ));
+    }
+    if let Some(mut kern_diff) = result.kerns.take() {
+        cli.ignore
+            .apply_to_kerns(&mut kern_diff, &mut result.suppressed);
+        if kern_diff.is_something() {
+            result.kerns = Some(kern_diff);
         }
     }
-
-    if settings.is_empty() {
-        // Add default setting
-        settings.push(Setting::Default);
+    for location in result.locations.iter_mut() {
+        cli.ignore
+            .apply_to_location(location, comparison.font_a(), &mut result.suppressed);
     }
-
-    // Deduplicate
-    settings.into_iter().unique().collect()
-}
-
-// Given an axis and a tuple of min/default/max, return a list of (axis, value) pairs
-// that split the axis into `split_count` parts.
-fn split_axis(axis: &Tag, tuple: (f32, f32, f32), split_count: usize) -> Vec<(Tag, f32)> {
-    let (min, default, max) = tuple;
-    let step = (default - min) / split_count as f32;
-    let mut splits: Vec<f32> = vec![min];
-    for i in 1..split_count {
-        splits.push(min + step * i as f32);
+    if result.locations.len() > 1 {
+        result.locations.retain(|l| l.is_some());
     }
-    splits.push(default);
-    let step = (max - default) / split_count as f32;
-    for i in 1..split_count {
-        splits.push(default + step * i as f32);
+    if cli.dossiers {
+        result.dossiers = glyph_dossiers(&result, comparison.font_a(), comparison.font_b());
     }
-    splits.push(max);
-    splits.dedup();
-    splits.into_iter().map(|v| (*axis, v)).collect()
+    result
 }
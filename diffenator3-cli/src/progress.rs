@@ -0,0 +1,42 @@
+//! Showing the progress of a comparison on the terminal
+use diffenator3_lib::comparison::Progress;
+use indicatif::ProgressBar;
+use std::sync::RwLock;
+
+/// Logs each stage of a comparison, with a progress bar for each word list
+#[derive(Default)]
+pub struct ProgressReporter {
+    bar: RwLock<Option<ProgressBar>>,
+}
+
+impl ProgressReporter {
+    pub fn report(&self, progress: Progress) {
+        match progress {
+            Progress::Tables => log::info!("Diffing binary tables"),
+            Progress::Kerns => log::info!("Diffing kerning"),
+            Progress::Languages => log::info!("Diffing language support"),
+            Progress::Sampling => log::info!("Searching the designspace for locations to test"),
+            Progress::Interpolation => log::info!("Checking interpolation between masters"),
+            Progress::Location { name, .. } => log::info!("Testing {}", name),
+            Progress::WordList { words, .. } => {
+                let mut bar = self.bar.write().unwrap();
+                if let Some(finished) = bar.replace(ProgressBar::new(words as u64)) {
+                    finished.finish();
+                }
+            }
+            Progress::Word => {
+                if let Some(bar) = self.bar.read().unwrap().as_ref() {
+                    bar.inc(1);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.get_mut().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
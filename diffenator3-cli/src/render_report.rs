@@ -0,0 +1,88 @@
+//! Showing a saved report again
+//!
+//! `diffenator3 render-report report.json --html` presents a report saved
+//! with `--json` or `--save-snapshot` without running the comparison again,
+//! so that the expensive part can run once in CI and reviewers can produce
+//! their own HTML (with their own templates) later.
+use crate::{
+    args::ReportFormat,
+    fail, reporters,
+    reporters::{Report, TestsRun},
+};
+use clap::{builder::ValueHint, Args};
+use diffenator3_lib::html::template_engine;
+use std::path::{Path, PathBuf};
+
+/// Show a report saved by `diffenator3 --json` or `--save-snapshot`
+#[derive(Args, Debug)]
+pub struct RenderReportArgs {
+    /// Use this font in place of the first font named in the report
+    #[clap(long = "font-a", value_hint = ValueHint::FilePath)]
+    pub font_a: Option<PathBuf>,
+
+    /// Use this font in place of the second font named in the report
+    #[clap(long = "font-b", value_hint = ValueHint::FilePath)]
+    pub font_b: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub format: ReportFormat,
+
+    /// The saved report
+    #[clap(value_hint = ValueHint::FilePath)]
+    pub report: PathBuf,
+}
+
+pub fn run(args: &RenderReportArgs) {
+    let mut report = Report::load(&args.report).unwrap_or_else(|e| fail(e));
+    if args.format.by_glyph {
+        report.group();
+    }
+
+    if args.format.html {
+        let font_a = font_path(args.font_a.as_ref(), report.font_a.as_ref(), "--font-a");
+        let font_b = font_path(args.font_b.as_ref(), report.font_b.as_ref(), "--font-b");
+        let tera = template_engine(args.format.templates.as_ref(), args.format.update_templates)
+            .unwrap_or_else(|e| fail(e));
+        reporters::html::report(
+            &font_a,
+            &font_b,
+            Path::new(&args.format.output),
+            &tera,
+            &report,
+        )
+        .unwrap_or_else(|e| fail(e));
+    } else if args.format.json {
+        reporters::json::report(report, args.format.pretty);
+    } else if args.format.markdown {
+        reporters::markdown::report(&report, &args.format.markdown_options());
+    } else if args.format.junit {
+        reporters::junit::report(&report, &TestsRun::from_report(&report));
+    } else if args.format.sarif {
+        let font_b = args
+            .font_b
+            .clone()
+            .or_else(|| report.font_b.as_ref().map(PathBuf::from))
+            .unwrap_or_else(|| args.report.clone());
+        reporters::sarif::report(&report, &font_b, &TestsRun::from_report(&report));
+    } else {
+        reporters::text::report(report, args.format.succinct);
+    }
+}
+
+/// Find a font for the HTML report, which needs to embed both fonts
+fn font_path(given: Option<&PathBuf>, recorded: Option<&String>, option: &str) -> PathBuf {
+    let Some(path) = given.cloned().or_else(|| recorded.map(PathBuf::from)) else {
+        fail(format!(
+            "The report doesn't name the fonts compared; pass {}",
+            option
+        ))
+    };
+    if !path.is_file() {
+        fail(format!(
+            "Font {} not found; pass {} to use another copy",
+            path.display(),
+            option
+        ))
+    }
+    path
+}
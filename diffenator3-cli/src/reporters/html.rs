@@ -1,16 +1,20 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-use diffenator3_lib::html::{gen_html, Tera};
+use diffenator3_lib::{
+    html::{gen_glyph_pages, gen_html, write_html, Tera},
+    Error,
+};
+use serde_json::json;
 
-use super::Report;
+use super::{BatchReport, Report};
 
 pub fn report(
-    font1_pb: &PathBuf,
-    font2_pb: &PathBuf,
+    font1_pb: &Path,
+    font2_pb: &Path,
     output_dir: &Path,
-    tera: Tera,
+    tera: &Tera,
     report: &Report,
-) -> ! {
+) -> Result<(), Error> {
     gen_html(
         font1_pb,
         font2_pb,
@@ -20,5 +24,57 @@ pub fn report(
         &serde_json::to_value(report).expect("Couldn't serialize report"),
         "diffenator.html",
         40,
-    );
+    )?;
+    if !report.dossiers.is_empty() {
+        gen_glyph_pages(font1_pb, font2_pb, output_dir, tera, &report.dossiers, 40)?;
+    }
+    Ok(())
+}
+
+/// Write a report for each pair into its own directory, and an index page
+pub fn report_batch(output_dir: &Path, tera: &Tera, batch: &BatchReport) -> Result<(), Error> {
+    let mut pairs = vec![];
+    for pair in batch.pairs.iter() {
+        // Each pair has its own font from the first directory, or from the
+        // family of static fonts when comparing with a variable font. Only
+        // whole filenames are unique within a directory (Foo.ttf and
+        // Foo.otf may both be there), so keep the extension.
+        let named_by = if batch.variable_font.as_ref() == Some(&pair.font_a) {
+            &pair.font_b
+        } else {
+            &pair.font_a
+        };
+        let pair_dir = named_by
+            .file_name()
+            .and_then(|s| s.to_str())
+            .expect("Font has no filename")
+            .to_string();
+        report(
+            &pair.font_a,
+            &pair.font_b,
+            &output_dir.join(&pair_dir),
+            tera,
+            &pair.report,
+        )?;
+        pairs.push(json!({
+            "font_a": pair.font_a,
+            "font_b": pair.font_b,
+            "paired_by": pair.paired_by,
+            "location": pair.location,
+            "summary": pair.summary,
+            "failures": pair.failures,
+            "link": format!("{}/diffenator.html", pair_dir),
+        }));
+    }
+    write_html(
+        tera,
+        "diffenator-index.html",
+        &json!({
+            "pairs": pairs,
+            "unpaired_a": batch.unpaired_a,
+            "unpaired_b": batch.unpaired_b,
+            "unmatched_instances": batch.unmatched_instances,
+        }),
+        &output_dir.join("index.html"),
+    )
 }
@@ -1,4 +1,4 @@
-use super::Report;
+use super::{BatchReport, Report};
 
 pub fn report(result: Report, pretty: bool) {
     if pretty {
@@ -6,5 +6,18 @@ pub fn report(result: Report, pretty: bool) {
     } else {
         println!("{}", serde_json::to_string(&result).expect("foo"));
     }
-    std::process::exit(0);
+}
+
+pub fn report_batch(batch: &BatchReport, pretty: bool) {
+    if pretty {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(batch).expect("Couldn't serialize batch report")
+        );
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string(batch).expect("Couldn't serialize batch report")
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! JUnit XML reports, for test dashboards
+//!
+//! Each kind of test (tables, glyphs, words...) becomes a test suite; see
+//! [`super::testcases`] for how results are turned into test cases.
+use std::fmt::Write;
+
+use super::{
+    testcases::{test_cases, TestCase, TestsRun, SUITES},
+    BatchReport, Report,
+};
+
+pub fn report(result: &Report, tests: &TestsRun) {
+    let cases = test_cases(result, tests);
+    let mut xml = String::new();
+    write_suites(&mut xml, "", &cases);
+    print!("{}", document(&xml, &cases));
+}
+
+pub fn report_batch(batch: &BatchReport, tests: &TestsRun) {
+    let mut xml = String::new();
+    let mut all_cases = vec![];
+    for pair in batch.pairs.iter() {
+        let cases = test_cases(&pair.report, tests);
+        let mut prefix = format!("{} vs {}", pair.font_a.display(), pair.font_b.display());
+        if let Some(location) = pair.location.as_ref() {
+            prefix.push_str(&format!(" at {}", location));
+        }
+        prefix.push_str(": ");
+        write_suites(&mut xml, &prefix, &cases);
+        all_cases.extend(cases);
+    }
+    let unpaired: Vec<TestCase> = batch
+        .unpaired_a
+        .iter()
+        .chain(batch.unpaired_b.iter())
+        .map(|font| {
+            TestCase::new(
+                "pairing",
+                font.display().to_string(),
+                vec!["No counterpart found".to_string()],
+            )
+        })
+        .chain(batch.unmatched_instances.iter().map(|instance| {
+            TestCase::new(
+                "pairing",
+                format!("{} instance", instance),
+                vec!["No static font found for this instance".to_string()],
+            )
+        }))
+        .collect();
+    write_suites(&mut xml, "", &unpaired);
+    all_cases.extend(unpaired);
+    print!("{}", document(&xml, &all_cases));
+}
+
+fn document(suites: &str, cases: &[TestCase]) -> String {
+    let failures = cases.iter().filter(|c| !c.failures.is_empty()).count();
+    let errors = cases.iter().filter(|c| c.error.is_some()).count();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"diffenator3\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n{}</testsuites>\n",
+        cases.len(),
+        failures,
+        errors,
+        suites
+    )
+}
+
+fn write_suites(xml: &mut String, prefix: &str, cases: &[TestCase]) {
+    for (suite, _) in SUITES.iter() {
+        let suite_cases: Vec<&TestCase> = cases.iter().filter(|c| c.suite == *suite).collect();
+        if suite_cases.is_empty() {
+            continue;
+        }
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+            escape(prefix),
+            suite,
+            suite_cases.len(),
+            suite_cases
+                .iter()
+                .filter(|c| !c.failures.is_empty())
+                .count(),
+            suite_cases.iter().filter(|c| c.error.is_some()).count(),
+        );
+        for case in suite_cases {
+            write_case(xml, prefix, case);
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+}
+
+fn write_case(xml: &mut String, prefix: &str, case: &TestCase) {
+    let _ = write!(
+        xml,
+        "    <testcase classname=\"{}{}\" name=\"{}\"",
+        escape(prefix),
+        case.suite,
+        escape(&case.name)
+    );
+    if case.passed() {
+        xml.push_str("/>\n");
+        return;
+    }
+    xml.push_str(">\n");
+    if let Some(error) = case.error.as_ref() {
+        let _ = writeln!(xml, "      <error message=\"{}\"/>", escape(error));
+    }
+    if let Some(first) = case.failures.first() {
+        let message = if case.failures.len() == 1 {
+            first.clone()
+        } else {
+            format!("{} differences; first: {}", case.failures.len(), first)
+        };
+        let _ = writeln!(
+            xml,
+            "      <failure message=\"{}\">{}</failure>",
+            escape(&message),
+            escape(&case.failures.join("\n"))
+        );
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+/// Escape text for use in XML attributes and content
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && c != '\t' => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
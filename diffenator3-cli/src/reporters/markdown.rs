@@ -0,0 +1,504 @@
+//! Markdown reports, for pasting into pull request comments
+//!
+//! Sections are added in order of importance; any which would take the
+//! report over its size budget are left out and counted in a note at the end.
+use std::fmt::Write;
+
+use super::{
+    flatten_diff, in_palette, text::language_support_tables, BatchReport, LocationResult, Report,
+};
+use diffenator3_lib::{
+    metrics::metric_value,
+    structs::{GroupedDifference, GroupedDifferences},
+};
+use itertools::Itertools;
+
+/// Options controlling the size of a Markdown report
+pub struct MarkdownOptions {
+    /// Number of glyph and word differences to show per script and location
+    pub top: usize,
+    /// Maximum length of the report, in bytes
+    pub budget: usize,
+    /// Skip unchanged language support levels
+    pub succinct: bool,
+}
+
+// Room kept back for the truncation note
+const NOTE_RESERVE: usize = 200;
+// Most lines shown for the glyph lists, a single table, or the kerning
+const MAX_DETAIL_LINES: usize = 100;
+
+/// Collects sections until the budget is spent
+struct Document {
+    text: String,
+    budget: usize,
+    omitted: usize,
+}
+
+impl Document {
+    fn new(budget: usize) -> Self {
+        Document {
+            text: String::new(),
+            budget: budget.saturating_sub(NOTE_RESERVE),
+            omitted: 0,
+        }
+    }
+
+    /// Add a section, if it fits
+    fn push(&mut self, section: String) {
+        if self.text.len() + section.len() <= self.budget {
+            self.text.push_str(&section);
+        } else {
+            self.omitted += 1;
+        }
+    }
+
+    /// Add sections under a heading, which is written only once one of
+    /// them fits
+    fn push_group(&mut self, heading: String, sections: Vec<String>) {
+        let mut heading = Some(heading);
+        for section in sections {
+            let heading_len = heading.as_ref().map_or(0, |h| h.len());
+            if self.text.len() + heading_len + section.len() <= self.budget {
+                if let Some(heading) = heading.take() {
+                    self.text.push_str(&heading);
+                }
+                self.text.push_str(&section);
+            } else {
+                self.omitted += 1;
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.omitted > 0 {
+            let _ = writeln!(
+                self.text,
+                "\n_{} more section(s) omitted to keep this report short; \
+                 run diffenator3 locally for the full report._",
+                self.omitted
+            );
+        }
+        self.text
+    }
+}
+
+pub fn report(result: &Report, options: &MarkdownOptions) {
+    let mut document = Document::new(options.budget);
+    document.push("## diffenator3 report\n\n".to_string());
+    report_sections(&mut document, result, options, "###");
+    print!("{}", document.finish());
+}
+
+pub fn report_batch(batch: &BatchReport, options: &MarkdownOptions) {
+    let mut document = Document::new(options.budget);
+    let mut summary = String::from("## diffenator3 report\n\n");
+    summary.push_str(
+        "| Font A | Font B | Paired by | Tables | Kerns | Missing glyphs | New glyphs | Glyphs | Words | Metrics | Languages |\n",
+    );
+    summary.push_str("|---|---|---|---|---|---|---|---|---|---|---|\n");
+    for pair in batch.pairs.iter() {
+        let s = &pair.summary;
+        let _ = writeln!(
+            summary,
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} |",
+            cell(&pair.font_a.display().to_string()),
+            cell(&pair.font_b.display().to_string()),
+            cell(&pair.pairing()),
+            s.tables,
+            s.kerns,
+            s.missing_glyphs,
+            s.new_glyphs,
+            s.glyphs,
+            s.words,
+            s.metrics,
+            s.languages
+        );
+    }
+    for (side, unpaired) in [("first", &batch.unpaired_a), ("second", &batch.unpaired_b)] {
+        if !unpaired.is_empty() {
+            let _ = writeln!(summary, "\nUnpaired fonts in the {} directory:\n", side);
+            for font in unpaired {
+                let _ = writeln!(summary, "- `{}`", font.display());
+            }
+        }
+    }
+    if !batch.unmatched_instances.is_empty() {
+        summary.push_str("\nInstances of the variable font with no static font:\n\n");
+        for instance in batch.unmatched_instances.iter() {
+            let _ = writeln!(summary, "- {}", cell(instance));
+        }
+    }
+    summary.push('\n');
+    document.push(summary);
+
+    for pair in batch.pairs.iter() {
+        let at = pair
+            .location
+            .as_ref()
+            .map(|location| format!(" at {}", cell(location)))
+            .unwrap_or_default();
+        document.push(format!(
+            "### `{}` vs `{}`{}\n\n",
+            pair.font_a.display(),
+            pair.font_b.display(),
+            at
+        ));
+        report_sections(&mut document, &pair.report, options, "####");
+    }
+    print!("{}", document.finish());
+}
+
+fn report_sections(
+    document: &mut Document,
+    result: &Report,
+    options: &MarkdownOptions,
+    heading: &str,
+) {
+    if !result.errors.is_empty() {
+        let mut section = format!("{} Problems\n\n", heading);
+        for error in result.errors.iter() {
+            let _ = writeln!(section, "- {}", cell(error));
+        }
+        section.push('\n');
+        document.push(section);
+    }
+
+    if let Some(cmap_diff) = result.cmap_diff.as_ref() {
+        if !cmap_diff.missing.is_empty() || !cmap_diff.new.is_empty() {
+            let mut section = format!(
+                "{} Encoded glyphs\n\n**{}** added, **{}** removed\n\n",
+                heading,
+                cmap_diff.new.len(),
+                cmap_diff.missing.len()
+            );
+            for (label, glyphs) in [("Removed", &cmap_diff.missing), ("Added", &cmap_diff.new)] {
+                if glyphs.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(
+                    section,
+                    "<details><summary>{} ({})</summary>\n",
+                    label,
+                    glyphs.len()
+                );
+                for glyph in glyphs.iter().take(MAX_DETAIL_LINES) {
+                    let _ = writeln!(section, "- {}", glyph);
+                }
+                if glyphs.len() > MAX_DETAIL_LINES {
+                    let _ = writeln!(
+                        section,
+                        "- ... and {} more",
+                        glyphs.len() - MAX_DETAIL_LINES
+                    );
+                }
+                section.push_str("\n</details>\n\n");
+            }
+            document.push(section);
+        }
+    }
+
+    let grouped = result.grouped.is_some();
+    for location in result.locations.iter() {
+        report_location(document, location, options, heading, grouped);
+    }
+    if let Some(grouped) = result.grouped.as_ref() {
+        report_grouped(document, grouped, options, heading);
+    }
+
+    if let Some(languages) = result.languages.as_ref() {
+        if languages.values().any(|l| l.level_a != l.level_b) {
+            let (levels, changes) = language_support_tables(languages, options.succinct);
+            document.push(format!(
+                "{} Language support\n\n{}\n\n<details><summary>Language differences</summary>\n\n{}\n\n</details>\n\n",
+                heading, levels, changes
+            ));
+        }
+    }
+
+    if !result.instances.is_empty() {
+        let mut section = format!("{} Named instances\n\n", heading);
+        for change in result.instances.iter() {
+            let _ = writeln!(section, "- {}", change);
+        }
+        section.push('\n');
+        document.push(section);
+    }
+
+    if !result.palettes.is_empty() {
+        let mut section = format!("{} Palettes\n\n", heading);
+        for change in result.palettes.iter() {
+            let _ = writeln!(section, "- {}", change);
+        }
+        section.push('\n');
+        document.push(section);
+    }
+
+    if let Some(interpolation) = result.interpolation.as_ref() {
+        let introduced = interpolation.font_b.iter().filter(|k| k.introduced).count();
+        if !interpolation.font_a.is_empty() || !interpolation.font_b.is_empty() {
+            let mut section = format!(
+                "{} Interpolation\n\n**{}** glyph(s) kink between masters in the new font, **{}** of them newly\n\n",
+                heading,
+                interpolation.font_b.len(),
+                introduced
+            );
+            for (label, kinks) in [
+                ("Old font", &interpolation.font_a),
+                ("New font", &interpolation.font_b),
+            ] {
+                let lines: Vec<String> = kinks.iter().map(|k| k.to_string()).collect();
+                if !lines.is_empty() {
+                    section.push_str(&details(label, &lines));
+                }
+            }
+            document.push(section);
+        }
+    }
+
+    if let Some(tables) = result.tables.as_ref().and_then(|t| t.as_object()) {
+        let sections = tables
+            .iter()
+            .map(|(table_name, diff)| {
+                let mut lines = vec![];
+                flatten_diff(diff, "", &mut lines);
+                details(
+                    &format!("<code>{}</code> ({} changes)", table_name, lines.len()),
+                    &lines,
+                )
+            })
+            .collect();
+        document.push_group(format!("{} Tables\n\n", heading), sections);
+    }
+
+    if let Some(kerns) = result.kerns.as_ref().and_then(|k| k.as_object()) {
+        let mut lines = vec![];
+        for (pair, diff) in kerns.iter() {
+            flatten_diff(diff, pair, &mut lines);
+        }
+        document.push(format!(
+            "{} Kerning\n\n{}",
+            heading,
+            details(&format!("{} pairs changed", kerns.len()), &lines)
+        ));
+    }
+
+    if !result.suppressed.is_empty() {
+        let mut section = format!("{} Suppressed differences\n\n", heading);
+        for (rule, count) in result.suppressed.iter() {
+            let _ = writeln!(section, "- {}: {}", cell(rule), count);
+        }
+        section.push('\n');
+        document.push(section);
+    }
+}
+
+/// Add the differences at one location; glyphs and words are left out
+/// when they are `grouped` across locations
+fn report_location(
+    document: &mut Document,
+    location: &LocationResult,
+    options: &MarkdownOptions,
+    heading: &str,
+    grouped: bool,
+) {
+    let shown = if grouped {
+        location.error.is_some() || !location.errors.is_empty() || !location.metrics.is_empty()
+    } else {
+        location.is_some()
+    };
+    if !shown {
+        return;
+    }
+    let mut location_heading = format!("{} Location `{}`\n\n", heading, location.location);
+    if let (Some(instance_a), Some(instance_b)) =
+        (location.instance_a.as_ref(), location.instance_b.as_ref())
+    {
+        let _ = write!(
+            location_heading,
+            "Compared `{}` instance of old font with `{}` instance of new font\n\n",
+            instance_a, instance_b
+        );
+    }
+    let mut sections = vec![];
+    if let Some(error) = location.error.as_ref() {
+        sections.push(format!("**Error:** {}\n\n", error));
+    }
+    if !location.errors.is_empty() {
+        let mut section = "**Problems:**\n\n".to_string();
+        for error in location.errors.iter() {
+            let _ = writeln!(section, "- {}", cell(error));
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    if !location.glyphs.is_empty() && !grouped {
+        let mut section = format!(
+            "**Glyphs** (top {} of {})\n\n| Glyph | Codepoint | Name | Differing pixels |\n|---|---|---|---|\n",
+            options.top.min(location.glyphs.len()),
+            location.glyphs.len()
+        );
+        for glyph in location.glyphs.iter().take(options.top) {
+            let _ = writeln!(
+                section,
+                "| {} | {} | {} | {} |",
+                cell(&glyph.string),
+                glyph.unicode,
+                cell(&format!("{}{}", glyph.name, in_palette(glyph.palette))),
+                glyph.differing_pixels
+            );
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    if !location.metrics.is_empty() {
+        let mut section = format!(
+            "**Metrics** (top {} of {})\n\n| Metric | Glyph | Before | After |\n|---|---|---|---|\n",
+            options.top.min(location.metrics.len()),
+            location.metrics.len()
+        );
+        for metric in location.metrics.iter().take(options.top) {
+            let _ = writeln!(
+                section,
+                "| {} | {} | {} | {} |",
+                metric.metric,
+                cell(metric.glyph.as_deref().unwrap_or("")),
+                metric_value(metric.value_a),
+                metric_value(metric.value_b)
+            );
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    for (script, differences) in location.words.iter().filter(|_| !grouped) {
+        let mut section = format!(
+            "**{}** (top {} of {})\n\n| Word | Features | Language | Differing pixels |\n|---|---|---|---|\n",
+            script,
+            options.top.min(differences.len()),
+            differences.len()
+        );
+        for difference in differences.iter().take(options.top) {
+            let _ = writeln!(
+                section,
+                "| {} | {} | {} | {} |",
+                cell(&format!(
+                    "{}{}",
+                    difference.word,
+                    in_palette(difference.palette)
+                )),
+                cell(&difference.ot_features),
+                cell(&difference.lang),
+                difference.differing_pixels
+            );
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    document.push_group(location_heading, sections);
+}
+
+/// Add the glyph and word differences, each merged across locations
+fn report_grouped(
+    document: &mut Document,
+    grouped: &GroupedDifferences,
+    options: &MarkdownOptions,
+    heading: &str,
+) {
+    if grouped.glyphs.is_empty() && grouped.words.is_empty() {
+        return;
+    }
+    let mut sections = vec![];
+    if !grouped.glyphs.is_empty() {
+        let mut section = format!(
+            "**Glyphs** (top {} of {})\n\n| Glyph | Name | Differing pixels | Locations |\n|---|---|---|---|\n",
+            options.top.min(grouped.glyphs.len()),
+            grouped.glyphs.len()
+        );
+        for glyph in grouped.glyphs.iter().take(options.top) {
+            let _ = writeln!(
+                section,
+                "| {} | {} | {} | {} |",
+                cell(&glyph.string),
+                glyph.name,
+                pixel_range(glyph),
+                cell(&glyph.locations.join(", "))
+            );
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    for (script, words) in &grouped.words.iter().chunk_by(|word| word.script.as_str()) {
+        let words: Vec<&GroupedDifference> = words.collect();
+        let mut section = format!(
+            "**{}** (top {} of {})\n\n| Word | Features | Language | Differing pixels | Locations |\n|---|---|---|---|---|\n",
+            script,
+            options.top.min(words.len()),
+            words.len()
+        );
+        for word in words.iter().take(options.top) {
+            let _ = writeln!(
+                section,
+                "| {} | {} | {} | {} | {} |",
+                cell(&word.string),
+                cell(&word.ot_features),
+                cell(&word.lang),
+                pixel_range(word),
+                cell(&word.locations.join(", "))
+            );
+        }
+        section.push('\n');
+        sections.push(section);
+    }
+    document.push_group(
+        format!("{} Glyphs and words across locations\n\n", heading),
+        sections,
+    );
+}
+
+fn pixel_range(difference: &GroupedDifference) -> String {
+    if difference.min_pixels == difference.max_pixels {
+        difference.max_pixels.to_string()
+    } else {
+        format!("{}-{}", difference.min_pixels, difference.max_pixels)
+    }
+}
+
+/// A collapsible section containing a code block
+fn details(summary: &str, lines: &[String]) -> String {
+    let mut shown = lines
+        .iter()
+        .take(MAX_DETAIL_LINES)
+        .cloned()
+        .collect::<Vec<_>>();
+    if lines.len() > MAX_DETAIL_LINES {
+        shown.push(format!("... and {} more", lines.len() - MAX_DETAIL_LINES));
+    }
+    format!(
+        "<details><summary>{}</summary>\n\n```\n{}\n```\n\n</details>\n\n",
+        summary,
+        shown.join("\n")
+    )
+}
+
+/// Escape text for use in a table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_need_a_section_which_fits() {
+        let mut document = Document::new(NOTE_RESERVE + 30);
+        document.push_group("## Tables\n\n".to_string(), vec!["x".repeat(40)]);
+        assert_eq!(document.text, "");
+        document.push_group(
+            "## Kerning\n\n".to_string(),
+            vec!["y".repeat(40), "z".repeat(10)],
+        );
+        assert_eq!(document.text, format!("## Kerning\n\n{}", "z".repeat(10)));
+        assert_eq!(document.omitted, 2);
+    }
+}
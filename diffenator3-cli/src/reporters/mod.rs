@@ -1,56 +1,116 @@
 pub mod html;
 pub mod json;
+pub mod junit;
+pub mod markdown;
+pub mod sarif;
+mod testcases;
 pub mod text;
 
-use diffenator3_lib::structs::{CmapDiff, Difference, GlyphDiff};
+use diffenator3_lib::family::MatchedBy;
+pub use diffenator3_lib::report::{LocationResult, Report, ReportSummary};
 use serde::Serialize;
-use std::collections::{BTreeMap, HashMap};
+use serde_json::Value;
+use std::path::PathBuf;
+pub use testcases::TestsRun;
 
-#[derive(Serialize, Default)]
-#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
-pub struct LocationResult {
-    /// Name of the location in designspace (named instance, or stringified coordinates)
-    pub location: String,
-    /// Coordinates of the location in designspace
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub coords: HashMap<String, f32>,
-    /// An error message, if something went wrong
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-    /// Differences between glyphs
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub glyphs: Vec<GlyphDiff>,
-    /// Differences between words
-    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
-    pub words: BTreeMap<String, Vec<Difference>>,
+/// Describes the palette a glyph or word difference was found in, if it
+/// wasn't the default
+pub(crate) fn in_palette(palette: Option<u16>) -> String {
+    palette
+        .map(|palette| format!(" in palette {}", palette))
+        .unwrap_or_default()
 }
 
-impl LocationResult {
-    pub fn is_some(&self) -> bool {
-        self.error.is_some() || !self.glyphs.is_empty() || !self.words.is_empty()
+/// Turn a nested diff into `path: before => after` lines
+pub(crate) fn flatten_diff(diff: &Value, path: &str, lines: &mut Vec<String>) {
+    match diff {
+        Value::Object(fields) => {
+            for (field, value) in fields.iter() {
+                let path = if path.is_empty() {
+                    field.clone()
+                } else {
+                    format!("{}.{}", path, field)
+                };
+                flatten_diff(value, &path, lines);
+            }
+        }
+        Value::Array(lr) if lr.len() == 2 => {
+            lines.push(format!("{}: {} => {}", path, lr[0], lr[1]));
+        }
+        _ => lines.push(format!("{}: {}", path, diff)),
     }
+}
+
+/// How two fonts in a batch comparison were paired
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PairedBy {
+    Filename,
+    PostscriptName,
+    FamilyAndSubfamily,
+    /// A static font was matched to a named instance of a variable font
+    /// with the same style name
+    InstanceName,
+    /// ...or with the same OS/2 weight and width class
+    WeightAndWidth,
+    /// ...or to a location given by the variable font's `STAT` table
+    Stat,
+}
 
-    pub fn from_error(location: String, error: String) -> Self {
-        LocationResult {
-            location,
-            error: Some(error),
-            ..Default::default()
+impl From<MatchedBy> for PairedBy {
+    fn from(matched_by: MatchedBy) -> Self {
+        match matched_by {
+            MatchedBy::InstanceName => PairedBy::InstanceName,
+            MatchedBy::WeightAndWidth => PairedBy::WeightAndWidth,
+            MatchedBy::Stat => PairedBy::Stat,
         }
     }
 }
-#[derive(Serialize, Default)]
-#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
-pub struct Report {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tables: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub kerns: Option<serde_json::Value>,
+
+/// The comparison of one pair of fonts in a batch
+#[derive(Serialize)]
+pub struct PairReport {
+    pub font_a: PathBuf,
+    pub font_b: PathBuf,
+    pub paired_by: PairedBy,
+    /// Where in the variable font a static font was compared, when comparing
+    /// a variable font with a family of static fonts
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub cmap_diff: Option<CmapDiff>,
+    pub location: Option<String>,
+    pub summary: ReportSummary,
+    /// Gating rules which tripped for this pair
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub locations: Vec<LocationResult>,
+    pub failures: Vec<String>,
+    pub report: Report,
+}
+
+impl PairReport {
+    /// How the fonts were paired, and where in the variable font they were
+    /// compared, for display
+    pub fn pairing(&self) -> String {
+        let paired_by = format!("{:?}", self.paired_by);
+        match self.location.as_ref() {
+            Some(location) => format!("{} ({})", paired_by, location),
+            None => paired_by,
+        }
+    }
+}
+
+/// The result of comparing two directories of fonts, or a variable font with
+/// a directory of static fonts
+#[derive(Serialize, Default)]
+pub struct BatchReport {
+    pub pairs: Vec<PairReport>,
+    /// Fonts in the first directory with no counterpart in the second
+    pub unpaired_a: Vec<PathBuf>,
+    /// Fonts in the second directory with no counterpart in the first
+    pub unpaired_b: Vec<PathBuf>,
+    /// The variable font, when comparing one with a family of static fonts
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub languages: Option<BTreeMap<String, crate::languages::LanguageDiff>>,
+    pub variable_font: Option<PathBuf>,
+    /// Named instances of the variable font which no static font matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmatched_instances: Vec<String>,
 }
 
 #[cfg(feature = "typescript")]
@@ -0,0 +1,106 @@
+//! SARIF 2.1.0 reports, for code scanning dashboards
+//!
+//! Each difference becomes a result of level `warning` against the second
+//! font; tests which found no differences are recorded as results of kind
+//! `pass`. See [`super::testcases`] for how results are grouped.
+use std::path::Path;
+
+use super::{
+    testcases::{test_cases, TestCase, TestsRun, SUITES},
+    BatchReport, Report,
+};
+use serde_json::{json, Value};
+
+/// Report the differences found in `font_b`
+pub fn report(result: &Report, font_b: &Path, tests: &TestsRun) {
+    let cases = test_cases(result, tests);
+    let results = results(font_b, &cases);
+    print_log(results);
+}
+
+pub fn report_batch(batch: &BatchReport, tests: &TestsRun) {
+    let mut all_results = vec![];
+    for pair in batch.pairs.iter() {
+        let cases = test_cases(&pair.report, tests);
+        all_results.extend(results(&pair.font_b, &cases));
+    }
+    for font in batch.unpaired_a.iter().chain(batch.unpaired_b.iter()) {
+        let case = TestCase::new(
+            "pairing",
+            font.display().to_string(),
+            vec!["No counterpart found".to_string()],
+        );
+        all_results.extend(results(font, &[case]));
+    }
+    if let Some(variable_font) = batch.variable_font.as_ref() {
+        for instance in batch.unmatched_instances.iter() {
+            let case = TestCase::new(
+                "pairing",
+                format!("{} instance", instance),
+                vec!["No static font found for this instance".to_string()],
+            );
+            all_results.extend(results(variable_font, &[case]));
+        }
+    }
+    print_log(all_results);
+}
+
+fn print_log(results: Vec<Value>) {
+    let rules: Vec<Value> = SUITES
+        .iter()
+        .map(|(id, description)| json!({"id": id, "shortDescription": {"text": description}}))
+        .collect();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "diffenator3",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/googlefonts/diffenator3",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&log).expect("Couldn't serialize SARIF")
+    );
+}
+
+fn results(font: &Path, cases: &[TestCase]) -> Vec<Value> {
+    let mut results = vec![];
+    for case in cases {
+        let location = |text: &str| {
+            json!({
+                "ruleId": case.suite,
+                "message": {"text": text},
+                "locations": [{
+                    "physicalLocation": {"artifactLocation": {"uri": font.display().to_string()}},
+                    "logicalLocations": [{"name": case.name}],
+                }],
+            })
+        };
+        if case.passed() {
+            let mut result = location(&format!("{}: no differences", case.name));
+            result["kind"] = json!("pass");
+            result["level"] = json!("none");
+            results.push(result);
+            continue;
+        }
+        if let Some(error) = case.error.as_ref() {
+            let mut result = location(&format!("{}: {}", case.name, error));
+            result["level"] = json!("error");
+            results.push(result);
+        }
+        for failure in case.failures.iter() {
+            let mut result = location(&format!("{}: {}", case.name, failure));
+            result["level"] = json!("warning");
+            results.push(result);
+        }
+    }
+    results
+}
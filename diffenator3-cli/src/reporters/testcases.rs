@@ -0,0 +1,284 @@
+//! Results as test cases, for the JUnit and SARIF reporters
+//!
+//! Each table, language, and glyph or word group at a location becomes a
+//! test case; each difference reported within it becomes a failure. Tests
+//! which ran without finding differences become passing test cases.
+use super::{flatten_diff, in_palette, Report};
+use crate::args::Cli;
+
+pub(super) struct TestCase {
+    /// The kind of test, e.g. "tables" or "words"
+    pub suite: &'static str,
+    /// What was tested, e.g. a table tag or "Latin at Default"
+    pub name: String,
+    /// One message per difference found
+    pub failures: Vec<String>,
+    /// Set if the test could not be run
+    pub error: Option<String>,
+}
+
+impl TestCase {
+    pub fn new(suite: &'static str, name: impl Into<String>, failures: Vec<String>) -> Self {
+        TestCase {
+            suite,
+            name: name.into(),
+            failures,
+            error: None,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty() && self.error.is_none()
+    }
+}
+
+pub(super) const SUITES: [(&str, &str); 12] = [
+    ("tables", "Differences in font tables"),
+    ("kerns", "Differences in kerning pairs"),
+    ("cmap", "Encoded glyphs added or removed"),
+    ("glyphs", "Differences in glyph images"),
+    ("words", "Differences in word images"),
+    ("metrics", "Differences in font and glyph metrics"),
+    ("languages", "Changes in language support"),
+    (
+        "instances",
+        "Named instances added, removed, renamed or moved",
+    ),
+    ("palettes", "CPAL palette colors changed"),
+    ("interpolation", "Glyphs which kink between masters"),
+    (
+        "pairing",
+        "Fonts or instances without a counterpart in a batch comparison",
+    ),
+    ("problems", "Tests which could not be run"),
+];
+
+/// Which tests were run, for those tests which leave nothing in the report
+/// when they find no differences
+pub struct TestsRun {
+    pub tables: bool,
+    pub kerns: bool,
+    pub glyphs: bool,
+    pub words: bool,
+    pub metrics: bool,
+}
+
+impl TestsRun {
+    pub fn from_cli(cli: &Cli) -> Self {
+        TestsRun {
+            tables: cli.tables,
+            kerns: cli.kerns,
+            glyphs: cli.glyphs,
+            words: cli.words,
+            metrics: cli.metrics,
+        }
+    }
+
+    /// Guess which tests were run from a saved report
+    ///
+    /// A saved report doesn't record the tests which found nothing, so
+    /// only tests with differences become test cases.
+    pub fn from_report(report: &Report) -> Self {
+        let grouped = report.grouped.as_ref();
+        TestsRun {
+            tables: report.tables.is_some(),
+            kerns: report.kerns.is_some(),
+            glyphs: report.locations.iter().any(|l| !l.glyphs.is_empty())
+                || grouped.is_some_and(|g| !g.glyphs.is_empty()),
+            words: report.locations.iter().any(|l| !l.words.is_empty())
+                || grouped.is_some_and(|g| !g.words.is_empty()),
+            metrics: report.locations.iter().any(|l| !l.metrics.is_empty()),
+        }
+    }
+}
+
+pub(super) fn test_cases(result: &Report, tests: &TestsRun) -> Vec<TestCase> {
+    let mut cases = vec![];
+
+    if tests.tables {
+        match result.tables.as_ref().and_then(|t| t.as_object()) {
+            Some(tables) if !tables.is_empty() => {
+                for (table, diff) in tables.iter() {
+                    let mut failures = vec![];
+                    flatten_diff(diff, table, &mut failures);
+                    cases.push(TestCase::new("tables", table, failures));
+                }
+            }
+            _ => cases.push(TestCase::new("tables", "tables", vec![])),
+        }
+    }
+
+    if tests.kerns {
+        let mut failures = vec![];
+        if let Some(kerns) = result.kerns.as_ref() {
+            flatten_diff(kerns, "", &mut failures);
+        }
+        cases.push(TestCase::new("kerns", "kerning", failures));
+    }
+
+    if let Some(cmap_diff) = result.cmap_diff.as_ref() {
+        let failures = cmap_diff
+            .missing
+            .iter()
+            .map(|g| format!("Removed {}", g))
+            .chain(cmap_diff.new.iter().map(|g| format!("Added {}", g)))
+            .collect();
+        cases.push(TestCase::new("cmap", "encoded glyphs", failures));
+    }
+
+    for location in result.locations.iter() {
+        if let Some(error) = location.error.as_ref() {
+            let suite = if tests.glyphs { "glyphs" } else { "words" };
+            let mut case = TestCase::new(suite, format!("at {}", location.location), vec![]);
+            case.error = Some(error.clone());
+            cases.push(case);
+            continue;
+        }
+        if !location.errors.is_empty() {
+            let suite = if tests.glyphs { "glyphs" } else { "words" };
+            let mut case =
+                TestCase::new(suite, format!("rendering at {}", location.location), vec![]);
+            case.error = Some(location.errors.join("; "));
+            cases.push(case);
+        }
+        if tests.glyphs && result.grouped.is_none() {
+            let failures = location
+                .glyphs
+                .iter()
+                .map(|glyph| {
+                    format!(
+                        "{} ({} {}){} differs by {} pixels",
+                        glyph.string,
+                        glyph.unicode,
+                        glyph.name,
+                        in_palette(glyph.palette),
+                        glyph.differing_pixels
+                    )
+                })
+                .collect();
+            cases.push(TestCase::new(
+                "glyphs",
+                format!("glyphs at {}", location.location),
+                failures,
+            ));
+        }
+        if tests.metrics {
+            cases.push(TestCase::new(
+                "metrics",
+                format!("metrics at {}", location.location),
+                location.metrics.iter().map(|m| m.to_string()).collect(),
+            ));
+        }
+        if tests.words && result.grouped.is_none() {
+            if location.words.is_empty() {
+                cases.push(TestCase::new(
+                    "words",
+                    format!("words at {}", location.location),
+                    vec![],
+                ));
+            }
+            for (script, differences) in location.words.iter() {
+                let failures = differences
+                    .iter()
+                    .map(|difference| {
+                        let mut message = format!(
+                            "'{}'{} differs by {} pixels; A: {}",
+                            difference.word,
+                            in_palette(difference.palette),
+                            difference.differing_pixels,
+                            difference.buffer_a
+                        );
+                        if let Some(buffer_b) = difference.buffer_b.as_ref() {
+                            message.push_str(&format!("; B: {}", buffer_b));
+                        }
+                        message
+                    })
+                    .collect();
+                cases.push(TestCase::new(
+                    "words",
+                    format!("{} at {}", script, location.location),
+                    failures,
+                ));
+            }
+        }
+    }
+
+    if let Some(grouped) = result.grouped.as_ref() {
+        // One case for each glyph or word, wherever it differs
+        for (suite, enabled, differences) in [
+            ("glyphs", tests.glyphs, &grouped.glyphs),
+            ("words", tests.words, &grouped.words),
+        ] {
+            if !enabled {
+                continue;
+            }
+            if differences.is_empty() {
+                cases.push(TestCase::new(suite, suite, vec![]));
+            }
+            for difference in differences.iter() {
+                let name = if difference.script.is_empty() {
+                    difference.string.clone()
+                } else {
+                    format!("{} ({})", difference.string, difference.script)
+                };
+                cases.push(TestCase::new(suite, name, vec![difference.to_string()]));
+            }
+        }
+    }
+
+    if let Some(languages) = result.languages.as_ref() {
+        for (language, diff) in languages.iter() {
+            // Languages neither font can support aren't interesting
+            if diff.level_a == diff.level_b
+                && (diff.level_a == "None" || diff.level_a == "Indeterminate")
+            {
+                continue;
+            }
+            let failures = if diff.level_a != diff.level_b {
+                vec![format!(
+                    "Support changed from {} to {}",
+                    diff.level_a, diff.level_b
+                )]
+            } else {
+                vec![]
+            };
+            cases.push(TestCase::new("languages", language, failures));
+        }
+    }
+
+    for change in result.instances.iter() {
+        let name = change
+            .name_a
+            .as_ref()
+            .or(change.name_b.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        cases.push(TestCase::new("instances", name, vec![change.to_string()]));
+    }
+
+    for change in result.palettes.iter() {
+        cases.push(TestCase::new(
+            "palettes",
+            format!("palette {} entry {}", change.palette, change.entry),
+            vec![change.to_string()],
+        ));
+    }
+
+    if let Some(interpolation) = result.interpolation.as_ref() {
+        // Only kinks which the new font introduced are failures
+        let failures = interpolation
+            .font_b
+            .iter()
+            .filter(|kink| kink.introduced)
+            .map(|kink| kink.to_string())
+            .collect();
+        cases.push(TestCase::new("interpolation", "interpolation", failures));
+    }
+
+    for error in result.errors.iter() {
+        let mut case = TestCase::new("problems", error, vec![]);
+        case.error = Some(error.clone());
+        cases.push(case);
+    }
+    cases
+}
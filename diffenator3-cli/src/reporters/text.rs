@@ -1,8 +1,9 @@
 use std::collections::BTreeMap;
 
-use super::{LocationResult, Report};
+use super::{in_palette, BatchReport, LocationResult, Report};
 
 use colored::Colorize;
+use itertools::Itertools;
 use serde_json::Map;
 use tabled::{settings::Style, Table, Tabled};
 use ttj::jsondiff::Substantial;
@@ -85,9 +86,28 @@ pub fn report(result: Report, succinct: bool) {
         }
     }
 
+    let grouped = result.grouped.is_some();
     for locationresult in result.locations {
         if locationresult.is_some() {
-            report_location(locationresult);
+            report_location(locationresult, grouped);
+        }
+    }
+
+    if let Some(grouped) = result.grouped.as_ref() {
+        if !grouped.glyphs.is_empty() {
+            println!("\n# Glyphs across locations\n");
+            for glyph in grouped.glyphs.iter() {
+                println!(" - {}", glyph);
+            }
+        }
+        if !grouped.words.is_empty() {
+            println!("\n# Words across locations");
+            for (script, words) in &grouped.words.iter().chunk_by(|word| word.script.as_str()) {
+                println!("\n## {}", script);
+                for word in words {
+                    println!("  - {}", word);
+                }
+            }
         }
     }
 
@@ -99,9 +119,124 @@ pub fn report(result: Report, succinct: bool) {
             println!("\nNo differences found");
         }
     }
+
+    if !result.instances.is_empty() {
+        println!("\n# Named instances\n");
+        for change in result.instances.iter() {
+            println!(" - {}", change);
+        }
+    }
+
+    if !result.palettes.is_empty() {
+        println!("\n# Palettes\n");
+        for change in result.palettes.iter() {
+            println!(" - {}", change);
+        }
+    }
+
+    if let Some(interpolation) = result.interpolation.as_ref() {
+        println!("\n# Interpolation\n");
+        if interpolation.font_a.is_empty() && interpolation.font_b.is_empty() {
+            println!("No kinks found between masters");
+        }
+        for (font, kinks) in [
+            ("old", &interpolation.font_a),
+            ("new", &interpolation.font_b),
+        ] {
+            if !kinks.is_empty() {
+                println!("Glyphs which kink between masters in the {} font:", font);
+                for kink in kinks.iter() {
+                    println!(" - {}", kink);
+                }
+            }
+        }
+    }
+
+    if !result.errors.is_empty() {
+        println!("\n# Problems\n");
+        for error in result.errors.iter() {
+            println!(" - {}", error.red());
+        }
+    }
+
+    if !result.suppressed.is_empty() {
+        println!("\n# Suppressed differences\n");
+        for (rule, count) in result.suppressed.iter() {
+            println!(" - {}: {}", rule, count);
+        }
+    }
 }
 
-fn report_location(locationresult: LocationResult) {
+pub fn report_batch(batch: BatchReport, succinct: bool) {
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record(vec![
+        "Font A",
+        "Font B",
+        "Paired by",
+        "Tables",
+        "Kerns",
+        "Missing glyphs",
+        "New glyphs",
+        "Glyphs",
+        "Words",
+        "Metrics",
+        "Languages",
+    ]);
+    for pair in batch.pairs {
+        let font_a = pair.font_a.display().to_string();
+        let font_b = pair.font_b.display().to_string();
+        let heading = match pair.location.as_ref() {
+            Some(location) => format!("# Comparing {} with {} at {}", font_a, font_b, location),
+            None => format!("# Comparing {} with {}", font_a, font_b),
+        };
+        println!("\n{}", heading.bold());
+        let summary = &pair.summary;
+        builder.push_record(vec![
+            font_a,
+            font_b,
+            pair.pairing(),
+            summary.tables.to_string(),
+            summary.kerns.to_string(),
+            summary.missing_glyphs.to_string(),
+            summary.new_glyphs.to_string(),
+            summary.glyphs.to_string(),
+            summary.words.to_string(),
+            summary.metrics.to_string(),
+            summary.languages.to_string(),
+        ]);
+        report(pair.report, succinct);
+    }
+
+    println!("\n{}\n", "# Summary".bold());
+    let mut table = builder.build();
+    table.with(Style::markdown());
+    println!("{}", table);
+    for (side, unpaired) in [("first", batch.unpaired_a), ("second", batch.unpaired_b)] {
+        if !unpaired.is_empty() {
+            println!("\nUnpaired fonts in the {} directory:", side);
+            for font in unpaired {
+                println!(" - {}", font.display());
+            }
+        }
+    }
+    if !batch.unmatched_instances.is_empty() {
+        println!("\nInstances of the variable font with no static font:");
+        for instance in batch.unmatched_instances {
+            println!(" - {}", instance);
+        }
+    }
+}
+
+/// Show the differences at one location; glyphs and words are left out
+/// when they are `grouped` across locations
+fn report_location(locationresult: LocationResult, grouped: bool) {
+    if grouped
+        && locationresult.error.is_none()
+        && locationresult.errors.is_empty()
+        && locationresult.metrics.is_empty()
+    {
+        return;
+    }
     print!("# Differences at location {} ", locationresult.location);
     if !locationresult.coords.is_empty() {
         print!("( ");
@@ -111,22 +246,51 @@ fn report_location(locationresult: LocationResult) {
         print!(")");
     }
     println!();
+    if let (Some(instance_a), Some(instance_b)) = (
+        locationresult.instance_a.as_ref(),
+        locationresult.instance_b.as_ref(),
+    ) {
+        println!(
+            "Compared {} instance of old font with {} instance of new font",
+            instance_a, instance_b
+        );
+    }
+
+    if let Some(error) = locationresult.error.as_ref() {
+        println!("{}", error.red());
+    }
+    for error in locationresult.errors.iter() {
+        println!(" - {}", error.red());
+    }
 
-    if !locationresult.glyphs.is_empty() {
+    if !locationresult.glyphs.is_empty() && !grouped {
         println!("\n## Glyphs");
         for glyph in locationresult.glyphs {
-            println!(" - {} ({:.3} pixels)", glyph.string, glyph.differing_pixels);
+            println!(
+                " - {}{} ({:.3} pixels)",
+                glyph.string,
+                in_palette(glyph.palette),
+                glyph.differing_pixels
+            );
         }
     }
 
-    if !locationresult.words.is_empty() {
+    if !locationresult.metrics.is_empty() {
+        println!("\n## Metrics");
+        for metric in locationresult.metrics {
+            println!(" - {}", metric);
+        }
+    }
+
+    if !locationresult.words.is_empty() && !grouped {
         println!("# Words");
         for (script, script_diff) in locationresult.words.iter() {
             println!("\n## {}", script);
             for difference in script_diff.iter() {
                 println!(
-                    "  - {} ({:.3}%)",
+                    "  - {}{} ({:.3}%)",
                     difference.word.as_str(),
+                    in_palette(difference.palette),
                     difference.differing_pixels
                 );
             }
@@ -148,7 +312,22 @@ struct DetailsRow {
     glyphs_needed: u64,
 }
 
-fn report_language_support(map: &BTreeMap<String, crate::languages::LanguageDiff>, succinct: bool) {
+fn report_language_support(
+    map: &BTreeMap<String, diffenator3_lib::languages::LanguageDiff>,
+    succinct: bool,
+) {
+    let (levels, details) = language_support_tables(map, succinct);
+    println!("{}", levels);
+    // Detailed differences
+    println!("\nLanguage differences:\n");
+    println!("{}", details);
+}
+
+/// Build tables of support level counts, and of languages whose support differs
+pub(super) fn language_support_tables(
+    map: &BTreeMap<String, diffenator3_lib::languages::LanguageDiff>,
+    succinct: bool,
+) -> (Table, Table) {
     // Supported status table
     let mut builder = tabled::builder::Builder::default();
     builder.push_record(vec!["Support Level", "Font A", "Font B"]);
@@ -168,11 +347,8 @@ fn report_language_support(map: &BTreeMap<String, crate::languages::LanguageDiff
         }
         builder.push_record(vec![level, &count_a.to_string(), &count_b.to_string()]);
     }
-    let mut table = builder.build();
-    table.with(Style::markdown());
-    println!("{}", table);
-    // Detailed differences
-    println!("\nLanguage differences:\n");
+    let mut levels = builder.build();
+    levels.with(Style::markdown());
     let mut rows: Vec<DetailsRow> = vec![];
     for (lang, details) in map.iter() {
         if succinct && details.level_a == details.level_b {
@@ -201,7 +377,7 @@ fn report_language_support(map: &BTreeMap<String, crate::languages::LanguageDiff
             glyphs_needed: details.fixes_b as u64,
         });
     }
-    let mut table = Table::new(rows);
-    table.with(Style::markdown());
-    println!("{}", table);
+    let mut details = Table::new(rows);
+    details.with(Style::markdown());
+    (levels, details)
 }
@@ -0,0 +1,561 @@
+//! Running a full comparison of two fonts
+//!
+//! [Comparison] drives all of the tests which diffenator3 knows about -
+//! tables, kerning, encoded glyphs, language support, and metrics and glyph
+//! and word rendering at each location in the designspace - and collects the
+//! results into a [Report]:
+//!
+//! ```no_run
+//! # use diffenator3_lib::{comparison::Comparison, dfont::DFont};
+//! # let (bytes_a, bytes_b) = (vec![], vec![]);
+//! let report = Comparison::new(DFont::new(&bytes_a)?, DFont::new(&bytes_b)?)
+//!     .kerns(false)
+//!     .instance("*")
+//!     .on_progress(|progress| eprintln!("{:?}", progress))
+//!     .run();
+//! # Ok::<(), diffenator3_lib::Error>(())
+//! ```
+//!
+//! Problems with part of a font don't stop the comparison: tables which
+//! can't be parsed are noted in the table diff, and tests which can't be run
+//! are listed in the report's `errors` (or a location's, for glyphs and
+//! words which can't be rendered there).
+use crate::{
+    dfont::DFont,
+    instances::instance_changes,
+    interpolation::interpolation_check,
+    languages::diff_languages,
+    metrics::metric_differences,
+    palettes::palette_changes,
+    render::{
+        encodedglyphs::{encoded_glyph_differences, CmapDiff},
+        word_differences,
+        wordlists::CustomWordList,
+    },
+    report::{LocationResult, Report},
+    sampling::{self, AxisExtents},
+    setting::{AxisMap, Setting},
+    structs::InstanceChangeKind,
+};
+use indexmap::IndexSet;
+use itertools::Itertools;
+use skrifa::{setting::VariationSetting, MetadataProvider, Tag};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use ttj::{jsondiff::Substantial, kern_diff, table_diff};
+
+/// How far a [Comparison] has got
+#[derive(Debug, Clone, Copy)]
+pub enum Progress<'a> {
+    /// Comparing the binary tables
+    Tables,
+    /// Comparing the kerning
+    Kerns,
+    /// Comparing language support
+    Languages,
+    /// Searching the designspace for locations to test
+    Sampling,
+    /// Checking interpolation between masters
+    Interpolation,
+    /// Starting the tests at a location; `index` counts from zero
+    Location {
+        name: &'a str,
+        index: usize,
+        count: usize,
+    },
+    /// Starting to render a list of words (or encoded glyphs)
+    WordList { name: &'a str, words: usize },
+    /// Finished with one word of the current list
+    ///
+    /// Words are rendered in parallel, so this may be reported from any thread.
+    Word,
+}
+
+/// A function which is told about a [Comparison]'s [Progress]
+pub type ProgressCallback<'a> = dyn Fn(Progress) + Sync + 'a;
+
+/// A comparison of two fonts, set up with a builder and then [run](Comparison::run)
+///
+/// By default all tests except the interpolation check are run, at the
+/// default location only.
+pub struct Comparison<'a> {
+    font_a: DFont,
+    font_b: DFont,
+    tables: bool,
+    kerns: bool,
+    glyphs: bool,
+    words: bool,
+    metrics: bool,
+    languages: bool,
+    interpolation: bool,
+    words_by_language: bool,
+    max_changes: usize,
+    no_match: bool,
+    instances: Vec<String>,
+    locations: Vec<Vec<VariationSetting>>,
+    given_settings: Vec<Setting>,
+    axis_map: AxisMap,
+    masters: bool,
+    stat: bool,
+    cross_product: bool,
+    splits: usize,
+    adaptive: Option<usize>,
+    wordlists: &'a [CustomWordList],
+    thresholds: HashMap<String, usize>,
+    progress: Box<ProgressCallback<'a>>,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(font_a: DFont, font_b: DFont) -> Self {
+        Comparison {
+            font_a,
+            font_b,
+            tables: true,
+            kerns: true,
+            glyphs: true,
+            words: true,
+            metrics: true,
+            languages: true,
+            interpolation: false,
+            words_by_language: false,
+            max_changes: 128,
+            no_match: false,
+            instances: vec![],
+            locations: vec![],
+            given_settings: vec![],
+            axis_map: AxisMap::default(),
+            masters: false,
+            stat: false,
+            cross_product: false,
+            splits: 1,
+            adaptive: None,
+            wordlists: &[],
+            thresholds: HashMap::new(),
+            progress: Box::new(|_| {}),
+        }
+    }
+
+    /// Compare the binary tables
+    pub fn tables(mut self, enabled: bool) -> Self {
+        self.tables = enabled;
+        self
+    }
+
+    /// Compare the kerning
+    pub fn kerns(mut self, enabled: bool) -> Self {
+        self.kerns = enabled;
+        self
+    }
+
+    /// Compare the encoded glyphs, and their images at each location
+    pub fn glyphs(mut self, enabled: bool) -> Self {
+        self.glyphs = enabled;
+        self
+    }
+
+    /// Compare images of words at each location
+    pub fn words(mut self, enabled: bool) -> Self {
+        self.words = enabled;
+        self
+    }
+
+    /// Compare font-wide metrics and advance widths at each location
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+
+    /// Look for glyphs which don't interpolate linearly between masters, in
+    /// either font (see [crate::interpolation])
+    pub fn interpolation(mut self, enabled: bool) -> Self {
+        self.interpolation = enabled;
+        self
+    }
+
+    /// Compare language support
+    pub fn languages(mut self, enabled: bool) -> Self {
+        self.languages = enabled;
+        self
+    }
+
+    /// Test words from each language's sample text instead of each script's word list
+    pub fn words_by_language(mut self, enabled: bool) -> Self {
+        self.words_by_language = enabled;
+        self
+    }
+
+    /// Give up reporting differences in a table after this many
+    pub fn max_changes(mut self, max_changes: usize) -> Self {
+        self.max_changes = max_changes;
+        self
+    }
+
+    /// Don't try to match glyph names between the fonts
+    pub fn no_match(mut self, no_match: bool) -> Self {
+        self.no_match = no_match;
+        self
+    }
+
+    /// Test at a named instance; `*` means all instances of both fonts
+    pub fn instance(mut self, instance: impl Into<String>) -> Self {
+        self.instances.push(instance.into());
+        self
+    }
+
+    /// Test at a location in user space (see [crate::setting::parse_location])
+    pub fn location(mut self, location: Vec<VariationSetting>) -> Self {
+        self.locations.push(location);
+        self
+    }
+
+    /// Test at a [Setting]; with [Setting::Split], font A and font B are
+    /// tested at different positions
+    pub fn setting(mut self, setting: Setting) -> Self {
+        self.given_settings.push(setting);
+        self
+    }
+
+    /// Translate each location into the second font's axes with this map,
+    /// for fonts whose axes differ
+    pub fn axis_map(mut self, axis_map: AxisMap) -> Self {
+        self.axis_map = axis_map;
+        self
+    }
+
+    /// Test at the default location and each master location
+    pub fn masters(mut self, enabled: bool) -> Self {
+        self.masters = enabled;
+        self
+    }
+
+    /// Test at each combination of the axis values in the `STAT` table of
+    /// either font (see [DFont::stat_locations])
+    pub fn stat(mut self, enabled: bool) -> Self {
+        self.stat = enabled;
+        self
+    }
+
+    /// Test at every combination of the min/default/max of each axis
+    ///
+    /// Each side of the default is divided into `splits` parts.
+    pub fn cross_product(mut self, splits: usize) -> Self {
+        self.cross_product = true;
+        self.splits = splits;
+        self
+    }
+
+    /// Search the designspace for the locations where the fonts differ most
+    ///
+    /// Starting from the masters and named instances, the outlines of the
+    /// encoded glyphs are compared at more and more locations, concentrating
+    /// on the regions where they differ; the `budget` locations with the
+    /// biggest differences are then tested.
+    pub fn adaptive(mut self, budget: usize) -> Self {
+        self.adaptive = Some(budget);
+        self
+    }
+
+    /// Also test these word lists
+    pub fn custom_wordlists(mut self, wordlists: &'a [CustomWordList]) -> Self {
+        self.wordlists = wordlists;
+        self
+    }
+
+    /// Word difference thresholds for particular scripts, keyed by ISO 15924 tag
+    pub fn thresholds(mut self, thresholds: HashMap<String, usize>) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Call `callback` as the comparison progresses
+    pub fn on_progress(mut self, callback: impl Fn(Progress) + Sync + 'a) -> Self {
+        self.progress = Box::new(callback);
+        self
+    }
+
+    /// The first font, as left after the last location tested
+    pub fn font_a(&self) -> &DFont {
+        &self.font_a
+    }
+
+    /// The second font, as left after the last location tested
+    pub fn font_b(&self) -> &DFont {
+        &self.font_b
+    }
+
+    /// Run all the requested tests
+    pub fn run(&mut self) -> Report {
+        let mut report = Report::default();
+
+        // Location-independent tests
+        if self.tables {
+            (self.progress)(Progress::Tables);
+            let table_diff = table_diff(
+                &self.font_a.fontref(),
+                &self.font_b.fontref(),
+                self.max_changes,
+                self.no_match,
+            );
+            if table_diff.is_something() {
+                report.tables = Some(table_diff);
+            }
+        }
+        if self.kerns {
+            (self.progress)(Progress::Kerns);
+            let kern_diff = kern_diff(
+                &self.font_a.fontref(),
+                &self.font_b.fontref(),
+                self.max_changes,
+                self.no_match,
+            );
+            if kern_diff.is_something() {
+                report.kerns = Some(kern_diff);
+            }
+        }
+        if self.glyphs {
+            report.cmap_diff = Some(CmapDiff::new(&self.font_a, &self.font_b));
+        }
+        if self.languages {
+            (self.progress)(Progress::Languages);
+            match diff_languages(&self.font_a, &self.font_b) {
+                Ok(languages) => report.languages = Some(languages),
+                Err(e) => report.errors.push(e.to_string()),
+            }
+        }
+        if self.font_a.is_variable() && self.font_b.is_variable() {
+            report.instances = instance_changes(&self.font_a, &self.font_b);
+        }
+        if self.glyphs || self.words {
+            // Palettes only matter to the colors glyphs and words are drawn in
+            report.palettes = palette_changes(&self.font_a, &self.font_b);
+        }
+        if self.interpolation {
+            (self.progress)(Progress::Interpolation);
+            match interpolation_check(&self.font_a, &self.font_b) {
+                Ok(interpolation) => report.interpolation = Some(interpolation),
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        if self.glyphs || self.words || self.metrics {
+            // Location-specific tests
+            let settings = self.settings();
+            let count = settings.len();
+            for (index, setting) in settings.into_iter().enumerate() {
+                let name = setting.name();
+                (self.progress)(Progress::Location {
+                    name: &name,
+                    index,
+                    count,
+                });
+                let counterparts = setting.counterparts(&self.font_a, &self.font_b);
+                let mut result = if let Err(e) =
+                    setting.set_on_fonts_mapped(&mut self.font_a, &mut self.font_b, &self.axis_map)
+                {
+                    LocationResult::from_error(name, e)
+                } else {
+                    self.test_at_location(name)
+                };
+                (result.instance_a, result.instance_b) = counterparts.unzip();
+                report.locations.push(result);
+            }
+
+            // If there's more than one, filter out the boring ones
+            if report.locations.len() > 1 {
+                report.locations.retain(|l| l.is_some());
+            }
+        }
+        report
+    }
+
+    fn test_at_location(&self, location: String) -> LocationResult {
+        // A static font stays where it is, so take the coordinates from
+        // the variable one
+        let located = if self.font_a.is_variable() || !self.font_b.is_variable() {
+            &self.font_a
+        } else {
+            &self.font_b
+        };
+        let mut result = LocationResult {
+            location,
+            coords: located
+                .location
+                .iter()
+                .map(|v| (v.selector.to_string(), v.value))
+                .collect(),
+            ..Default::default()
+        };
+        if self.glyphs {
+            result.glyphs = encoded_glyph_differences(
+                &self.font_a,
+                &self.font_b,
+                self.progress.as_ref(),
+                &mut result.errors,
+            );
+        }
+        if self.metrics {
+            result.metrics = metric_differences(&self.font_a, &self.font_b);
+        }
+        if self.words {
+            result.words = word_differences(
+                &self.font_a,
+                &self.font_b,
+                self.wordlists,
+                self.words_by_language,
+                &self.thresholds,
+                self.progress.as_ref(),
+                &mut result.errors,
+            );
+        }
+        result
+    }
+
+    /// The locations to set the fonts to, from the instances, locations,
+    /// masters, cross-product and adaptive sampling requested
+    fn settings(&self) -> Vec<Setting> {
+        let (font_a, font_b) = (&self.font_a, &self.font_b);
+        let mut settings = vec![];
+        for instance in &self.instances {
+            if instance == "*" {
+                // Add the union of instances from both fonts, leaving out
+                // the new names of renamed instances, which are compared
+                // under their old names
+                let renamed: Vec<String> = instance_changes(font_a, font_b)
+                    .into_iter()
+                    .filter(|change| change.change == InstanceChangeKind::Renamed)
+                    .filter_map(|change| change.name_b)
+                    .collect();
+                let mut instances: IndexSet<String> = font_a.instances().into_iter().collect();
+                instances.extend(
+                    font_b
+                        .instances()
+                        .into_iter()
+                        .filter(|instance| !renamed.contains(instance)),
+                );
+                settings.extend(instances.into_iter().map(Setting::from_instance));
+            } else {
+                settings.push(Setting::from_instance(instance.clone()));
+            }
+        }
+
+        for location in &self.locations {
+            settings.push(Setting::from_setting(location.clone()));
+        }
+        settings.extend(self.given_settings.iter().cloned());
+
+        if self.masters {
+            if let Ok(masters) = font_a.masters() {
+                settings.push(Setting::Default);
+                for master in masters.into_iter() {
+                    let setting = Setting::from_setting(master);
+                    settings.push(setting);
+                }
+            }
+        }
+
+        if self.stat {
+            // Locations from both fonts, in the first font's axes, named by
+            // the first font to have them
+            let mut seen = HashSet::new();
+            for (name, location) in font_a.stat_locations().into_iter().chain(
+                font_b
+                    .stat_locations()
+                    .into_iter()
+                    .map(|(name, location)| (name, self.axis_map.untranslate(&location))),
+            ) {
+                let mut key: Vec<(Tag, u32)> = location
+                    .iter()
+                    .map(|setting| (setting.selector, setting.value.to_bits()))
+                    .collect();
+                key.sort();
+                if seen.insert(key) {
+                    settings.push(Setting::Named(name, location));
+                }
+            }
+        }
+
+        if self.cross_product {
+            let axes_min_max = axis_extents(font_a, font_b, &self.axis_map);
+            let mut per_axis_splits: Vec<Vec<(Tag, f32)>> = vec![];
+
+            for (axis, tuple) in axes_min_max.into_iter() {
+                per_axis_splits.push(split_axis(&axis, tuple, self.splits))
+            }
+            per_axis_splits.dedup();
+            // Find the cartesian product of all axis/value iterators
+            for locations in per_axis_splits.into_iter().multi_cartesian_product() {
+                settings.push(Setting::from_setting(
+                    locations
+                        .into_iter()
+                        .map(|(a, v)| VariationSetting::new(a, v))
+                        .collect(),
+                ));
+            }
+        }
+
+        if let Some(budget) = self.adaptive {
+            (self.progress)(Progress::Sampling);
+            let axes = axis_extents(font_a, font_b, &self.axis_map);
+            if axes.is_empty() {
+                settings.push(Setting::Default);
+            } else {
+                settings.extend(
+                    sampling::sample(font_a, font_b, &axes, budget, &self.axis_map)
+                        .into_iter()
+                        .map(Setting::from_setting),
+                );
+            }
+        }
+
+        if settings.is_empty() {
+            // Add default setting
+            settings.push(Setting::Default);
+        }
+
+        // Deduplicate
+        settings.into_iter().unique().collect()
+    }
+}
+
+/// The min/default/max of every axis in either font, in tag order
+///
+/// Where both fonts have an axis, its range covers both, and the default is
+/// taken from the first font. Axes of the second font which the axis map
+/// covers are left out, since locations are given in the first font's axes.
+fn axis_extents(font_a: &DFont, font_b: &DFont, axis_map: &AxisMap) -> Vec<AxisExtents> {
+    let mut axes: BTreeMap<Tag, (f32, f32, f32)> = BTreeMap::new();
+    for (font, mapped) in [(font_b, true), (font_a, false)] {
+        for axis in font.fontref().axes().iter() {
+            if mapped && axis_map.covers(axis.tag()) {
+                continue;
+            }
+            let extents = (axis.min_value(), axis.default_value(), axis.max_value());
+            axes.entry(axis.tag())
+                .and_modify(|(min, default, max)| {
+                    *min = min.min(extents.0);
+                    *default = extents.1;
+                    *max = max.max(extents.2);
+                })
+                .or_insert(extents);
+        }
+    }
+    axes.into_iter().collect()
+}
+
+// Given an axis and a tuple of min/default/max, return a list of (axis, value) pairs
+// that split the axis into `split_count` parts.
+fn split_axis(axis: &Tag, tuple: (f32, f32, f32), split_count: usize) -> Vec<(Tag, f32)> {
+    let (min, default, max) = tuple;
+    let step = (default - min) / split_count as f32;
+    let mut splits: Vec<f32> = vec![min];
+    for i in 1..split_count {
+        splits.push(min + step * i as f32);
+    }
+    splits.push(default);
+    let step = (max - default) / split_count as f32;
+    for i in 1..split_count {
+        splits.push(default + step * i as f32);
+    }
+    splits.push(max);
+    splits.dedup();
+    splits.into_iter().map(|v| (*axis, v)).collect()
+}
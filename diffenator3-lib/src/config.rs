@@ -0,0 +1,309 @@
+// Shared configuration file code between diffenator3-cli and diff3proof
+use clap::{
+    builder::ValueHint, error::ErrorKind, parser::ValueSource, Arg, ArgAction, ArgMatches, Command,
+    Parser,
+};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+pub use toml::Table;
+
+/// Name of the project configuration file
+pub const CONFIG_FILENAME: &str = "diffenator3.toml";
+
+/// Look for a configuration file in the current directory and its parents
+pub fn find_config_file() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(CONFIG_FILENAME))
+        .find(|path| path.is_file())
+}
+
+/// Parse the command line, filling in options from a configuration file
+///
+/// The configuration file is given with `--config` (if the command has such
+/// an option) or found with [find_config_file]. Each key in the file (or in
+/// its `section` table, if given) names a command line option, either by its
+/// long flag (`max-changes = 64`, `no-tables = true`) or by the field it sets
+/// (`tables = false`). Options given explicitly on the command line take
+/// precedence over the file. Relative paths are resolved against the
+/// directory containing the file.
+///
+/// Keys listed in `extra_keys` are not treated as options; they are returned
+/// to the caller in a table, for settings which have no command line
+/// equivalent. If a subcommand is given, the file fills in only those
+/// options which the subcommand has.
+pub fn parse_with_config<T: Parser>(section: Option<&str>, extra_keys: &[&str]) -> (T, Table) {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let mut command = T::command();
+    let matches = command.clone().get_matches_from(&args);
+
+    let config_file = if command.get_arguments().any(|arg| arg.get_id() == "config") {
+        matches.get_one::<PathBuf>("config").cloned()
+    } else {
+        None
+    }
+    .or_else(find_config_file);
+    let Some(config_file) = config_file else {
+        return (
+            T::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()),
+            Table::new(),
+        );
+    };
+
+    let mut table = read_config_file(&config_file, section)
+        .unwrap_or_else(|e| command.error(ErrorKind::Io, e).exit());
+    let extras: Table = extra_keys
+        .iter()
+        .filter_map(|key| table.remove_entry(*key))
+        .collect();
+    let base_dir = config_file.parent().unwrap_or(Path::new("."));
+    // Options go before the first argument, or the subcommand's first
+    let (target, target_matches, position) = match matches.subcommand() {
+        Some((name, sub_matches)) => {
+            let subcommand = command
+                .find_subcommand(name)
+                .expect("matched subcommand exists");
+            table = table
+                .into_iter()
+                .filter(|(key, _)| find_arg(subcommand, key).is_some())
+                .collect();
+            let position = args.iter().position(|arg| arg == name).unwrap_or(0) + 1;
+            (subcommand, sub_matches, position)
+        }
+        None => (&command, &matches, 1),
+    };
+    let config_args =
+        config_to_args(target, target_matches, &table, base_dir).unwrap_or_else(|e| {
+            command
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!("in {}: {}", config_file.display(), e),
+                )
+                .exit()
+        });
+    // Options from the file go first, so that explicit negations on the
+    // command line (e.g. `--tables` against `tables = false`) win.
+    let mut combined = args[..position].to_vec();
+    combined.extend(config_args);
+    combined.extend(args.into_iter().skip(position));
+    (T::parse_from(combined), extras)
+}
+
+fn read_config_file(path: &Path, section: Option<&str>) -> Result<Table, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let mut table: Table = contents
+        .parse()
+        .map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))?;
+    match section {
+        Some(section) => match table.remove(section) {
+            Some(toml::Value::Table(section_table)) => Ok(section_table),
+            Some(_) => Err(format!(
+                "[{}] in {} should be a table",
+                section,
+                path.display()
+            )),
+            None => Ok(Table::new()),
+        },
+        None => Ok(table),
+    }
+}
+
+fn given_on_command_line(matches: &ArgMatches, arg: &Arg) -> bool {
+    matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+}
+
+/// Find the option a configuration key names, either by its long flag or by
+/// the field it sets
+///
+/// Also returns the value which means "pass this flag"; a field such as
+/// `tables` is set to false by passing `--no-tables`.
+fn find_arg<'a>(command: &'a Command, key: &str) -> Option<(&'a Arg, bool)> {
+    let by_long = command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key));
+    let by_id = command
+        .get_arguments()
+        .find(|arg| arg.get_id().as_str() == key.replace('-', "_"));
+    match (by_id, by_long) {
+        (Some(arg), _) => Some((arg, !matches!(arg.get_action(), ArgAction::SetFalse))),
+        (None, Some(arg)) => Some((arg, true)),
+        (None, None) => None,
+    }
+}
+
+fn config_to_args(
+    command: &Command,
+    matches: &ArgMatches,
+    table: &Table,
+    base_dir: &Path,
+) -> Result<Vec<OsString>, String> {
+    let mut args = vec![];
+    for (key, value) in table.iter() {
+        let (arg, flag_value) =
+            find_arg(command, key).ok_or_else(|| format!("unknown option '{}'", key))?;
+        if arg.is_positional() || arg.get_id() == "config" {
+            return Err(format!("'{}' can only be given on the command line", key));
+        }
+        if given_on_command_line(matches, arg) {
+            continue;
+        }
+        let long = arg.get_long().unwrap_or(key);
+        match arg.get_action() {
+            ArgAction::SetTrue | ArgAction::SetFalse => {
+                let value = value
+                    .as_bool()
+                    .ok_or_else(|| format!("'{}' should be true or false", key))?;
+                if value == flag_value {
+                    args.push(format!("--{}", long).into());
+                }
+            }
+            _ => {
+                let values = match value {
+                    toml::Value::Array(values) => values.iter().collect(),
+                    _ => vec![value],
+                };
+                for value in values {
+                    let value = match value {
+                        toml::Value::String(s) => s.clone(),
+                        toml::Value::Integer(_) | toml::Value::Float(_) => value.to_string(),
+                        _ => return Err(format!("'{}' has an unsupported value", key)),
+                    };
+                    let value = match arg.get_value_hint() {
+                        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath => {
+                            base_dir.join(value).into_os_string()
+                        }
+                        _ => value.into(),
+                    };
+                    let mut arg = OsString::from(format!("--{}=", long));
+                    arg.push(value);
+                    args.push(arg);
+                }
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("test")
+            .arg(Arg::new("config").long("config"))
+            .arg(Arg::new("max_changes").long("max-changes"))
+            .arg(
+                Arg::new("tables")
+                    .long("no-tables")
+                    .action(ArgAction::SetFalse),
+            )
+            .arg(Arg::new("quiet").long("quiet").action(ArgAction::SetTrue))
+            .arg(
+                Arg::new("templates")
+                    .long("templates")
+                    .value_hint(ValueHint::DirPath),
+            )
+            .arg(
+                Arg::new("location")
+                    .long("location")
+                    .action(ArgAction::Append),
+            )
+            .arg(Arg::new("font1").required(true))
+    }
+
+    fn args(config: &str, command_line: &[&str]) -> Result<Vec<OsString>, String> {
+        let matches =
+            command().get_matches_from(std::iter::once("test").chain(command_line.iter().copied()));
+        let table: Table = config.parse().unwrap();
+        config_to_args(&command(), &matches, &table, Path::new("/project"))
+    }
+
+    #[test]
+    fn keys_become_options() {
+        assert_eq!(
+            args(
+                "max-changes = 64\nquiet = true\nlocation = [\"wght=400\", \"wght=700\"]",
+                &["a.ttf"]
+            )
+            .unwrap(),
+            vec![
+                "--location=wght=400",
+                "--location=wght=700",
+                "--max-changes=64",
+                "--quiet"
+            ]
+        );
+        // Keys may also name the field an option sets
+        assert_eq!(
+            args("max_changes = 1.5", &["a.ttf"]).unwrap(),
+            vec!["--max-changes=1.5"]
+        );
+    }
+
+    #[test]
+    fn flags_follow_their_field_or_their_name() {
+        assert_eq!(
+            args("tables = false", &["a.ttf"]).unwrap(),
+            vec!["--no-tables"]
+        );
+        assert!(args("tables = true", &["a.ttf"]).unwrap().is_empty());
+        assert_eq!(
+            args("no-tables = true", &["a.ttf"]).unwrap(),
+            vec!["--no-tables"]
+        );
+        assert!(args("quiet = false", &["a.ttf"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_config_file() {
+        let mut expected = OsString::from("--templates=");
+        expected.push(Path::new("/project").join("templates"));
+        assert_eq!(
+            args("templates = \"templates\"", &["a.ttf"]).unwrap(),
+            vec![expected]
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let command_line = ["--max-changes", "5", "--no-tables", "a.ttf"];
+        let config = "max-changes = 64\ntables = false\nquiet = true";
+        let config_args = args(config, &command_line).unwrap();
+        assert_eq!(config_args, vec!["--quiet"]);
+
+        let mut combined: Vec<OsString> = vec!["test".into()];
+        combined.extend(config_args);
+        combined.extend(command_line.iter().map(OsString::from));
+        let matches = command().get_matches_from(combined);
+        assert_eq!(matches.get_one::<String>("max_changes").unwrap(), "5");
+        assert!(!matches.get_flag("tables"));
+        assert!(matches.get_flag("quiet"));
+    }
+
+    #[test]
+    fn bad_keys_are_errors() {
+        assert_eq!(
+            args("colour = true", &["a.ttf"]).unwrap_err(),
+            "unknown option 'colour'"
+        );
+        assert_eq!(
+            args("font1 = \"b.ttf\"", &["a.ttf"]).unwrap_err(),
+            "'font1' can only be given on the command line"
+        );
+        assert_eq!(
+            args("config = \"other.toml\"", &["a.ttf"]).unwrap_err(),
+            "'config' can only be given on the command line"
+        );
+        assert_eq!(
+            args("quiet = 1", &["a.ttf"]).unwrap_err(),
+            "'quiet' should be true or false"
+        );
+        assert_eq!(
+            args("max-changes = { a = 1 }", &["a.ttf"]).unwrap_err(),
+            "'max-changes' has an unsupported value"
+        );
+    }
+}
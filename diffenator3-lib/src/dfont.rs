@@ -1,5 +1,10 @@
-use crate::setting::parse_location;
-use read_fonts::{types::NameId, FontRef, ReadError, TableProvider};
+use crate::{error::Error, setting::parse_location};
+use itertools::Itertools;
+use read_fonts::{
+    tables::stat::{AxisValue, AxisValueTableFlags},
+    types::{NameId, Tag},
+    FontRef, ReadError, TableProvider,
+};
 use skrifa::{instance::Location, setting::VariationSetting, MetadataProvider};
 use std::{
     borrow::Cow,
@@ -23,7 +28,10 @@ pub struct DFont {
 
 impl DFont {
     /// Create a new DFont from a byte slice
-    pub fn new(string: &[u8]) -> Self {
+    ///
+    /// Fails if the data can't be parsed as a font.
+    pub fn new(string: &[u8]) -> Result<Self, Error> {
+        FontRef::new(string)?;
         let backing: Vec<u8> = string.to_vec();
 
         let mut fnt = DFont {
@@ -34,7 +42,7 @@ impl DFont {
         };
         let cmap = fnt.fontref().charmap();
         fnt.codepoints = cmap.mappings().map(|(cp, _)| cp).collect();
-        fnt
+        Ok(fnt)
     }
 
     /// Normalize the location
@@ -66,6 +74,33 @@ impl DFont {
             .collect()
     }
 
+    /// The font's named instances, with their locations in user space
+    pub fn instance_locations(&self) -> Vec<(String, Vec<VariationSetting>)> {
+        let axes: Vec<_> = self.fontref().axes().iter().map(|a| a.tag()).collect();
+        self.fontref()
+            .named_instances()
+            .iter()
+            .map(|ni| {
+                let name = self
+                    .fontref()
+                    .localized_strings(ni.subfamily_name_id())
+                    .english_or_first()
+                    .map_or_else(|| "Unknown".to_string(), |s| s.chars().collect());
+                let location = axes
+                    .iter()
+                    .zip(ni.user_coords())
+                    .map(|(tag, value)| (*tag, value).into())
+                    .collect();
+                (name, location)
+            })
+            .collect()
+    }
+
+    /// Whether the font has any variation axes
+    pub fn is_variable(&self) -> bool {
+        !self.fontref().axes().is_empty()
+    }
+
     /// Set the location of the font to a given named instance
     pub fn set_instance(&mut self, instance: &str) -> Result<(), String> {
         let instance = self
@@ -92,6 +127,7 @@ impl DFont {
     }
 
     pub fn fontref(&self) -> FontRef<'_> {
+        // The data was checked when the DFont was created
         FontRef::new(&self.backing).expect("Couldn't parse font")
     }
     pub fn family_name(&self) -> String {
@@ -108,6 +144,30 @@ impl DFont {
             .map_or_else(|| "Regular".to_string(), |s| s.chars().collect())
     }
 
+    /// The PostScript name of the font, if it has one
+    pub fn postscript_name(&self) -> Option<String> {
+        self.fontref()
+            .localized_strings(NameId::POSTSCRIPT_NAME)
+            .english_or_first()
+            .map(|s| s.chars().collect())
+    }
+
+    /// The typographic family and subfamily names of the font
+    ///
+    /// These fall back to the legacy family and subfamily names when absent.
+    pub fn typographic_family_and_style(&self) -> (String, String) {
+        let name = |id: NameId| -> Option<String> {
+            self.fontref()
+                .localized_strings(id)
+                .english_or_first()
+                .map(|s| s.chars().collect())
+        };
+        (
+            name(NameId::TYPOGRAPHIC_FAMILY_NAME).unwrap_or_else(|| self.family_name()),
+            name(NameId::TYPOGRAPHIC_SUBFAMILY_NAME).unwrap_or_else(|| self.style_name()),
+        )
+    }
+
     /// The axes of the font
     ///
     /// Returns a map from axis tag to (min, default, max) values
@@ -143,22 +203,157 @@ impl DFont {
     /// This is derived heuristically from locations of shared tuples in the `gvar` table.
     /// This should work well enough for most "normal" fonts.
     pub fn masters(&self) -> Result<Vec<Vec<VariationSetting>>, ReadError> {
+        Ok(self
+            .master_coords()?
+            .iter()
+            .flat_map(|location| self.fontref().denormalize_location(location))
+            .collect())
+    }
+
+    /// Returns the master locations in the font, as normalized coordinates
+    /// for each axis
+    ///
+    /// See [DFont::masters] for how these are found.
+    pub fn master_coords(&self) -> Result<Vec<Vec<f32>>, ReadError> {
         let gvar = self.fontref().gvar()?;
         let tuples = gvar.shared_tuples()?.tuples();
-        let peaks: Vec<Vec<VariationSetting>> = tuples
+        Ok(tuples
             .iter()
             .flatten()
-            .flat_map(|tuple| {
-                let location = tuple
+            .map(|tuple| {
+                tuple
                     .values()
                     .iter()
                     .map(|x| x.get().to_f32())
-                    .collect::<Vec<f32>>();
-                self.fontref().denormalize_location(&location)
+                    .collect::<Vec<f32>>()
             })
-            .collect();
-        Ok(peaks)
+            .collect())
     }
+
+    /// Returns the axis values described in the `STAT` table
+    ///
+    /// Each value has a name, such as "Bold" or "Condensed", and a location
+    /// on one axis or (for format 4 values) several; for format 2 values,
+    /// which cover a range, the location is the nominal value.
+    pub fn stat_axis_values(&self) -> Result<Vec<StatAxisValue>, ReadError> {
+        let stat = self.fontref().stat()?;
+        let axes = stat.design_axes()?;
+        let axis_tag = |index: u16| axes.get(index as usize).map(|a| a.axis_tag());
+        let Some(values) = stat.offset_to_axis_values().transpose()? else {
+            return Ok(vec![]);
+        };
+        let mut result = vec![];
+        for value in values.axis_values().iter() {
+            let value = value?;
+            let location: Vec<(Option<Tag>, f32)> = match &value {
+                AxisValue::Format1(v) => vec![(axis_tag(v.axis_index()), v.value().to_f32())],
+                AxisValue::Format2(v) => {
+                    vec![(axis_tag(v.axis_index()), v.nominal_value().to_f32())]
+                }
+                AxisValue::Format3(v) => vec![(axis_tag(v.axis_index()), v.value().to_f32())],
+                AxisValue::Format4(v) => v
+                    .axis_values()
+                    .iter()
+                    .map(|r| (axis_tag(r.axis_index()), r.value().to_f32()))
+                    .collect(),
+            };
+            let name = self
+                .fontref()
+                .localized_strings(value.value_name_id())
+                .english_or_first()
+                .map_or_else(String::new, |s| s.chars().collect());
+            result.push(StatAxisValue {
+                name,
+                location: location
+                    .into_iter()
+                    .filter_map(|(tag, value)| Some((tag?, value).into()))
+                    .collect(),
+                elidable: value
+                    .flags()
+                    .contains(AxisValueTableFlags::ELIDABLE_AXIS_VALUE_NAME),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Returns the locations described by the `STAT` table, with their names
+    ///
+    /// These are every combination of the single-axis values on the font's
+    /// axes, named from the values' names leaving out elidable ones (as
+    /// "Condensed Bold"), plus the locations of format 4 values; axes with
+    /// no values stay at their defaults.
+    pub fn stat_locations(&self) -> Vec<(String, Vec<VariationSetting>)> {
+        let Ok(values) = self.stat_axis_values() else {
+            return vec![];
+        };
+        let axes: Vec<Tag> = self.fontref().axes().iter().map(|a| a.tag()).collect();
+        let mut per_axis: Vec<Vec<&StatAxisValue>> = vec![vec![]; axes.len()];
+        let mut locations = vec![];
+        for value in values.iter() {
+            if !value.location.iter().all(|v| axes.contains(&v.selector)) {
+                continue;
+            }
+            match value.location.as_slice() {
+                [] => {}
+                [single] => {
+                    let Some(index) = axes.iter().position(|tag| *tag == single.selector) else {
+                        continue;
+                    };
+                    if !per_axis[index]
+                        .iter()
+                        .any(|v| v.location[0].value == single.value)
+                    {
+                        per_axis[index].push(value);
+                    }
+                }
+                _ => locations.push((value.name.clone(), value.location.clone())),
+            }
+        }
+        let elided_name = self
+            .fontref()
+            .stat()
+            .ok()
+            .and_then(|stat| stat.elided_fallback_name_id())
+            .and_then(|id| {
+                self.fontref()
+                    .localized_strings(id)
+                    .english_or_first()
+                    .map(|s| s.chars().collect())
+            })
+            .unwrap_or_else(|| "Regular".to_string());
+        per_axis.retain(|values| !values.is_empty());
+        if !per_axis.is_empty() {
+            for combination in per_axis.into_iter().multi_cartesian_product() {
+                let name = combination
+                    .iter()
+                    .filter(|v| !v.elidable)
+                    .map(|v| v.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                locations.push((
+                    if name.is_empty() {
+                        elided_name.clone()
+                    } else {
+                        name
+                    },
+                    combination.iter().map(|v| v.location[0]).collect(),
+                ));
+            }
+        }
+        locations
+    }
+}
+
+/// A named position on one or more axes, from the `STAT` table
+#[derive(Debug, Clone)]
+pub struct StatAxisValue {
+    /// The name of the value, e.g. "Bold"
+    pub name: String,
+    /// The location in user space
+    pub location: Vec<VariationSetting>,
+    /// Whether the name is left out when naming a combination of values
+    /// (as "Regular" usually is)
+    pub elidable: bool,
 }
 
 type InstancePositions = Vec<(String, HashMap<String, f32>)>;
@@ -0,0 +1,513 @@
+//! Everything that changed about each glyph, in one place
+//!
+//! Changes to a glyph are spread across a report: its advance in `hmtx`,
+//! its class in `GDEF`, its anchors and kerning in `GPOS`, the rules it
+//! takes part in in `GSUB`, and its images at each location. A
+//! [GlyphDossier] gathers them up, by walking the table and kerning diffs
+//! for the glyph's name, and adds changes to its outline and bounding box,
+//! which the table diffs leave out.
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    dfont::DFont,
+    report::Report,
+    sampling::points,
+    structs::{GlyphChange, GlyphChangeKind, GlyphDossier, GlyphRender},
+};
+use serde_json::{json, Map, Value};
+use skrifa::{
+    instance::{Location, LocationRef, Size},
+    outline::OutlineGlyphCollection,
+    GlyphId, MetadataProvider,
+};
+use ttj::namemap::NameMap;
+
+/// Tables whose diffs mention glyphs by name
+const GLYPH_TABLES: [&str; 6] = ["hmtx", "vmtx", "cmap", "GDEF", "GPOS", "GSUB"];
+
+/// Points which move less than this, in font units, are left alone
+const TOLERANCE: f32 = 0.5;
+
+/// Gather the changes to each glyph from a report, and compare the glyphs'
+/// outlines at the default location
+///
+/// Only glyphs with at least one change get a dossier; they are sorted by
+/// name.
+pub fn glyph_dossiers(report: &Report, font_a: &DFont, font_b: &DFont) -> Vec<GlyphDossier> {
+    let glyphs_a = Glyphs::new(font_a);
+    let glyphs_b = Glyphs::new(font_b);
+    let names: HashSet<&str> = glyphs_a
+        .ids
+        .keys()
+        .chain(glyphs_b.ids.keys())
+        .map(|name| name.as_str())
+        .collect();
+
+    let mut changes: BTreeMap<String, Vec<GlyphChange>> = BTreeMap::new();
+    if let Some(tables) = report.tables.as_ref().and_then(|t| t.as_object()) {
+        for table in GLYPH_TABLES {
+            if let Some(diff) = tables.get(table) {
+                collect(diff, table, &names, &mut changes);
+            }
+        }
+    }
+    if let Some(kerns) = report.kerns.as_ref().and_then(|k| k.as_object()) {
+        for (pair, diff) in kerns.iter() {
+            // A pair's diff is either a leaf or split by direction, e.g. `x`
+            let mut leaves = vec![];
+            leaves_of(diff, pair, &mut leaves);
+            for glyph in pair.split('/').filter(|g| names.contains(g)) {
+                for (path, leaf) in leaves.iter() {
+                    changes
+                        .entry(glyph.to_string())
+                        .or_default()
+                        .push(GlyphChange {
+                            kind: GlyphChangeKind::Kerning,
+                            path: path.clone(),
+                            value_a: side(leaf, 0),
+                            value_b: side(leaf, 1),
+                        });
+                }
+            }
+        }
+    }
+    for (name, id_a) in glyphs_a.ids.iter() {
+        if let Some(id_b) = glyphs_b.ids.get(name) {
+            let outline_changes = glyphs_a.compare(*id_a, &glyphs_b, *id_b);
+            if !outline_changes.is_empty() {
+                changes
+                    .entry(name.clone())
+                    .or_default()
+                    .extend(outline_changes);
+            }
+        }
+    }
+
+    let mut renders: BTreeMap<String, Vec<GlyphRender>> = BTreeMap::new();
+    for location in report.locations.iter() {
+        for glyph in location.glyphs.iter() {
+            let Some(name) = glyphs_a
+                .name_of(&glyph.string)
+                .or_else(|| glyphs_b.name_of(&glyph.string))
+            else {
+                continue;
+            };
+            renders.entry(name).or_default().push(GlyphRender {
+                location: location.location.clone(),
+                differing_pixels: glyph.differing_pixels,
+            });
+        }
+    }
+
+    let mut glyphs: Vec<String> = changes.keys().chain(renders.keys()).cloned().collect();
+    glyphs.sort();
+    glyphs.dedup();
+    glyphs
+        .into_iter()
+        .map(|glyph| {
+            let codepoint = glyphs_a
+                .codepoints
+                .get(&glyph)
+                .or_else(|| glyphs_b.codepoints.get(&glyph))
+                .copied();
+            GlyphDossier {
+                string: codepoint.and_then(char::from_u32).map(|c| c.to_string()),
+                unicode: codepoint.map(|cp| format!("U+{:04X}", cp)),
+                changes: changes.remove(&glyph).unwrap_or_default(),
+                renders: renders.remove(&glyph).unwrap_or_default(),
+                glyph,
+            }
+        })
+        .collect()
+}
+
+/// The glyphs of a font, by name
+struct Glyphs<'a> {
+    ids: HashMap<String, GlyphId>,
+    codepoints: HashMap<String, u32>,
+    names: NameMap,
+    font: &'a DFont,
+    outlines: OutlineGlyphCollection<'a>,
+}
+
+impl<'a> Glyphs<'a> {
+    fn new(font: &'a DFont) -> Self {
+        let fontref = font.fontref();
+        let names = NameMap::new(&fontref);
+        let ids: HashMap<String, GlyphId> = (1..=names.len() as u32)
+            .map(|gid| (names.get(GlyphId::new(gid)), GlyphId::new(gid)))
+            .collect();
+        let mut codepoints = HashMap::new();
+        for (codepoint, gid) in fontref.charmap().mappings() {
+            codepoints.entry(names.get(gid)).or_insert(codepoint);
+        }
+        Glyphs {
+            ids,
+            codepoints,
+            names,
+            font,
+            outlines: font.fontref().outline_glyphs(),
+        }
+    }
+
+    /// The name of the glyph encoded as `string`
+    fn name_of(&self, string: &str) -> Option<String> {
+        let mut chars = string.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        let gid = self.font.fontref().charmap().map(c)?;
+        Some(self.names.get(gid))
+    }
+
+    /// Changes to the bounding box and outline of a glyph, at the default
+    /// location
+    fn compare(&self, id: GlyphId, other: &Glyphs, other_id: GlyphId) -> Vec<GlyphChange> {
+        let mut changes = vec![];
+        let bounds = |glyphs: &Glyphs, id: GlyphId| {
+            glyphs
+                .font
+                .fontref()
+                .glyph_metrics(Size::unscaled(), LocationRef::default())
+                .bounds(id)
+                .map(|b| json!({"x_min": b.x_min, "y_min": b.y_min, "x_max": b.x_max, "y_max": b.y_max}))
+                .unwrap_or(Value::Null)
+        };
+        let (bounds_a, bounds_b) = (bounds(self, id), bounds(other, other_id));
+        if bounds_a != bounds_b {
+            changes.push(GlyphChange {
+                kind: GlyphChangeKind::Bounds,
+                path: "bounds".to_string(),
+                value_a: bounds_a,
+                value_b: bounds_b,
+            });
+        }
+
+        let location = Location::default();
+        let (Some(points_a), Some(points_b)) = (
+            self.outlines.get(id).and_then(|g| points(&g, &location)),
+            other
+                .outlines
+                .get(other_id)
+                .and_then(|g| points(&g, &location)),
+        ) else {
+            return changes;
+        };
+        if points_a.len() != points_b.len() {
+            changes.push(GlyphChange {
+                kind: GlyphChangeKind::Outline,
+                path: "points".to_string(),
+                value_a: json!(points_a.len() / 2),
+                value_b: json!(points_b.len() / 2),
+            });
+        } else {
+            // The point which moved furthest, and where it moved from and to
+            let largest_move = points_a
+                .chunks(2)
+                .zip(points_b.chunks(2))
+                .enumerate()
+                .map(|(index, (a, b))| {
+                    let distance = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+                    (index, a, b, distance)
+                })
+                .max_by(|x, y| x.3.total_cmp(&y.3));
+            if let Some((index, a, b, distance)) = largest_move {
+                if distance >= TOLERANCE {
+                    changes.push(GlyphChange {
+                        kind: GlyphChangeKind::Outline,
+                        path: format!(
+                            "point {} moved {} units",
+                            index,
+                            (distance * 10.0).round() / 10.0
+                        ),
+                        value_a: json!(a),
+                        value_b: json!(b),
+                    });
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Walk a table diff, attributing each difference to the glyphs it mentions
+///
+/// A key which names a glyph claims everything beneath it. A difference
+/// which doesn't sit under such a key (a whole lookup added, say, or a class
+/// which changed) is given to every glyph it mentions, cut down to the
+/// parts which mention that glyph.
+fn collect(
+    diff: &Value,
+    path: &str,
+    names: &HashSet<&str>,
+    changes: &mut BTreeMap<String, Vec<GlyphChange>>,
+) {
+    match diff {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter() {
+                let path = format!("{}/{}", path, key);
+                if names.contains(key.as_str()) {
+                    let mut leaves = vec![];
+                    leaves_of(value, &path, &mut leaves);
+                    for (path, leaf) in leaves {
+                        changes.entry(key.clone()).or_default().push(GlyphChange {
+                            kind: kind_of(&path, leaf),
+                            path,
+                            value_a: side(leaf, 0),
+                            value_b: side(leaf, 1),
+                        });
+                    }
+                } else {
+                    collect(value, &path, names, changes);
+                }
+            }
+        }
+        Value::Array(sides) if sides.len() == 2 => {
+            let mut mentioned = HashSet::new();
+            for value in sides.iter() {
+                mentions(value, names, &mut mentioned);
+            }
+            for glyph in mentioned {
+                changes
+                    .entry(glyph.to_string())
+                    .or_default()
+                    .push(GlyphChange {
+                        kind: kind_of(path, diff),
+                        path: path.to_string(),
+                        value_a: excerpt(&sides[0], glyph).unwrap_or(Value::Null),
+                        value_b: excerpt(&sides[1], glyph).unwrap_or(Value::Null),
+                    });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The leaves of a diff, which are `[before, after]` pairs, with their paths
+fn leaves_of<'a>(diff: &'a Value, path: &str, leaves: &mut Vec<(String, &'a Value)>) {
+    match diff {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter() {
+                leaves_of(value, &format!("{}/{}", path, key), leaves);
+            }
+        }
+        _ => leaves.push((path.to_string(), diff)),
+    }
+}
+
+/// One side of a `[before, after]` leaf
+fn side(leaf: &Value, index: usize) -> Value {
+    leaf.as_array()
+        .and_then(|sides| sides.get(index))
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+fn kind_of(path: &str, leaf: &Value) -> GlyphChangeKind {
+    let table = path.split('/').next().unwrap_or_default();
+    match table {
+        "hmtx" | "vmtx" => GlyphChangeKind::Metrics,
+        "cmap" => GlyphChangeKind::Encoding,
+        "GDEF" => GlyphChangeKind::Gdef,
+        "GSUB" => GlyphChangeKind::Substitution,
+        _ if path.contains("anchor") || leaf.to_string().contains("anchor") => {
+            GlyphChangeKind::Anchors
+        }
+        _ => GlyphChangeKind::Positioning,
+    }
+}
+
+/// Glyph names in a string, such as a layout rule
+fn tokens(string: &str) -> impl Iterator<Item = &str> {
+    string
+        .split(|c: char| c.is_whitespace() || "[]()',/".contains(c))
+        .filter(|token| !token.is_empty())
+}
+
+/// Add the glyphs mentioned anywhere in a value to `mentioned`
+fn mentions<'a>(value: &Value, names: &HashSet<&'a str>, mentioned: &mut HashSet<&'a str>) {
+    match value {
+        Value::String(string) => {
+            mentioned.extend(tokens(string).filter_map(|token| names.get(token).copied()))
+        }
+        Value::Array(values) => {
+            for value in values.iter() {
+                mentions(value, names, mentioned);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields.iter() {
+                if let Some(name) = names.get(key.as_str()) {
+                    mentioned.insert(name);
+                }
+                mentions(value, names, mentioned);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The parts of a value which mention `glyph`, or None if none do
+fn excerpt(value: &Value, glyph: &str) -> Option<Value> {
+    match value {
+        Value::String(string) => tokens(string)
+            .any(|token| token == glyph)
+            .then(|| value.clone()),
+        Value::Array(values) => {
+            let values: Vec<Value> = values.iter().filter_map(|v| excerpt(v, glyph)).collect();
+            (!values.is_empty()).then_some(Value::Array(values))
+        }
+        Value::Object(fields) => {
+            let fields: Map<String, Value> = fields
+                .iter()
+                .filter_map(|(key, value)| {
+                    if key == glyph {
+                        Some((key.clone(), value.clone()))
+                    } else {
+                        excerpt(value, glyph).map(|value| (key.clone(), value))
+                    }
+                })
+                .collect();
+            (!fields.is_empty()).then_some(Value::Object(fields))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{report::LocationResult, structs::GlyphDiff, test_fonts::nabla};
+
+    const NAMES: [&str; 7] = ["d", "e", "h", "l", "o", "r", "w"];
+
+    fn collected(table: &str, diff: Value) -> BTreeMap<String, Vec<GlyphChange>> {
+        let names: HashSet<&str> = NAMES.into_iter().collect();
+        let mut changes = BTreeMap::new();
+        collect(&diff, table, &names, &mut changes);
+        changes
+    }
+
+    #[test]
+    fn kinds_follow_the_table() {
+        let leaf = json!([1, 2]);
+        assert_eq!(kind_of("hmtx/e/width", &leaf), GlyphChangeKind::Metrics);
+        assert_eq!(kind_of("cmap/U+0065", &leaf), GlyphChangeKind::Encoding);
+        assert_eq!(
+            kind_of("GDEF/glyph_classes/e", &leaf),
+            GlyphChangeKind::Gdef
+        );
+        assert_eq!(
+            kind_of("GSUB/lookup_list/0", &leaf),
+            GlyphChangeKind::Substitution
+        );
+        assert_eq!(
+            kind_of("GPOS/lookup_list/1/base_anchors/o", &leaf),
+            GlyphChangeKind::Anchors
+        );
+        assert_eq!(
+            kind_of("GPOS/lookup_list/1", &json!([{"anchor": [250, 600]}, null])),
+            GlyphChangeKind::Anchors
+        );
+        assert_eq!(
+            kind_of("GPOS/lookup_list/0", &leaf),
+            GlyphChangeKind::Positioning
+        );
+    }
+
+    #[test]
+    fn excerpts_keep_what_mentions_the_glyph() {
+        let value = json!({
+            "rules": ["sub o l by d", "sub e by h"],
+            "o": {"x": 10},
+            "count": 2,
+        });
+        assert_eq!(
+            excerpt(&value, "o"),
+            Some(json!({"rules": ["sub o l by d"], "o": {"x": 10}}))
+        );
+        assert_eq!(excerpt(&value, "h"), Some(json!({"rules": ["sub e by h"]})));
+        // Names are matched as whole tokens
+        assert_eq!(excerpt(&json!("sub ee by w"), "e"), None);
+        assert_eq!(excerpt(&value, "w"), None);
+    }
+
+    #[test]
+    fn anchors_belong_to_the_glyph_named_above_them() {
+        let changes = collected(
+            "GPOS",
+            json!({"lookup_list": {"1": {"base_anchors": {"o": {"top": ["(250,600)", "(250,620)"]}}}}}),
+        );
+        assert_eq!(changes.keys().collect::<Vec<_>>(), vec!["o"]);
+        assert_eq!(
+            changes["o"],
+            vec![GlyphChange {
+                kind: GlyphChangeKind::Anchors,
+                path: "GPOS/lookup_list/1/base_anchors/o/top".to_string(),
+                value_a: json!("(250,600)"),
+                value_b: json!("(250,620)"),
+            }]
+        );
+    }
+
+    #[test]
+    fn rules_belong_to_every_glyph_they_mention() {
+        let changes = collected(
+            "GSUB",
+            json!({"lookup_list": {"2": [["sub e e by w"], ["sub e e by w", "sub o by r"]]}}),
+        );
+        assert_eq!(changes.keys().collect::<Vec<_>>(), vec!["e", "o", "r", "w"]);
+        let change = &changes["o"][0];
+        assert_eq!(change.kind, GlyphChangeKind::Substitution);
+        assert_eq!(change.path, "GSUB/lookup_list/2");
+        assert_eq!(change.value_a, Value::Null);
+        assert_eq!(change.value_b, json!(["sub o by r"]));
+        assert_eq!(changes["e"][0].value_a, json!(["sub e e by w"]));
+    }
+
+    #[test]
+    fn dossiers_gather_kerns_tables_and_renders() {
+        let data = nabla();
+        let font = DFont::new(&data).unwrap();
+        let report = Report {
+            tables: Some(json!({"hmtx": {"e": {"width": [500, 510]}}})),
+            kerns: Some(json!({"o/l": {"x": [-20, -30]}})),
+            locations: vec![LocationResult {
+                location: "Default".to_string(),
+                glyphs: vec![GlyphDiff {
+                    string: "o".to_string(),
+                    name: "LATIN SMALL LETTER O".to_string(),
+                    unicode: "U+006F".to_string(),
+                    differing_pixels: 40,
+                    palette: None,
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let dossiers = glyph_dossiers(&report, &font, &font);
+        let glyphs: Vec<&str> = dossiers.iter().map(|d| d.glyph.as_str()).collect();
+        assert_eq!(glyphs, vec!["e", "l", "o"]);
+
+        assert_eq!(dossiers[0].changes[0].kind, GlyphChangeKind::Metrics);
+        assert_eq!(dossiers[0].unicode.as_deref(), Some("U+0065"));
+        for dossier in &dossiers[1..] {
+            assert_eq!(
+                dossier.changes,
+                vec![GlyphChange {
+                    kind: GlyphChangeKind::Kerning,
+                    path: "o/l/x".to_string(),
+                    value_a: json!(-20),
+                    value_b: json!(-30),
+                }]
+            );
+        }
+        assert_eq!(
+            dossiers[2].renders,
+            vec![GlyphRender {
+                location: "Default".to_string(),
+                differing_pixels: 40,
+            }]
+        );
+    }
+}
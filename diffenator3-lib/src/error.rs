@@ -0,0 +1,87 @@
+//! Errors which can occur while comparing fonts
+//!
+//! Failures which only affect part of a comparison - a glyph which can't be
+//! drawn, a location whose renderer can't be set up - are recorded in the
+//! [crate::report::Report] as strings, and the rest of the comparison
+//! carries on.
+use read_fonts::ReadError;
+use skrifa::GlyphId;
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The font (or a table needed from it) could not be read
+    Font(ReadError),
+    /// A glyph has no outline which can be drawn
+    MissingOutline(GlyphId),
+    /// The font's language support could not be checked
+    Languages(String),
+    /// A file or directory could not be read or written
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The user's home directory, where templates are kept, could not be found
+    #[cfg(feature = "html")]
+    NoHomeDirectory,
+    /// A template could not be loaded or rendered
+    #[cfg(feature = "html")]
+    Template(tera::Error),
+}
+
+impl Error {
+    #[cfg(feature = "html")]
+    pub(crate) fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Font(e) => write!(f, "Couldn't read font: {}", e),
+            Error::MissingOutline(gid) => write!(f, "No outline for glyph {}", gid),
+            Error::Languages(e) => write!(f, "Couldn't check language support: {}", e),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            #[cfg(feature = "html")]
+            Error::NoHomeDirectory => write!(f, "Couldn't find home directory for templates"),
+            #[cfg(feature = "html")]
+            Error::Template(e) => {
+                // Tera's own messages are terse; the detail is in the causes
+                write!(f, "{}", e)?;
+                let mut cause = std::error::Error::source(e);
+                while let Some(e) = cause {
+                    write!(f, ": {}", e)?;
+                    cause = e.source();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Font(e) => Some(e),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReadError> for Error {
+    fn from(e: ReadError) -> Self {
+        Error::Font(e)
+    }
+}
+
+#[cfg(feature = "html")]
+impl From<tera::Error> for Error {
+    fn from(e: tera::Error) -> Self {
+        Error::Template(e)
+    }
+}
@@ -0,0 +1,174 @@
+//! Matching static fonts to locations in a variable font
+//!
+//! When a family of static fonts is replaced by a variable font, each static
+//! font should look the same as the variable font at the corresponding
+//! location. [match_static] finds that location: first by looking for a
+//! named instance with the same style name, then for one with the same
+//! OS/2 weight and width class, and finally by reading the style name as a
+//! combination of the variable font's `STAT` axis value names.
+use crate::{dfont::DFont, setting::Setting};
+use read_fonts::{tables::os2::SelectionFlags, TableProvider};
+use serde::{Deserialize, Serialize};
+use skrifa::{setting::VariationSetting, MetadataProvider, Tag};
+
+/// How a static font was matched to a location in a variable font
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchedBy {
+    /// A named instance has the same style name
+    InstanceName,
+    /// A named instance has the same OS/2 weight and width class
+    WeightAndWidth,
+    /// The style name is made up of `STAT` axis value names
+    Stat,
+}
+
+/// Find the location in `variable` which corresponds to `static_font`
+///
+/// The location is a named instance where there is one, and otherwise a
+/// location in user space.
+pub fn match_static(variable: &DFont, static_font: &DFont) -> Option<(Setting, MatchedBy)> {
+    let instances = variable.instance_locations();
+    let (_, style) = static_font.typographic_family_and_style();
+    if let Some((name, _)) = instances
+        .iter()
+        .find(|(name, _)| squash(name) == squash(&style))
+    {
+        return Some((
+            Setting::from_instance(name.clone()),
+            MatchedBy::InstanceName,
+        ));
+    }
+
+    if let Some(name) = match_weight_and_width(variable, static_font, &instances) {
+        return Some((Setting::from_instance(name), MatchedBy::WeightAndWidth));
+    }
+
+    let location = match_stat(variable, &style)?;
+    // Prefer the name of an instance at that location, if there is one
+    let setting = instances
+        .into_iter()
+        .find(|(_, instance)| same_location(variable, instance, &location))
+        .map_or_else(
+            || Setting::from_setting(location),
+            |(name, _)| Setting::from_instance(name),
+        );
+    Some((setting, MatchedBy::Stat))
+}
+
+/// A name in lower case, without spaces or hyphens, for comparing style names
+fn squash(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The user space coordinate of the OS/2 width classes, 1 to 9
+const WIDTH_CLASSES: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+
+fn width_percent(width_class: u16) -> f32 {
+    WIDTH_CLASSES
+        .get((width_class as usize).saturating_sub(1))
+        .copied()
+        .unwrap_or(100.0)
+}
+
+/// The weight, width and italic-ness of a font, from its OS/2 table
+fn os2_style(font: &DFont) -> Option<(f32, f32, bool)> {
+    let os2 = font.fontref().os2().ok()?;
+    Some((
+        os2.us_weight_class() as f32,
+        width_percent(os2.us_width_class()),
+        os2.fs_selection().contains(SelectionFlags::ITALIC),
+    ))
+}
+
+/// Find the instance with the same weight, width and italic-ness as the
+/// static font
+///
+/// Axes the variable font doesn't have take their values from its OS/2
+/// table instead.
+fn match_weight_and_width(
+    variable: &DFont,
+    static_font: &DFont,
+    instances: &[(String, Vec<VariationSetting>)],
+) -> Option<String> {
+    let (weight, width, italic) = os2_style(static_font)?;
+    let (default_weight, default_width, variable_italic) = os2_style(variable)?;
+    instances
+        .iter()
+        .find(|(name, location)| {
+            let coord = |tag: &[u8; 4]| {
+                location
+                    .iter()
+                    .find(|v| v.selector == Tag::new(tag))
+                    .map(|v| v.value)
+            };
+            let instance_italic = variable_italic
+                || coord(b"ital").is_some_and(|v| v >= 0.5)
+                || coord(b"slnt").is_some_and(|v| v != 0.0)
+                || squash(name).contains("italic")
+                || squash(name).contains("oblique");
+            coord(b"wght").unwrap_or(default_weight) == weight
+                && coord(b"wdth").unwrap_or(default_width) == width
+                && instance_italic == italic
+        })
+        .map(|(name, _)| name.clone())
+}
+
+/// Read a style name as a combination of the variable font's `STAT` axis
+/// value names, e.g. "SemiCondensed Bold Italic"
+///
+/// Axes not mentioned in the name are left at their defaults. Fails if any
+/// part of the name isn't accounted for.
+fn match_stat(variable: &DFont, style: &str) -> Option<Vec<VariationSetting>> {
+    let mut values = variable.stat_axis_values().ok()?;
+    // Match longer names first, so that "SemiBold" isn't taken as "Bold"
+    values.sort_by_key(|value| std::cmp::Reverse(squash(&value.name).len()));
+    let mut remaining = squash(style);
+    let mut location: Vec<VariationSetting> = vec![];
+    for value in values.iter() {
+        let name = squash(&value.name);
+        if name.is_empty()
+            || value
+                .location
+                .iter()
+                .any(|v| location.iter().any(|l| l.selector == v.selector))
+        {
+            continue;
+        }
+        if let Some(start) = remaining.find(&name) {
+            remaining.replace_range(start..start + name.len(), "");
+            location.extend(value.location.iter().copied());
+        }
+    }
+    // Elided names (usually "Regular") may be spelt out or left out
+    for value in values.iter().filter(|v| v.elidable) {
+        remaining = remaining.replacen(&squash(&value.name), "", 1);
+    }
+    if !remaining.is_empty() && remaining != "regular" {
+        return None;
+    }
+    let axes: Vec<Tag> = variable.fontref().axes().iter().map(|a| a.tag()).collect();
+    location.retain(|v| axes.contains(&v.selector));
+    Some(location)
+}
+
+/// Whether an instance is at a location, where axes missing from the
+/// location are at their defaults
+fn same_location(
+    variable: &DFont,
+    instance: &[VariationSetting],
+    location: &[VariationSetting],
+) -> bool {
+    let axes = variable.fontref().axes();
+    instance.iter().all(|v| {
+        let target = location
+            .iter()
+            .find(|l| l.selector == v.selector)
+            .map(|l| l.value)
+            .or_else(|| axes.get_by_tag(v.selector).map(|a| a.default_value()));
+        target.is_some_and(|target| (target - v.value).abs() < 0.01)
+    })
+}
@@ -1,71 +1,94 @@
 // Shared HTML templating code between diffenator3-cli and diff3proof
+use crate::{error::Error, structs::GlyphDossier};
 use serde_json::{json, Value};
-use std::{
-    error::Error,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 use tera::Context;
 pub use tera::Tera;
 use walkdir::WalkDir;
 
-pub(crate) fn die(doing: &str, err: impl Error) -> ! {
-    eprintln!("Error {}: {}", doing, err);
-    eprintln!();
-    eprintln!("Caused by:");
-    if let Some(cause) = err.source() {
-        for (i, e) in std::iter::successors(Some(cause), |e| (*e).source()).enumerate() {
-            eprintln!("   {}: {}", i, e);
-        }
-    }
-    std::process::exit(1);
-}
-
 #[allow(clippy::too_many_arguments)]
 pub fn gen_html(
-    font1_pb: &PathBuf,
-    font2_pb: &PathBuf,
+    font1_pb: &Path,
+    font2_pb: &Path,
     output_dir: &Path,
-    tera: Tera,
+    tera: &Tera,
     template_name: &str,
     template_variables: &Value,
     output_file: &str,
     point_size: u32,
-) -> ! {
+) -> Result<(), Error> {
     // Make output directory
     if !output_dir.exists() {
-        std::fs::create_dir(output_dir).expect("Couldn't create output directory");
+        std::fs::create_dir_all(output_dir).map_err(|e| Error::io(output_dir, e))?;
     }
 
     // Copy old font to output/old-<existing name>
-    let old_font = output_dir.join(format!(
-        "old-{}",
-        font1_pb.file_name().unwrap().to_str().unwrap()
-    ));
-    std::fs::copy(font1_pb, &old_font).expect("Couldn't copy old font");
-    let new_font = output_dir.join(format!(
-        "new-{}",
-        font2_pb.file_name().unwrap().to_str().unwrap()
-    ));
-    std::fs::copy(font2_pb, &new_font).expect("Couldn't copy new font");
+    let old_font = output_dir.join(format!("old-{}", file_name(font1_pb)));
+    std::fs::copy(font1_pb, &old_font).map_err(|e| Error::io(font1_pb, e))?;
+    let new_font = output_dir.join(format!("new-{}", file_name(font2_pb)));
+    std::fs::copy(font2_pb, &new_font).map_err(|e| Error::io(font2_pb, e))?;
+
+    write_html(
+        tera,
+        template_name,
+        &json!({
+            "report": template_variables,
+            "old_filename": file_name(&old_font),
+            "new_filename": file_name(&new_font),
+            "pt_size": point_size,
+        }),
+        &output_dir.join(output_file),
+    )
+}
 
-    let html = tera
-        .render(
-            template_name,
-            &Context::from_serialize(json!({
-                "report": template_variables,
-                "old_filename": old_font.file_name().unwrap().to_str().unwrap(),
-                "new_filename": new_font.file_name().unwrap().to_str().unwrap(),
+/// Write a page for each glyph dossier into `output_dir/glyphs`, named by
+/// its index; the pages use the fonts copied by [gen_html]
+pub fn gen_glyph_pages(
+    font1_pb: &Path,
+    font2_pb: &Path,
+    output_dir: &Path,
+    tera: &Tera,
+    dossiers: &[GlyphDossier],
+    point_size: u32,
+) -> Result<(), Error> {
+    let glyphs_dir = output_dir.join("glyphs");
+    if !glyphs_dir.exists() {
+        std::fs::create_dir_all(&glyphs_dir).map_err(|e| Error::io(&glyphs_dir, e))?;
+    }
+    for (index, dossier) in dossiers.iter().enumerate() {
+        write_html(
+            tera,
+            "glyph.html",
+            &json!({
+                "dossier": dossier,
+                "old_filename": format!("../old-{}", file_name(font1_pb)),
+                "new_filename": format!("../new-{}", file_name(font2_pb)),
                 "pt_size": point_size,
-            }))
-            .unwrap_or_else(|err| die("creating context", err)),
-        )
-        .unwrap_or_else(|err| die("rendering HTML", err));
+            }),
+            &glyphs_dir.join(format!("{}.html", index)),
+        )?;
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Render a template with the given variables and write it to a file
+pub fn write_html(
+    tera: &Tera,
+    template_name: &str,
+    variables: &Value,
+    output_file: &Path,
+) -> Result<(), Error> {
+    let html = tera.render(template_name, &Context::from_serialize(variables)?)?;
 
     // Write output
-    let output_file = output_dir.join(output_file);
-    println!("Writing output to {}", output_file.to_str().unwrap());
-    std::fs::write(output_file, html).expect("Couldn't write output file");
-    std::process::exit(0);
+    println!("Writing output to {}", output_file.display());
+    std::fs::write(output_file, html).map_err(|e| Error::io(output_file, e))
 }
 
 /// Instantiate a Tera template engine
@@ -74,49 +97,34 @@ pub fn gen_html(
 /// passes a directory for their own templates, these are used. Otherwise, the
 /// templates supplied in the binary are copied into the user's home directory,
 /// and this directory is used as the template root.
-pub fn template_engine(user_templates: Option<&String>, overwrite: bool) -> Tera {
-    let homedir = create_user_home_templates_directory(overwrite);
-    let mut tera = Tera::new(&format!("{}/*", homedir.to_str().unwrap())).unwrap_or_else(|e| {
-        println!("Problem parsing templates: {:?}", e);
-        std::process::exit(1)
-    });
+pub fn template_engine(user_templates: Option<&String>, overwrite: bool) -> Result<Tera, Error> {
+    let homedir = create_user_home_templates_directory(overwrite)?;
+    let mut tera = Tera::new(&format!("{}/*", homedir.to_string_lossy()))?;
     if let Some(template_dir) = user_templates {
         for entry in WalkDir::new(template_dir) {
-            if entry.as_ref().is_ok_and(|e| e.file_type().is_dir()) {
+            let entry = entry.map_err(|e| {
+                let path = e.path().unwrap_or(Path::new(template_dir)).to_path_buf();
+                Error::io(path, e.into())
+            })?;
+            if entry.file_type().is_dir() {
                 continue;
             }
-            let path = entry
-                .as_ref()
-                .unwrap_or_else(|e| {
-                    println!("Problem reading template path: {:}", e);
-                    std::process::exit(1)
-                })
-                .path();
-            if let Err(e) =
-                tera.add_template_file(path, path.strip_prefix(template_dir).unwrap().to_str())
-            {
-                println!("Problem adding template file: {:}", e);
-                std::process::exit(1)
-            }
-        }
-        if let Err(e) = tera.build_inheritance_chains() {
-            println!("Problem building inheritance chains: {:}", e);
-            std::process::exit(1)
+            let path = entry.path();
+            tera.add_template_file(path, path.strip_prefix(template_dir).unwrap().to_str())?;
         }
+        tera.build_inheritance_chains()?;
     }
-    tera
+    Ok(tera)
 }
 
-pub fn create_user_home_templates_directory(force: bool) -> PathBuf {
+pub fn create_user_home_templates_directory(force: bool) -> Result<PathBuf, Error> {
     let home = homedir::my_home()
-        .expect("Couldn't got home directory")
-        .expect("No home directory found");
+        .ok()
+        .flatten()
+        .ok_or(Error::NoHomeDirectory)?;
     let templates_dir = home.join(".diffenator3/templates");
     if !templates_dir.exists() {
-        std::fs::create_dir_all(&templates_dir).unwrap_or_else(|e| {
-            println!("Couldn't create {}: {}", templates_dir.to_str().unwrap(), e);
-            std::process::exit(1);
-        });
+        std::fs::create_dir_all(&templates_dir).map_err(|e| Error::io(&templates_dir, e))?;
     }
     let all_templates = [
         ["script.js", include_str!("../../templates/script.js")],
@@ -126,23 +134,21 @@ pub fn create_user_home_templates_directory(force: bool) -> PathBuf {
             "diffenator.html",
             include_str!("../../templates/diffenator.html"),
         ],
+        ["glyph.html", include_str!("../../templates/glyph.html")],
         [
             "diff3proof.html",
             include_str!("../../templates/diff3proof.html"),
         ],
+        [
+            "diffenator-index.html",
+            include_str!("../../templates/diffenator-index.html"),
+        ],
     ];
     for template in all_templates.iter() {
         let path = templates_dir.join(template[0]);
         if !path.exists() || force {
-            std::fs::write(&path, template[1]).unwrap_or_else(|e| {
-                println!(
-                    "Couldn't write template file {}: {}",
-                    path.to_str().unwrap(),
-                    e
-                );
-                std::process::exit(1)
-            });
+            std::fs::write(&path, template[1]).map_err(|e| Error::io(&path, e))?;
         }
     }
-    templates_dir
+    Ok(templates_dir)
 }
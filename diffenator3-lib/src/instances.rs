@@ -0,0 +1,385 @@
+//! Matching named instances between two fonts
+//!
+//! Instances are usually matched by name, but names change: "SemiBold"
+//! becomes "Semibold", or an instance is moved to a new location. When a
+//! name isn't found in the other font, instances are matched by PostScript
+//! name, and then by location: with the nearest instance, if it is no
+//! further away than [NEAREST_LIMIT]. [counterpart] finds the match for one
+//! instance, and [instance_changes] lists the instances which were added,
+//! removed, renamed or moved.
+use std::fmt::Display;
+
+use crate::{
+    dfont::DFont,
+    setting::Setting,
+    structs::{InstanceChange, InstanceChangeKind},
+};
+use read_fonts::types::NameId;
+use skrifa::{setting::VariationSetting, MetadataProvider, Tag};
+
+/// How far apart two instances may be and still be matched by location,
+/// as a fraction of the axis ranges (see [distance])
+pub const NEAREST_LIMIT: f32 = 0.1;
+
+/// A named instance, with what we need to know to match it
+struct Instance {
+    /// Its position in the font's `fvar` table
+    index: usize,
+    name: String,
+    postscript_name: Option<String>,
+    location: Vec<VariationSetting>,
+}
+
+fn named_instances(font: &DFont) -> Vec<Instance> {
+    let fontref = font.fontref();
+    let name = |id: NameId| {
+        fontref
+            .localized_strings(id)
+            .english_or_first()
+            .map(|s| s.chars().collect::<String>())
+    };
+    fontref
+        .named_instances()
+        .iter()
+        .zip(font.instance_locations())
+        .enumerate()
+        .map(|(index, (instance, (_, location)))| Instance {
+            index,
+            name: name(instance.subfamily_name_id()).unwrap_or_else(|| "Unknown".to_string()),
+            postscript_name: instance.postscript_name_id().and_then(name),
+            location,
+        })
+        .collect()
+}
+
+/// Whether an instance of `font` has `name` in any language
+fn has_instance(font: &DFont, name: &str) -> bool {
+    let fontref = font.fontref();
+    fontref.named_instances().iter().any(|instance| {
+        fontref
+            .localized_strings(instance.subfamily_name_id())
+            .any(|s| s.chars().eq(name.chars()))
+    })
+}
+
+/// The name of the instance in `to` which corresponds to the instance
+/// called `name` in `from`
+///
+/// This is the instance with the same name if there is one; otherwise the
+/// instances are paired up as for [instance_changes].
+pub fn counterpart(from: &DFont, to: &DFont, name: &str) -> Option<String> {
+    if has_instance(to, name) {
+        return Some(name.to_string());
+    }
+    let fontref = from.fontref();
+    let index = fontref.named_instances().iter().position(|instance| {
+        fontref
+            .localized_strings(instance.subfamily_name_id())
+            .any(|s| s.chars().eq(name.chars()))
+    })?;
+    let (pairs, _, _) = pair_instances(to, named_instances(from), named_instances(to));
+    pairs
+        .into_iter()
+        .find(|(a, _)| a.index == index)
+        .map(|(_, b)| b.name)
+}
+
+/// The named instances which were added, removed, renamed or moved
+///
+/// Instances are paired by name, then by PostScript name, then by location
+/// (see [NEAREST_LIMIT]). A pair with different names is reported as
+/// renamed, and a pair at different locations as moved (so a pair may be
+/// both); instances left over are reported as removed from the first font
+/// or added to the second.
+pub fn instance_changes(font_a: &DFont, font_b: &DFont) -> Vec<InstanceChange> {
+    let (pairs, unpaired_a, unpaired_b) =
+        pair_instances(font_b, named_instances(font_a), named_instances(font_b));
+
+    let label = |location: &[VariationSetting]| Setting::Location(location.to_vec()).name();
+    let mut changes = vec![];
+    for (a, b) in pairs {
+        let change = |kind| InstanceChange {
+            change: kind,
+            name_a: Some(a.name.clone()),
+            name_b: Some(b.name.clone()),
+            location_a: Some(label(&a.location)),
+            location_b: Some(label(&b.location)),
+        };
+        if a.name != b.name {
+            changes.push(change(InstanceChangeKind::Renamed));
+        }
+        if distance(font_b, &a.location, &b.location) >= 1e-6 {
+            changes.push(change(InstanceChangeKind::Moved));
+        }
+    }
+    for a in unpaired_a {
+        changes.push(InstanceChange {
+            change: InstanceChangeKind::Removed,
+            name_a: Some(a.name),
+            name_b: None,
+            location_a: Some(label(&a.location)),
+            location_b: None,
+        });
+    }
+    for b in unpaired_b {
+        changes.push(InstanceChange {
+            change: InstanceChangeKind::Added,
+            name_a: None,
+            name_b: Some(b.name),
+            location_a: None,
+            location_b: Some(label(&b.location)),
+        });
+    }
+    changes
+}
+
+/// Pair up the instances of two fonts, measuring distances in `font`
+///
+/// Returns the pairs, and the instances of each font left unpaired.
+fn pair_instances(
+    font: &DFont,
+    mut unpaired_a: Vec<Instance>,
+    mut unpaired_b: Vec<Instance>,
+) -> (Vec<(Instance, Instance)>, Vec<Instance>, Vec<Instance>) {
+    let mut pairs = vec![];
+    pair_off(&mut unpaired_a, &mut unpaired_b, &mut pairs, |a, b| {
+        a.name == b.name
+    });
+    pair_off(&mut unpaired_a, &mut unpaired_b, &mut pairs, |a, b| {
+        a.postscript_name.is_some() && a.postscript_name == b.postscript_name
+    });
+    pair_nearest(font, &mut unpaired_a, &mut unpaired_b, &mut pairs);
+    (pairs, unpaired_a, unpaired_b)
+}
+
+/// Move instances which `matches` pairs up from the unpaired lists to `pairs`
+fn pair_off(
+    unpaired_a: &mut Vec<Instance>,
+    unpaired_b: &mut Vec<Instance>,
+    pairs: &mut Vec<(Instance, Instance)>,
+    matches: impl Fn(&Instance, &Instance) -> bool,
+) {
+    let mut left = vec![];
+    for a in unpaired_a.drain(..) {
+        match unpaired_b.iter().position(|b| matches(&a, b)) {
+            Some(index) => pairs.push((a, unpaired_b.remove(index))),
+            None => left.push(a),
+        }
+    }
+    *unpaired_a = left;
+}
+
+/// Pair up the instances which are nearest to each other, closest first,
+/// leaving out those more than [NEAREST_LIMIT] apart
+fn pair_nearest(
+    font: &DFont,
+    unpaired_a: &mut Vec<Instance>,
+    unpaired_b: &mut Vec<Instance>,
+    pairs: &mut Vec<(Instance, Instance)>,
+) {
+    let mut candidates = vec![];
+    for (index_a, a) in unpaired_a.iter().enumerate() {
+        for (index_b, b) in unpaired_b.iter().enumerate() {
+            let distance = distance(font, &a.location, &b.location);
+            if distance <= NEAREST_LIMIT {
+                candidates.push((distance, index_a, index_b));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut left_a: Vec<Option<Instance>> = unpaired_a.drain(..).map(Some).collect();
+    let mut left_b: Vec<Option<Instance>> = unpaired_b.drain(..).map(Some).collect();
+    for (_, index_a, index_b) in candidates {
+        if left_a[index_a].is_some() && left_b[index_b].is_some() {
+            pairs.push((
+                left_a[index_a].take().unwrap(),
+                left_b[index_b].take().unwrap(),
+            ));
+        }
+    }
+    unpaired_a.extend(left_a.into_iter().flatten());
+    unpaired_b.extend(left_b.into_iter().flatten());
+}
+
+/// How far apart two locations are in `font`, with each axis scaled to
+/// its range; axes a location doesn't mention are at their defaults
+fn distance(font: &DFont, a: &[VariationSetting], b: &[VariationSetting]) -> f32 {
+    let value = |location: &[VariationSetting], tag: Tag, default: f32| {
+        location
+            .iter()
+            .find(|v| v.selector == tag)
+            .map_or(default, |v| v.value)
+    };
+    font.fontref()
+        .axes()
+        .iter()
+        .map(|axis| {
+            let range = (axis.max_value() - axis.min_value()).max(f32::EPSILON);
+            let default = axis.default_value();
+            let difference =
+                (value(a, axis.tag(), default) - value(b, axis.tag(), default)) / range;
+            difference * difference
+        })
+        .sum::<f32>()
+        .sqrt()
+}
+
+impl Display for InstanceChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_a = self.name_a.as_deref().unwrap_or_default();
+        let name_b = self.name_b.as_deref().unwrap_or_default();
+        let location_a = self.location_a.as_deref().unwrap_or_default();
+        let location_b = self.location_b.as_deref().unwrap_or_default();
+        match self.change {
+            InstanceChangeKind::Added => write!(f, "Added {} at {}", name_b, location_b),
+            InstanceChangeKind::Removed => write!(f, "Removed {} at {}", name_a, location_a),
+            InstanceChangeKind::Renamed => write!(f, "Renamed {} to {}", name_a, name_b),
+            InstanceChangeKind::Moved => {
+                write!(f, "Moved {} from {} to {}", name_b, location_a, location_b)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{get_u16, nabla, set_u16, set_u32, table_offset};
+
+    /// Nabla, whose axes run from 0 to 200 (EDPT) and 0 to 24 (EHLT), with
+    /// its one named instance changed; name IDs 258 to 264 are "Regular",
+    /// "Nabla-Regular", "Shallow", "Deep", "No Highlight", "Highlight" and
+    /// "Max Highlight"
+    fn with_instance(subfamily: u16, postscript: u16, edpt: f32) -> DFont {
+        let mut data = nabla();
+        let fvar = table_offset(&data, b"fvar");
+        let instance = fvar
+            + get_u16(&data, fvar + 4) as usize
+            + (get_u16(&data, fvar + 8) * get_u16(&data, fvar + 10)) as usize;
+        set_u16(&mut data, instance, subfamily);
+        set_u32(&mut data, instance + 4, (edpt * 65536.0) as u32);
+        set_u16(&mut data, instance + 12, postscript);
+        DFont::new(&data).unwrap()
+    }
+
+    fn instance(name: &str, postscript_name: Option<&str>, edpt: f32) -> Instance {
+        Instance {
+            index: 0,
+            name: name.to_string(),
+            postscript_name: postscript_name.map(|s| s.to_string()),
+            location: vec![VariationSetting::new(Tag::new(b"EDPT"), edpt)],
+        }
+    }
+
+    fn names(pairs: &[(Instance, Instance)]) -> Vec<(&str, &str)> {
+        pairs
+            .iter()
+            .map(|(a, b)| (a.name.as_str(), b.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_by_name_then_postscript_name_then_location() {
+        let font = DFont::new(&nabla()).unwrap();
+        let (pairs, unpaired_a, unpaired_b) = pair_instances(
+            &font,
+            vec![
+                instance("Thin", None, 0.0),
+                instance("Bold", Some("Nabla-Bold"), 150.0),
+                instance("Regular", Some("Nabla-Regular"), 100.0),
+            ],
+            vec![
+                instance("Regular", Some("Nabla-Normal"), 100.0),
+                instance("Heavy", Some("Nabla-Bold"), 190.0),
+                instance("Hairline", None, 10.0),
+            ],
+        );
+        assert_eq!(
+            names(&pairs),
+            vec![
+                ("Regular", "Regular"),
+                ("Bold", "Heavy"),
+                ("Thin", "Hairline")
+            ]
+        );
+        assert!(unpaired_a.is_empty() && unpaired_b.is_empty());
+    }
+
+    #[test]
+    fn pairs_by_location_closest_first() {
+        let font = DFont::new(&nabla()).unwrap();
+        let (pairs, _, _) = pair_instances(
+            &font,
+            vec![instance("A", None, 5.0), instance("B", None, 10.0)],
+            vec![instance("C", None, 10.0), instance("D", None, 0.0)],
+        );
+        assert_eq!(names(&pairs), vec![("B", "C"), ("A", "D")]);
+    }
+
+    #[test]
+    fn leaves_distant_instances_unpaired() {
+        let font = DFont::new(&nabla()).unwrap();
+        // 30 units of a 200 unit axis is further than NEAREST_LIMIT
+        let (pairs, unpaired_a, unpaired_b) = pair_instances(
+            &font,
+            vec![instance("Black", None, 200.0)],
+            vec![instance("ExtraBold", None, 170.0)],
+        );
+        assert!(pairs.is_empty());
+        assert_eq!(unpaired_a.len(), 1);
+        assert_eq!(unpaired_b.len(), 1);
+    }
+
+    #[test]
+    fn finds_counterparts_in_fonts() {
+        let font_a = DFont::new(&nabla()).unwrap();
+
+        assert_eq!(
+            counterpart(&font_a, &font_a, "Regular").as_deref(),
+            Some("Regular")
+        );
+        assert!(instance_changes(&font_a, &font_a).is_empty());
+
+        // Renamed, with the same PostScript name
+        let font_b = with_instance(261, 259, 100.0);
+        assert_eq!(
+            counterpart(&font_a, &font_b, "Regular").as_deref(),
+            Some("Deep")
+        );
+        assert_eq!(
+            counterpart(&font_b, &font_a, "Deep").as_deref(),
+            Some("Regular")
+        );
+        let changes = instance_changes(&font_a, &font_b);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change, InstanceChangeKind::Renamed);
+
+        // Renamed and moved a little, without a PostScript name
+        let font_b = with_instance(261, 0xFFFF, 110.0);
+        assert_eq!(
+            counterpart(&font_a, &font_b, "Regular").as_deref(),
+            Some("Deep")
+        );
+        let kinds: Vec<_> = instance_changes(&font_a, &font_b)
+            .into_iter()
+            .map(|change| change.change)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![InstanceChangeKind::Renamed, InstanceChangeKind::Moved]
+        );
+
+        // Renamed and moved too far to be the same instance
+        let font_b = with_instance(261, 0xFFFF, 200.0);
+        assert_eq!(counterpart(&font_a, &font_b, "Regular"), None);
+        let kinds: Vec<_> = instance_changes(&font_a, &font_b)
+            .into_iter()
+            .map(|change| change.change)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![InstanceChangeKind::Removed, InstanceChangeKind::Added]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Checking that outlines interpolate smoothly between masters
+//!
+//! Comparing the fonts at their masters says nothing about the space in
+//! between, where a bad intermediate master or a stray delta puts a kink in
+//! an outline. Between two neighbouring masters, `gvar` moves every point
+//! in a straight line; so at each of a row of samples between them, a point
+//! should sit halfway between where the samples on either side put it.
+//! [interpolation_check] reports the glyphs where a point strays from that
+//! line, in each font.
+use std::{collections::BTreeSet, fmt::Display};
+
+use crate::{
+    dfont::DFont,
+    sampling::points,
+    setting::Setting,
+    structs::{InterpolationCheck, InterpolationKink},
+};
+use skrifa::{
+    instance::{Location, NormalizedCoord, Size},
+    metrics::Metrics,
+    raw::ReadError,
+    MetadataProvider,
+};
+use ttj::monkeypatching::DenormalizeLocation;
+
+/// Samples taken along each span between neighbouring masters, including
+/// the masters at either end
+const SAMPLES: usize = 9;
+/// Deviations smaller than this fraction of an em are not reported
+const TOLERANCE: f32 = 0.002;
+
+impl Display for InterpolationKink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}: off by {} units",
+            self.glyph, self.location, self.deviation
+        )?;
+        if self.introduced {
+            write!(f, " (new)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Look for glyphs which kink between masters in either font
+///
+/// A glyph in the second font is marked as `introduced` unless the same
+/// glyph also kinks in the first. Fonts without a `gvar` table (static or
+/// CFF2 fonts) can't be checked, and are reported as an error.
+pub fn interpolation_check(font_a: &DFont, font_b: &DFont) -> Result<InterpolationCheck, String> {
+    let font_a_kinks = kinks(font_a)
+        .map_err(|e| format!("Couldn't check interpolation in the old font: {}", e))?;
+    let mut font_b_kinks = kinks(font_b)
+        .map_err(|e| format!("Couldn't check interpolation in the new font: {}", e))?;
+    for kink in font_b_kinks.iter_mut() {
+        kink.introduced = !font_a_kinks.iter().any(|k| k.glyph == kink.glyph);
+    }
+    Ok(InterpolationCheck {
+        font_a: font_a_kinks,
+        font_b: font_b_kinks,
+    })
+}
+
+/// The worst kink in each encoded glyph of a font, worst first
+fn kinks(font: &DFont) -> Result<Vec<InterpolationKink>, ReadError> {
+    let fontref = font.fontref();
+    let axis_count = fontref.axes().len();
+    let masters = font.master_coords()?;
+    let mut points_to_join = vec![vec![0.0; axis_count]];
+    for master in masters {
+        if master.len() == axis_count && !points_to_join.contains(&master) {
+            points_to_join.push(master);
+        }
+    }
+    let spans = neighbouring_spans(&points_to_join);
+    let locations: Vec<Vec<Vec<f32>>> = spans
+        .iter()
+        .map(|(a, b)| {
+            (0..SAMPLES)
+                .map(|i| {
+                    let t = i as f32 / (SAMPLES - 1) as f32;
+                    a.iter()
+                        .zip(b.iter())
+                        .map(|(x, y)| x + (y - x) * t)
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    let upem = Metrics::new(&fontref, Size::unscaled(), &Location::default()).units_per_em;
+    let tolerance = upem as f32 * TOLERANCE;
+
+    let charmap = fontref.charmap();
+    let outlines = fontref.outline_glyphs();
+    let codepoints: BTreeSet<u32> = font.codepoints.iter().copied().collect();
+    let mut kinks = vec![];
+    for codepoint in codepoints {
+        let (Some(c), Some(glyph)) = (
+            char::from_u32(codepoint),
+            charmap.map(codepoint).and_then(|gid| outlines.get(gid)),
+        ) else {
+            continue;
+        };
+        let mut worst: Option<(f32, &[f32])> = None;
+        for span in locations.iter() {
+            let samples: Vec<Option<Vec<f32>>> = span
+                .iter()
+                .map(|coords| points(&glyph, &location(coords)))
+                .collect();
+            for (i, coords) in span.iter().enumerate().skip(1).take(SAMPLES - 2) {
+                let (Some(before), Some(here), Some(after)) =
+                    (&samples[i - 1], &samples[i], &samples[i + 1])
+                else {
+                    continue;
+                };
+                let deviation = deviation(before, here, after);
+                if deviation > worst.map_or(tolerance, |(d, _)| d) {
+                    worst = Some((deviation, coords));
+                }
+            }
+        }
+        if let Some((deviation, coords)) = worst {
+            kinks.push(InterpolationKink {
+                glyph: format!("{} (U+{:04X})", c, codepoint),
+                location: fontref
+                    .denormalize_location(coords)
+                    .map(|location| {
+                        if location.is_empty() {
+                            Setting::Default.name()
+                        } else {
+                            Setting::Location(location).name()
+                        }
+                    })
+                    .unwrap_or_default(),
+                deviation: (deviation * 10.0).round() / 10.0,
+                introduced: false,
+            });
+        }
+    }
+    kinks.sort_by(|x, y| y.deviation.total_cmp(&x.deviation));
+    Ok(kinks)
+}
+
+/// Pairs of masters (or the default) which differ on only one axis, with
+/// no other master between them
+///
+/// Along a line parallel to an axis, `gvar` only bends outlines at masters,
+/// so any other bend is a kink. Other lines may legitimately curve, where
+/// masters in the corners of the designspace pull on them.
+fn neighbouring_spans(masters: &[Vec<f32>]) -> Vec<(Vec<f32>, Vec<f32>)> {
+    let mut spans = vec![];
+    for (i, a) in masters.iter().enumerate() {
+        for b in masters.iter().skip(i + 1) {
+            let Some(axis) = single_differing_axis(a, b) else {
+                continue;
+            };
+            let (low, high) = (a[axis].min(b[axis]), a[axis].max(b[axis]));
+            let blocked = masters.iter().any(|m| {
+                single_differing_axis(a, m) == Some(axis) && m[axis] > low && m[axis] < high
+            });
+            if !blocked {
+                spans.push((a.clone(), b.clone()));
+            }
+        }
+    }
+    spans
+}
+
+fn single_differing_axis(a: &[f32], b: &[f32]) -> Option<usize> {
+    let mut differing = a
+        .iter()
+        .zip(b.iter())
+        .enumerate()
+        .filter(|(_, (x, y))| x != y)
+        .map(|(axis, _)| axis);
+    let axis = differing.next()?;
+    differing.next().is_none().then_some(axis)
+}
+
+/// How far a point at `here` is from halfway between `before` and `after`,
+/// for the point which strays the most
+fn deviation(before: &[f32], here: &[f32], after: &[f32]) -> f32 {
+    if before.len() != here.len() || after.len() != here.len() {
+        return 0.0;
+    }
+    let mut worst: f32 = 0.0;
+    for ((b, h), a) in before.chunks(2).zip(here.chunks(2)).zip(after.chunks(2)) {
+        let (dx, dy) = ((b[0] + a[0]) / 2.0 - h[0], (b[1] + a[1]) / 2.0 - h[1]);
+        worst = worst.max((dx * dx + dy * dy).sqrt());
+    }
+    worst
+}
+
+fn location(coords: &[f32]) -> Location {
+    let mut location = Location::new(coords.len());
+    for (coord, value) in location.coords_mut().iter_mut().zip(coords) {
+        *coord = NormalizedCoord::from_f32(*value);
+    }
+    location
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::nabla;
+
+    #[test]
+    fn finds_the_single_differing_axis() {
+        assert_eq!(single_differing_axis(&[0.0, 0.0], &[0.0, 1.0]), Some(1));
+        assert_eq!(single_differing_axis(&[0.0, 0.0], &[1.0, 1.0]), None);
+        assert_eq!(single_differing_axis(&[0.5, 0.0], &[0.5, 0.0]), None);
+    }
+
+    #[test]
+    fn spans_join_neighbouring_masters() {
+        let masters = vec![
+            vec![0.0, 0.0],
+            vec![1.0, 0.0],
+            vec![0.5, 0.0],
+            vec![0.0, 1.0],
+            vec![1.0, 1.0],
+        ];
+        assert_eq!(
+            neighbouring_spans(&masters),
+            vec![
+                // Not the default to (1, 0), which is blocked by (0.5, 0)
+                (vec![0.0, 0.0], vec![0.5, 0.0]),
+                (vec![0.0, 0.0], vec![0.0, 1.0]),
+                (vec![1.0, 0.0], vec![0.5, 0.0]),
+                (vec![1.0, 0.0], vec![1.0, 1.0]),
+                (vec![0.0, 1.0], vec![1.0, 1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn measures_the_worst_deviation() {
+        let before = [0.0, 0.0, 10.0, 10.0];
+        let after = [10.0, 0.0, 20.0, 10.0];
+        assert_eq!(deviation(&before, &[5.0, 0.0, 15.0, 10.0], &after), 0.0);
+        assert_eq!(deviation(&before, &[5.0, 0.0, 15.0, 14.0], &after), 4.0);
+        assert_eq!(deviation(&before, &[5.0, 3.0, 19.0, 10.0], &after), 4.0);
+        // Outlines with different structures can't be compared
+        assert_eq!(deviation(&before, &[5.0, 3.0], &after), 0.0);
+    }
+
+    #[test]
+    fn fonts_without_gvar_are_an_error() {
+        let variable = nabla();
+        let variable = DFont::new(&variable).unwrap();
+        let check = interpolation_check(&variable, &variable).unwrap();
+        assert!(check.font_b.iter().all(|kink| !kink.introduced));
+
+        let data = std::fs::read("test-data/cbdt.ttf").unwrap();
+        let fixed = DFont::new(&data).unwrap();
+        let error = interpolation_check(&variable, &fixed).unwrap_err();
+        assert!(error.contains("new font"), "{}", error);
+        let error = interpolation_check(&fixed, &variable).unwrap_err();
+        assert!(error.contains("old font"), "{}", error);
+    }
+}
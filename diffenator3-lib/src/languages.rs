@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use diffenator3_lib::dfont::DFont;
+use crate::{dfont::DFont, error::Error};
 use shaperglot::{Checker, Languages, SupportLevel};
 
 fn support_label(level: &SupportLevel) -> &'static str {
@@ -14,26 +14,39 @@ fn support_label(level: &SupportLevel) -> &'static str {
     }
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct LanguageDiff {
     /// Support level for font A
     pub level_a: String,
     /// Support score for font A
+    #[serde(deserialize_with = "score_or_nan")]
     pub score_a: f32,
     /// Number of fixes required for font A
     pub fixes_a: usize,
     /// Support level for font B
     pub level_b: String,
     /// Support score for font B
+    #[serde(deserialize_with = "score_or_nan")]
     pub score_b: f32,
     /// Number of fixes required for font B
     pub fixes_b: usize,
 }
 
-pub fn diff_languages(font_a: &DFont, font_b: &DFont) -> BTreeMap<String, LanguageDiff> {
-    let checker_a = Checker::new(&font_a.backing).expect("Failed to load font");
-    let checker_b = Checker::new(&font_b.backing).expect("Failed to load font");
+/// Read a score back from JSON, which writes a score of NaN (for a
+/// language with nothing to check) as `null`
+fn score_or_nan<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    Ok(<Option<f32> as serde::Deserialize>::deserialize(deserializer)?.unwrap_or(f32::NAN))
+}
+
+pub fn diff_languages(
+    font_a: &DFont,
+    font_b: &DFont,
+) -> Result<BTreeMap<String, LanguageDiff>, Error> {
+    let checker_a =
+        Checker::new(&font_a.backing).map_err(|e| Error::Languages(format!("font A: {}", e)))?;
+    let checker_b =
+        Checker::new(&font_b.backing).map_err(|e| Error::Languages(format!("font B: {}", e)))?;
     let languages = Languages::new();
     let mut supported: BTreeMap<String, LanguageDiff> = BTreeMap::new();
     for language in languages.iter() {
@@ -52,5 +65,26 @@ pub fn diff_languages(font_a: &DFont, font_b: &DFont) -> BTreeMap<String, Langua
             },
         );
     }
-    supported
+    Ok(supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_of_nan_round_trip() {
+        let diff = LanguageDiff {
+            level_a: "None".to_string(),
+            score_a: f32::NAN,
+            fixes_a: 0,
+            level_b: "Complete".to_string(),
+            score_b: 1.0,
+            fixes_b: 0,
+        };
+        let json = serde_json::to_string(&diff).unwrap();
+        let diff: LanguageDiff = serde_json::from_str(&json).unwrap();
+        assert!(diff.score_a.is_nan());
+        assert_eq!(diff.score_b, 1.0);
+    }
 }
@@ -1,8 +1,25 @@
+pub mod comparison;
+// Shared configuration file code
+#[cfg(feature = "config")]
+pub mod config;
 pub mod dfont;
+pub mod dossier;
+pub mod error;
+pub mod family;
+pub mod instances;
+pub mod interpolation;
+pub mod languages;
+pub mod metrics;
+pub mod palettes;
+pub mod report;
+mod sampling;
 pub mod structs;
+#[cfg(test)]
+mod test_fonts;
 // Shared HTML rendering/templating code
 #[cfg(feature = "html")]
 pub mod html;
 pub mod render;
 pub mod setting;
+pub use error::Error;
 pub use static_lang_word_lists::WordList;
@@ -0,0 +1,222 @@
+//! Compare font-wide and per-glyph metrics at the fonts' current locations
+//!
+//! The table diff compares metrics as they are stored, which for a variable
+//! font is at the default location only. Here the metrics are computed at
+//! each font's location, with any variations applied.
+use std::{collections::BTreeSet, fmt::Display};
+
+use crate::{dfont::DFont, structs::MetricDiff};
+use skrifa::{instance::Size, metrics::Metrics, MetadataProvider};
+
+impl Display for MetricDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(glyph) = self.glyph.as_ref() {
+            write!(f, "{} of {}", self.metric, glyph)?;
+        } else {
+            write!(f, "{}", self.metric)?;
+        }
+        write!(
+            f,
+            ": {} => {}",
+            metric_value(self.value_a),
+            metric_value(self.value_b)
+        )
+    }
+}
+
+/// A metric value for display, or `missing` if the font doesn't have the metric
+pub fn metric_value(value: Option<f32>) -> String {
+    value.map_or("missing".to_string(), |v| v.to_string())
+}
+
+/// Compare the vertical metrics of the fonts, and the advance widths of
+/// the glyphs encoded in both
+///
+/// The second font's metrics are scaled to the first font's units per em.
+/// Values are rounded to whole units before comparing them, since
+/// interpolated metrics are rarely whole numbers. Font-wide metrics come
+/// first, followed by the glyphs whose advance widths changed the most.
+pub fn metric_differences(font_a: &DFont, font_b: &DFont) -> Vec<MetricDiff> {
+    let (fontref_a, fontref_b) = (font_a.fontref(), font_b.fontref());
+    let (location_a, location_b) = (&font_a.normalized_location, &font_b.normalized_location);
+    let size =
+        Size::new(Metrics::new(&fontref_a, Size::unscaled(), location_a).units_per_em as f32);
+    let mut differences = vec![];
+
+    let metrics_a = fontref_a.metrics(size, location_a);
+    let metrics_b = fontref_b.metrics(size, location_b);
+    for (metric, a, b) in [
+        ("ascender", Some(metrics_a.ascent), Some(metrics_b.ascent)),
+        (
+            "descender",
+            Some(metrics_a.descent),
+            Some(metrics_b.descent),
+        ),
+        ("line gap", Some(metrics_a.leading), Some(metrics_b.leading)),
+        ("cap height", metrics_a.cap_height, metrics_b.cap_height),
+        ("x-height", metrics_a.x_height, metrics_b.x_height),
+        (
+            "underline position",
+            metrics_a.underline.map(|d| d.offset),
+            metrics_b.underline.map(|d| d.offset),
+        ),
+        (
+            "underline thickness",
+            metrics_a.underline.map(|d| d.thickness),
+            metrics_b.underline.map(|d| d.thickness),
+        ),
+        (
+            "strikeout position",
+            metrics_a.strikeout.map(|d| d.offset),
+            metrics_b.strikeout.map(|d| d.offset),
+        ),
+        (
+            "strikeout thickness",
+            metrics_a.strikeout.map(|d| d.thickness),
+            metrics_b.strikeout.map(|d| d.thickness),
+        ),
+    ] {
+        differences.extend(compare(metric, None, a, b));
+    }
+    let font_wide = differences.len();
+
+    let (charmap_a, charmap_b) = (fontref_a.charmap(), fontref_b.charmap());
+    let glyph_metrics_a = fontref_a.glyph_metrics(size, location_a);
+    let glyph_metrics_b = fontref_b.glyph_metrics(size, location_b);
+    let codepoints: BTreeSet<u32> = font_a
+        .codepoints
+        .intersection(&font_b.codepoints)
+        .copied()
+        .collect();
+    for codepoint in codepoints {
+        let Some(c) = char::from_u32(codepoint) else {
+            continue;
+        };
+        differences.extend(compare(
+            "advance width",
+            Some(format!("{} (U+{:04X})", c, codepoint)),
+            charmap_a
+                .map(codepoint)
+                .and_then(|gid| glyph_metrics_a.advance_width(gid)),
+            charmap_b
+                .map(codepoint)
+                .and_then(|gid| glyph_metrics_b.advance_width(gid)),
+        ));
+    }
+    // A metric only one font has is the biggest change of all
+    let change = |diff: &MetricDiff| match (diff.value_a, diff.value_b) {
+        (Some(a), Some(b)) => (b - a).abs(),
+        _ => f32::INFINITY,
+    };
+    differences[font_wide..].sort_by(|x, y| change(y).total_cmp(&change(x)));
+    differences
+}
+
+/// A difference between two values, if they differ once rounded
+///
+/// A value which only one font has is a difference too.
+fn compare(
+    metric: &str,
+    glyph: Option<String>,
+    a: Option<f32>,
+    b: Option<f32>,
+) -> Option<MetricDiff> {
+    let (a, b) = (a.map(f32::round), b.map(f32::round));
+    (a != b).then(|| MetricDiff {
+        metric: metric.to_string(),
+        glyph,
+        value_a: a,
+        value_b: b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{get_u16, nabla, set_u16, table_offset};
+
+    /// Nabla with the advance widths of some characters widened by some amount
+    fn widened(data: &[u8], widths: &[(char, u16)]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let hmtx = table_offset(&data, b"hmtx");
+        for &(c, extra) in widths {
+            let gid = skrifa::FontRef::new(&data)
+                .unwrap()
+                .charmap()
+                .map(c)
+                .unwrap()
+                .to_u32() as usize;
+            let advance = get_u16(&data, hmtx + gid * 4);
+            set_u16(&mut data, hmtx + gid * 4, advance + extra);
+        }
+        data
+    }
+
+    #[test]
+    fn test_same_font_has_no_differences() {
+        let font = DFont::new(&nabla()).unwrap();
+        assert!(metric_differences(&font, &font).is_empty());
+    }
+
+    #[test]
+    fn test_font_wide_metrics_come_first() {
+        let font_a = DFont::new(&nabla()).unwrap();
+        let mut data = widened(&nabla(), &[('e', 10), ('o', 50)]);
+        let hhea = table_offset(&data, b"hhea");
+        let os2 = table_offset(&data, b"OS/2");
+        for offset in [hhea + 4, os2 + 68] {
+            let ascender = get_u16(&data, offset);
+            set_u16(&mut data, offset, ascender + 100);
+        }
+        let font_b = DFont::new(&data).unwrap();
+        let differences = metric_differences(&font_a, &font_b);
+        let labels: Vec<(&str, Option<&str>)> = differences
+            .iter()
+            .map(|d| (d.metric.as_str(), d.glyph.as_deref()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("ascender", None),
+                ("advance width", Some("o (U+006F)")),
+                ("advance width", Some("e (U+0065)")),
+            ]
+        );
+        assert_eq!(
+            differences[1].value_b.unwrap() - differences[1].value_a.unwrap(),
+            50.0
+        );
+    }
+
+    #[test]
+    fn test_one_sided_metrics() {
+        // OS/2 versions before 2 have no cap height or x-height
+        let font_a = DFont::new(&nabla()).unwrap();
+        let mut data = nabla();
+        let os2 = table_offset(&data, b"OS/2");
+        set_u16(&mut data, os2, 1);
+        let font_b = DFont::new(&data).unwrap();
+        let differences = metric_differences(&font_a, &font_b);
+        let cap_height = differences
+            .iter()
+            .find(|d| d.metric == "cap height")
+            .expect("cap height removal is reported");
+        assert!(cap_height.value_a.is_some());
+        assert_eq!(cap_height.value_b, None);
+        assert_eq!(
+            cap_height.to_string(),
+            format!("cap height: {} => missing", cap_height.value_a.unwrap())
+        );
+        assert!(differences.iter().any(|d| d.metric == "x-height"));
+    }
+
+    #[test]
+    fn test_compare_rounds() {
+        assert!(compare("ascender", None, Some(10.4), Some(9.6)).is_none());
+        let diff = compare("ascender", None, Some(10.4), Some(10.6)).unwrap();
+        assert_eq!((diff.value_a, diff.value_b), (Some(10.0), Some(11.0)));
+        let diff = compare("ascender", None, None, Some(0.2)).unwrap();
+        assert_eq!((diff.value_a, diff.value_b), (None, Some(0.0)));
+        assert!(compare("ascender", None, None, None).is_none());
+    }
+}
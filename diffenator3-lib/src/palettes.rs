@@ -0,0 +1,203 @@
+//! Comparing the CPAL color palettes of two fonts
+//!
+//! The table diff shows the `CPAL` table as raw arrays of color records,
+//! which are shared between palettes and hard to read. [palette_changes]
+//! instead lists each palette entry whose color differs, and
+//! [shared_palettes] tells the renderer how many palettes to compare
+//! color glyphs in.
+use std::collections::HashSet;
+
+use read_fonts::{tables::cpal::ColorRecord, FontRef, TableProvider};
+
+use crate::{dfont::DFont, structs::PaletteChange};
+
+/// The number of palettes in a font's `CPAL` table, or zero if it has none
+pub(crate) fn palette_count(font: &FontRef) -> u16 {
+    font.cpal().map_or(0, |cpal| cpal.num_palettes())
+}
+
+/// The number of palettes which both fonts have, and so which color glyphs
+/// can be compared in; at least one, the default
+pub(crate) fn shared_palettes(font_a: &DFont, font_b: &DFont) -> u16 {
+    palette_count(&font_a.fontref())
+        .min(palette_count(&font_b.fontref()))
+        .max(1)
+}
+
+/// The colors of one palette, or an empty list if the font has no such
+/// palette
+pub(crate) fn palette(font: &FontRef, index: u16) -> Vec<ColorRecord> {
+    let Ok(cpal) = font.cpal() else {
+        return vec![];
+    };
+    let Some(Ok(records)) = cpal.color_records_array() else {
+        return vec![];
+    };
+    let Some(first) = cpal.color_record_indices().get(index as usize) else {
+        return vec![];
+    };
+    let first = first.get() as usize;
+    records
+        .get(first..first + cpal.num_palette_entries() as usize)
+        .map(|colors| colors.to_vec())
+        .unwrap_or_default()
+}
+
+fn hex(color: &ColorRecord) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}{:02X}",
+        color.red, color.green, color.blue, color.alpha
+    )
+}
+
+/// The palette entries whose colors differ between the fonts
+///
+/// Palettes and entries which only one font has are listed with no color
+/// on the other side.
+pub fn palette_changes(font_a: &DFont, font_b: &DFont) -> Vec<PaletteChange> {
+    let (fontref_a, fontref_b) = (font_a.fontref(), font_b.fontref());
+    let count = palette_count(&fontref_a).max(palette_count(&fontref_b));
+    let mut changes = vec![];
+    for index in 0..count {
+        let palette_a = palette(&fontref_a, index);
+        let palette_b = palette(&fontref_b, index);
+        for entry in 0..palette_a.len().max(palette_b.len()) {
+            let color_a = palette_a.get(entry).map(hex);
+            let color_b = palette_b.get(entry).map(hex);
+            if color_a != color_b {
+                changes.push(PaletteChange {
+                    palette: index,
+                    entry: entry as u16,
+                    color_a,
+                    color_b,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// The palettes with any entry whose color differs between the fonts
+pub(crate) fn changed_palettes(font_a: &DFont, font_b: &DFont) -> HashSet<u16> {
+    palette_changes(font_a, font_b)
+        .into_iter()
+        .map(|change| change.palette)
+        .collect()
+}
+
+impl std::fmt::Display for PaletteChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let none = "(none)".to_string();
+        write!(
+            f,
+            "Palette {} entry {}: {} -> {}",
+            self.palette,
+            self.entry,
+            self.color_a.as_ref().unwrap_or(&none),
+            self.color_b.as_ref().unwrap_or(&none)
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_fonts::{get_u16, get_u32, nabla, set_u16, table_offset};
+
+    /// The font with fewer palettes, or fewer entries in each palette
+    pub(crate) fn with_counts(data: &[u8], palettes: u16, entries: u16) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let cpal = table_offset(&data, b"CPAL");
+        set_u16(&mut data, cpal + 2, entries);
+        set_u16(&mut data, cpal + 4, palettes);
+        data
+    }
+
+    /// The font with a palette made to share the colors of another
+    pub(crate) fn with_shared_palette(data: &[u8], palette: u16, shared_with: u16) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let cpal = table_offset(&data, b"CPAL");
+        let first = get_u16(&data, cpal + 12 + 2 * shared_with as usize);
+        set_u16(&mut data, cpal + 12 + 2 * palette as usize, first);
+        data
+    }
+
+    /// The font with one palette entry set to an RGBA color
+    pub(crate) fn with_color(data: &[u8], palette: u16, entry: u16, rgba: [u8; 4]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let cpal = table_offset(&data, b"CPAL");
+        let records = cpal + get_u32(&data, cpal + 8) as usize;
+        let first = get_u16(&data, cpal + 12 + 2 * palette as usize);
+        let record = records + 4 * (first + entry) as usize;
+        // Color records are stored as BGRA
+        data[record..record + 4].copy_from_slice(&[rgba[2], rgba[1], rgba[0], rgba[3]]);
+        data
+    }
+
+    fn dfont(data: &[u8]) -> DFont {
+        DFont::new(data).unwrap()
+    }
+
+    #[test]
+    fn counts_palettes() {
+        let seven = dfont(&nabla());
+        let two = dfont(&with_counts(&nabla(), 2, 10));
+        let no_cpal = dfont(&std::fs::read("test-data/cbdt.ttf").unwrap());
+
+        assert_eq!(palette_count(&seven.fontref()), 7);
+        assert_eq!(palette_count(&no_cpal.fontref()), 0);
+        assert_eq!(shared_palettes(&seven, &two), 2);
+        // Fonts without palettes are still compared in the default one
+        assert_eq!(shared_palettes(&seven, &no_cpal), 1);
+    }
+
+    #[test]
+    fn reads_palettes() {
+        let data = with_color(&nabla(), 1, 2, [0x12, 0x34, 0x56, 0x78]);
+        let font = FontRef::new(&data).unwrap();
+
+        let colors = palette(&font, 1);
+        assert_eq!(colors.len(), 10);
+        assert_eq!(hex(&colors[2]), "#12345678");
+        assert!(palette(&font, 7).is_empty());
+        let no_cpal = std::fs::read("test-data/cbdt.ttf").unwrap();
+        assert!(palette(&FontRef::new(&no_cpal).unwrap(), 0).is_empty());
+    }
+
+    #[test]
+    fn lists_changed_colors() {
+        let font_a = dfont(&nabla());
+        assert!(palette_changes(&font_a, &dfont(&nabla())).is_empty());
+
+        let old = hex(&palette(&font_a.fontref(), 3)[4]);
+        let font_b = dfont(&with_color(&nabla(), 3, 4, [0xff, 0, 0, 0xff]));
+        assert_eq!(
+            palette_changes(&font_a, &font_b),
+            vec![PaletteChange {
+                palette: 3,
+                entry: 4,
+                color_a: Some(old),
+                color_b: Some("#FF0000FF".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn lists_missing_palettes_and_entries() {
+        let font_a = dfont(&nabla());
+
+        // Palettes 2 to 6 are missing entirely
+        let changes = palette_changes(&font_a, &dfont(&with_counts(&nabla(), 2, 10)));
+        assert_eq!(changes.len(), 5 * 10);
+        assert!(changes.iter().all(|change| change.palette >= 2
+            && change.color_a.is_some()
+            && change.color_b.is_none()));
+
+        // The last entry of each palette is missing
+        let changes = palette_changes(&dfont(&with_counts(&nabla(), 7, 9)), &font_a);
+        assert_eq!(changes.len(), 7);
+        assert!(changes.iter().all(|change| change.entry == 9
+            && change.color_a.is_none()
+            && change.color_b.is_some()));
+    }
+}
@@ -0,0 +1,100 @@
+/// Embedded bitmap glyphs, from `sbix` and `CBDT`/`CBLC` tables.
+///
+/// Colour bitmap fonts carry a PNG (or raw BGRA) image of each glyph in one
+/// or more strikes of fixed sizes. [`render_bitmap_glyph`] picks the strike
+/// closest to the size being rendered, decodes the image and scales it into
+/// a tile which [`ColorRenderer`](super::colorrenderer::ColorRenderer)
+/// composites like any other colour glyph.
+use skrifa::{
+    bitmap::{BitmapData, BitmapFormat, BitmapStrikes, Origin},
+    instance::Size,
+    FontRef, GlyphId,
+};
+use tiny_skia::{FilterQuality, IntSize, Pixmap, PixmapPaint, Transform};
+
+use super::colorrenderer::CachedColorGlyph;
+
+/// Returns true if the font has colour bitmap strikes.
+pub(crate) fn has_color_bitmaps(font: &FontRef) -> bool {
+    strikes(font).is_some()
+}
+
+/// The colour bitmap strikes of a font; monochrome `EBDT` strikes are left
+/// to the outline renderer
+fn strikes<'a>(font: &FontRef<'a>) -> Option<BitmapStrikes<'a>> {
+    [BitmapFormat::Sbix, BitmapFormat::Cbdt]
+        .into_iter()
+        .find_map(|format| BitmapStrikes::with_format(font, format))
+        .filter(|strikes| !strikes.is_empty())
+}
+
+/// Render the bitmap of a glyph at `font_size` pixels per em, from the
+/// strike closest to that size.
+///
+/// Returns `None` if the glyph has no colour bitmap, so it can be drawn
+/// some other way.
+pub(super) fn render_bitmap_glyph(
+    font: &FontRef,
+    glyph_id: GlyphId,
+    font_size: f32,
+    upem: u16,
+) -> Option<CachedColorGlyph> {
+    let glyph = strikes(font)?.glyph_for_size(Size::new(font_size), glyph_id)?;
+    let image = match glyph.data {
+        BitmapData::Png(data) => Pixmap::decode_png(data).ok()?,
+        BitmapData::Bgra(data) => bgra_to_pixmap(data, glyph.width, glyph.height)?,
+        BitmapData::Mask(_) => return None,
+    };
+
+    // From pixels of the strike to pixels at our size
+    let scale_x = font_size / glyph.ppem_x.max(1.0);
+    let scale_y = font_size / glyph.ppem_y.max(1.0);
+    let width = image.width() as f32 * scale_x;
+    let height = image.height() as f32 * scale_y;
+    let (bearing_x, bearing_y) = match glyph.placement_origin {
+        Origin::TopLeft => (
+            glyph.inner_bearing_x * scale_x,
+            glyph.inner_bearing_y * scale_y,
+        ),
+        // sbix places the bottom of the image relative to the glyph's
+        // outline bounds, which are in font units
+        Origin::BottomLeft => {
+            let factor = font_size / upem as f32;
+            let bottom = glyph.bearing_y * factor + glyph.inner_bearing_y * scale_y;
+            (
+                glyph.bearing_x * factor + glyph.inner_bearing_x * scale_x,
+                bottom + height,
+            )
+        }
+    };
+
+    let mut pixmap = Pixmap::new(width.ceil().max(1.0) as u32, height.ceil().max(1.0) as u32)?;
+    pixmap.draw_pixmap(
+        0,
+        0,
+        image.as_ref(),
+        &PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..PixmapPaint::default()
+        },
+        Transform::from_scale(scale_x, scale_y),
+        None,
+    );
+    Some(CachedColorGlyph {
+        pixmap,
+        bearing_x,
+        bearing_y,
+    })
+}
+
+/// Convert premultiplied BGRA data into a (premultiplied RGBA) pixmap.
+fn bgra_to_pixmap(data: &[u8], width: u32, height: u32) -> Option<Pixmap> {
+    let size = IntSize::from_wh(width, height)?;
+    let length = (width * height * 4) as usize;
+    let rgba = data
+        .get(..length)?
+        .chunks_exact(4)
+        .flat_map(|px| [px[2], px[1], px[0], px[3]])
+        .collect();
+    Pixmap::from_vec(rgba, size)
+}
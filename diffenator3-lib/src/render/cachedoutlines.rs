@@ -7,6 +7,7 @@ use skrifa::{
 use zeno::Command;
 
 use super::utils::RecordingPen;
+use crate::error::Error;
 
 pub(crate) struct CachedOutlineGlyphCollection<'a> {
     source: OutlineGlyphCollection<'a>,
@@ -25,21 +26,27 @@ impl<'a> CachedOutlineGlyphCollection<'a> {
         }
     }
 
-    pub fn get(&mut self, glyph_id: GlyphId) -> Option<&Vec<Command>> {
-        if let std::collections::hash_map::Entry::Vacant(e) = self.cache.entry(glyph_id) {
-            let outlined = self.source.get(glyph_id).unwrap();
-            let mut pen = RecordingPen::default();
-            let settings = DrawSettings::unhinted(self.size, self.location);
-            let _ = outlined.draw(settings, &mut pen);
-            e.insert(pen.buffer);
+    pub fn get(&mut self, glyph_id: GlyphId) -> Result<&Vec<Command>, Error> {
+        match self.cache.entry(glyph_id) {
+            std::collections::hash_map::Entry::Occupied(e) => Ok(e.into_mut()),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let outlined = self
+                    .source
+                    .get(glyph_id)
+                    .ok_or(Error::MissingOutline(glyph_id))?;
+                let mut pen = RecordingPen::default();
+                let settings = DrawSettings::unhinted(self.size, self.location);
+                let _ = outlined.draw(settings, &mut pen);
+                Ok(e.insert(pen.buffer))
+            }
         }
-        self.cache.get(&glyph_id)
     }
 
-    pub fn draw(&mut self, glyph_id: GlyphId, pen: &mut RecordingPen) {
-        let commands = self.get(glyph_id).unwrap();
+    pub fn draw(&mut self, glyph_id: GlyphId, pen: &mut RecordingPen) -> Result<(), Error> {
+        let commands = self.get(glyph_id)?;
         let matrix = zeno::Transform::translation(pen.offset_x, pen.offset_y);
         pen.buffer
             .extend(commands.iter().map(|c| c.transform(&matrix)));
+        Ok(())
     }
 }
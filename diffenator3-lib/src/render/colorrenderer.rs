@@ -1,14 +1,16 @@
-/// Color glyph rendering for fonts with COLR tables.
+/// Color glyph rendering for fonts with COLR, SVG or colour bitmap tables.
 ///
-/// This module provides [`ColorRenderer`], a renderer for fonts containing a
-/// COLR table (color glyphs). It renders each unique glyph once via
-/// [`SkiaPainter`] and caches the resulting RGBA tile. Subsequent occurrences
-/// of the same glyph are composited from the cache, avoiding repeated paint
-/// graph traversals.
+/// This module provides [`ColorRenderer`], a renderer for fonts containing
+/// color glyphs. It renders each unique glyph once (via [`SkiaPainter`] for
+/// COLR glyphs, [`render_svg_glyph`] for SVG glyphs and
+/// [`render_bitmap_glyph`] for sbix and CBDT bitmaps) and caches the
+/// resulting RGBA tile. Subsequent occurrences of the same glyph are
+/// composited from the cache, avoiding repeated paint graph traversals.
 use std::collections::HashMap;
 
 use harfrust::{
-    Direction, Script, ShapePlan, ShaperData, ShaperInstance, UnicodeBuffer, Variation,
+    Direction, Feature, Language, Script, ShapePlan, ShaperData, ShaperInstance, UnicodeBuffer,
+    Variation,
 };
 use image::{GrayImage, Luma};
 use skrifa::{
@@ -20,28 +22,36 @@ use skrifa::{
 };
 use tiny_skia::{Pixmap, PixmapPaint, Transform as TsTransform};
 
-use super::colorpainter::{PaletteColor, SkiaPainter};
-use crate::dfont::DFont;
+use super::{
+    bitmapglyphs::render_bitmap_glyph,
+    colorpainter::{PaletteColor, SkiaPainter},
+    svgglyphs::render_svg_glyph,
+};
+use crate::{dfont::DFont, error::Error, palettes::palette};
 
 /// A pre-rendered glyph tile cached for reuse across words.
-struct CachedColorGlyph {
+pub(super) struct CachedColorGlyph {
     /// The rendered RGBA bitmap of this glyph.
-    pixmap: Pixmap,
+    pub(super) pixmap: Pixmap,
     /// Pixel offset from the glyph origin to the left edge of the bitmap.
-    bearing_x: f32,
+    pub(super) bearing_x: f32,
     /// Pixel offset from the baseline to the top edge of the bitmap.
-    bearing_y: f32,
+    pub(super) bearing_y: f32,
 }
 
 pub struct ColorRenderer<'a> {
     shaper_data: ShaperData,
     scale: f32,
+    upem: u16,
     font: skrifa::FontRef<'a>,
     plan: Option<ShapePlan>,
     instance: ShaperInstance,
     palette: Vec<PaletteColor>,
     location: LocationRef<'a>,
     cache: HashMap<u32, CachedColorGlyph>,
+    direction: Option<Direction>,
+    language: Option<Language>,
+    features: Vec<Feature>,
 }
 
 impl<'a> ColorRenderer<'a> {
@@ -50,13 +60,11 @@ impl<'a> ColorRenderer<'a> {
         font_size: f32,
         direction: Option<Direction>,
         script: Option<Script>,
-    ) -> Self {
-        let font = harfrust::FontRef::new(&dfont.backing).unwrap_or_else(|_| {
-            panic!(
-                "error constructing a Font from data for {:}",
-                dfont.family_name()
-            );
-        });
+        language: Option<&Language>,
+        features: &[Feature],
+    ) -> Result<Self, Error> {
+        let font = harfrust::FontRef::new(&dfont.backing)?;
+        let upem = font.head()?.units_per_em();
         let shaper_data = ShaperData::new(&font);
 
         let instance = ShaperInstance::from_variations(
@@ -71,7 +79,9 @@ impl<'a> ColorRenderer<'a> {
 
         let plan = if let Some(direction) = direction {
             if script.is_some() {
-                Some(ShapePlan::new(&shaper, direction, script, None, &[]))
+                Some(ShapePlan::new(
+                    &shaper, direction, script, language, features,
+                ))
             } else {
                 None
             }
@@ -80,18 +90,29 @@ impl<'a> ColorRenderer<'a> {
         };
 
         let location: LocationRef = (&dfont.normalized_location).into();
-        let palette = read_cpal_palette(&font);
+        let palette = read_cpal_palette(&font, 0);
 
-        Self {
+        Ok(Self {
             shaper_data,
             font,
             plan,
             instance,
+            direction,
+            language: language.cloned(),
+            features: features.to_vec(),
             scale: font_size,
+            upem,
             palette,
             location,
             cache: HashMap::new(),
-        }
+        })
+    }
+
+    /// Draw COLR glyphs with another CPAL palette than the default
+    pub fn with_palette(mut self, index: u16) -> Self {
+        self.palette = read_cpal_palette(&self.font, index);
+        self.cache.clear();
+        self
     }
 
     /// Compute the tile dimensions and bearing for a glyph.
@@ -122,9 +143,20 @@ impl<'a> ColorRenderer<'a> {
     }
 
     /// Render a single glyph into a tile for caching.
+    ///
+    /// COLR glyphs are preferred, then SVG documents, then bitmaps; glyphs
+    /// with none of these are drawn from their outlines.
     fn render_glyph(&self, glyph_id: GlyphId) -> CachedColorGlyph {
-        let upem = self.font.head().unwrap().units_per_em() as f32;
-        let factor = self.scale / upem;
+        let color_glyphs = self.font.color_glyphs();
+        if color_glyphs.get(glyph_id).is_none() {
+            if let Some(tile) = render_svg_glyph(&self.font, glyph_id, self.scale, self.upem)
+                .or_else(|| render_bitmap_glyph(&self.font, glyph_id, self.scale, self.upem))
+            {
+                return tile;
+            }
+        }
+
+        let factor = self.scale / self.upem as f32;
 
         let (bearing_x, bearing_y, tile_w, tile_h) = self.glyph_tile_bounds(glyph_id);
 
@@ -142,7 +174,6 @@ impl<'a> ColorRenderer<'a> {
             dy: bearing_y,
         };
 
-        let color_glyphs = self.font.color_glyphs();
         if let Some(color_glyph) = color_glyphs.get(glyph_id) {
             painter.push_transform(transform);
             let _ = color_glyph.paint(self.location, &mut painter);
@@ -188,14 +219,19 @@ impl<'a> ColorRenderer<'a> {
             if let Some(lang) = plan.language() {
                 buffer.set_language(lang.clone());
             }
-            shaper.shape_with_plan(plan, buffer, &[])
+            shaper.shape_with_plan(plan, buffer, &self.features)
         } else {
             buffer.guess_segment_properties();
-            shaper.shape(buffer, &[])
+            if let Some(direction) = self.direction {
+                buffer.set_direction(direction);
+            }
+            if let Some(language) = &self.language {
+                buffer.set_language(language.clone());
+            }
+            shaper.shape(buffer, &self.features)
         };
 
-        let upem = self.font.head().unwrap().units_per_em() as f32;
-        let factor = self.scale / upem;
+        let factor = self.scale / self.upem as f32;
 
         let positions = output.glyph_positions();
         let infos = output.glyph_infos();
@@ -271,6 +307,19 @@ impl<'a> ColorRenderer<'a> {
     }
 }
 
+/// Read a CPAL palette from a font.
+fn read_cpal_palette(font: &skrifa::FontRef, index: u16) -> Vec<PaletteColor> {
+    palette(font, index)
+        .into_iter()
+        .map(|rec| PaletteColor {
+            r: rec.red,
+            g: rec.green,
+            b: rec.blue,
+            a: rec.alpha,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,8 +331,8 @@ mod tests {
     #[test]
     fn colrv1_render_produces_non_empty_image() {
         let data = load_test_font();
-        let dfont = DFont::new(&data);
-        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None);
+        let dfont = DFont::new(&data).unwrap();
+        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
         let (buffer, img) = renderer
             .render_string("hello")
             .expect("render_string returned None");
@@ -298,8 +347,8 @@ mod tests {
     #[test]
     fn colrv1_glyph_cache_is_reused() {
         let data = load_test_font();
-        let dfont = DFont::new(&data);
-        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None);
+        let dfont = DFont::new(&data).unwrap();
+        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
 
         // "ll" shares the same glyph; after rendering, the cache should contain it
         renderer.render_string("hello").unwrap();
@@ -318,8 +367,8 @@ mod tests {
     #[test]
     fn colrv1_cached_tiles_contain_color() {
         let data = load_test_font();
-        let dfont = DFont::new(&data);
-        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None);
+        let dfont = DFont::new(&data).unwrap();
+        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
 
         renderer.render_string("hello").unwrap();
 
@@ -338,9 +387,9 @@ mod tests {
     #[test]
     fn colrv1_same_font_has_zero_diff() {
         let data = load_test_font();
-        let dfont = DFont::new(&data);
-        let mut renderer_a = ColorRenderer::new(&dfont, 32.0, None, None);
-        let mut renderer_b = ColorRenderer::new(&dfont, 32.0, None, None);
+        let dfont = DFont::new(&data).unwrap();
+        let mut renderer_a = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
+        let mut renderer_b = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
 
         let (_, img_a) = renderer_a.render_string("world").unwrap();
         let (_, img_b) = renderer_b.render_string("world").unwrap();
@@ -348,28 +397,55 @@ mod tests {
         let diff = crate::render::utils::count_differences(img_a, img_b, 0);
         assert_eq!(diff, 0, "same font should produce identical images");
     }
-}
 
-/// Read the first CPAL palette from a font.
-fn read_cpal_palette(font: &skrifa::FontRef) -> Vec<PaletteColor> {
-    let cpal = match font.cpal() {
-        Ok(cpal) => cpal,
-        Err(_) => return vec![],
-    };
-    let num_entries = cpal.num_palette_entries();
-    let color_records = match cpal.color_records_array() {
-        Some(Ok(records)) => records,
-        _ => return vec![],
-    };
-    (0..num_entries)
-        .map(|i| {
-            let rec = color_records[i as usize];
-            PaletteColor {
-                r: rec.red,
-                g: rec.green,
-                b: rec.blue,
-                a: rec.alpha,
-            }
-        })
-        .collect()
+    /// Render a string in a test font, returning the image and the number
+    /// of cached tiles with color in them
+    fn render_test_font(filename: &str, string: &str) -> (GrayImage, usize) {
+        let data = std::fs::read(format!("test-data/{}", filename)).expect("missing test font");
+        let dfont = DFont::new(&data).unwrap();
+        let mut renderer = ColorRenderer::new(&dfont, 32.0, None, None, None, &[]).unwrap();
+        let (_, img) = renderer
+            .render_string(string)
+            .expect("render_string returned None");
+        let colored = renderer
+            .cache
+            .values()
+            .filter(|tile| {
+                tile.pixmap.pixels().iter().any(|px| {
+                    px.alpha() > 0 && !(px.red() == px.green() && px.green() == px.blue())
+                })
+            })
+            .count();
+        (img, colored)
+    }
+
+    #[test]
+    fn cbdt_render_produces_color_image() {
+        let (img, colored) = render_test_font("cbdt.ttf", "\u{2662}\u{1F600}");
+        assert!(
+            img.pixels().any(|p| p.0[0] > 0),
+            "image is completely blank"
+        );
+        assert_eq!(colored, 2, "bitmap glyphs were not drawn in color");
+    }
+
+    #[test]
+    fn sbix_render_produces_color_image() {
+        let (img, colored) = render_test_font("sbix.ttf", "\u{270D}");
+        assert!(
+            img.pixels().any(|p| p.0[0] > 0),
+            "image is completely blank"
+        );
+        assert_eq!(colored, 1, "bitmap glyph was not drawn in color");
+    }
+
+    #[test]
+    fn svg_render_produces_color_image() {
+        let (img, colored) = render_test_font("svg.ttf", "\u{2662}\u{1F600}");
+        assert!(
+            img.pixels().any(|p| p.0[0] > 0),
+            "image is completely blank"
+        );
+        assert_eq!(colored, 2, "SVG glyphs were not drawn in color");
+    }
 }
@@ -4,6 +4,7 @@ use std::fmt::Display;
 use super::{DEFAULT_GLYPHS_FONT_SIZE, DEFAULT_GLYPHS_THRESHOLD};
 pub use crate::structs::{CmapDiff, EncodedGlyph};
 use crate::{
+    comparison::ProgressCallback,
     dfont::DFont,
     render::{diff_many_words, GlyphDiff},
 };
@@ -58,7 +59,25 @@ impl CmapDiff {
 }
 
 /// Render the encoded glyphs common to both fonts, and return any differences
+///
+/// Glyphs which can't be rendered are left out, and the problems logged.
 pub fn modified_encoded_glyphs(font_a: &DFont, font_b: &DFont) -> Vec<GlyphDiff> {
+    let mut errors = vec![];
+    let differences = encoded_glyph_differences(font_a, font_b, &|_| {}, &mut errors);
+    for error in errors {
+        log::warn!("{}", error);
+    }
+    differences
+}
+
+/// As [modified_encoded_glyphs], reporting progress through a callback and
+/// problems rendering glyphs in `errors`
+pub(crate) fn encoded_glyph_differences(
+    font_a: &DFont,
+    font_b: &DFont,
+    progress: &ProgressCallback,
+    errors: &mut Vec<String>,
+) -> Vec<GlyphDiff> {
     let cmap_a = &font_a.codepoints;
     let cmap_b = &font_b.codepoints;
     let same_glyphs = cmap_a.intersection(cmap_b);
@@ -71,9 +90,11 @@ pub fn modified_encoded_glyphs(font_a: &DFont, font_b: &DFont) -> Vec<GlyphDiff>
         font_a,
         font_b,
         DEFAULT_GLYPHS_FONT_SIZE,
-        &wl,
+        &(&wl).into(),
         None,
         DEFAULT_GLYPHS_THRESHOLD,
+        progress,
+        errors,
     )
     .into_iter()
     .map(|x| x.into())
@@ -2,32 +2,42 @@
 ///
 /// The routines in this file handle the rendering and comparison of text
 /// strings; the actual rendering proper is done in the `renderer` module.
+mod bitmapglyphs;
 mod cachedoutlines;
 pub(crate) mod colorpainter;
 pub(crate) mod colorrenderer;
 pub mod encodedglyphs;
 pub mod renderer;
+mod svgglyphs;
 pub mod utils;
 pub mod wordlists;
 pub use crate::structs::{Difference, GlyphDiff};
 use crate::{
+    comparison::{Progress, ProgressCallback},
     dfont::DFont,
-    render::{utils::count_differences, wordlists::direction_from_script},
+    error::Error,
+    palettes::{changed_palettes, shared_palettes},
+    render::{
+        utils::count_differences,
+        wordlists::{
+            direction_from_script, features_label, language_label, CustomWordList,
+            GeneratedWordList, LanguageWordList,
+        },
+    },
 };
 use cfg_if::cfg_if;
 use colorrenderer::ColorRenderer;
-use harfrust::Script;
+use harfrust::{Direction, Feature, Language, Script};
 use renderer::Renderer;
 use skrifa::raw::TableProvider;
 use static_lang_word_lists::WordList;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     str::FromStr,
 };
 
 cfg_if! {
     if #[cfg(not(target_family = "wasm"))] {
-        use indicatif::ParallelProgressIterator;
         use rayon::iter::ParallelIterator;
         use thread_local::ThreadLocal;
         use std::cell::RefCell;
@@ -46,24 +56,178 @@ pub const DEFAULT_WORDS_THRESHOLD: usize = 8;
 pub const DEFAULT_GLYPHS_THRESHOLD: usize = 16;
 /// Gray pixels which differ by less than this amount are considered the same
 pub const DEFAULT_GRAY_FUZZ: u8 = 8;
+/// Glyphs named in a message about glyphs which couldn't be drawn
+const MAX_LISTED_GLYPHS: usize = 10;
 
-/// Returns true if the font has a COLR table.
-fn font_has_colr(dfont: &DFont) -> bool {
-    dfont.fontref().colr().is_ok()
+/// Returns true if the font has color glyphs: a COLR or SVG table, or
+/// sbix or CBDT bitmaps.
+fn font_has_color_glyphs(dfont: &DFont) -> bool {
+    let font = dfont.fontref();
+    font.colr().is_ok()
+        || svgglyphs::has_svg_glyphs(&font)
+        || bitmapglyphs::has_color_bitmaps(&font)
+}
+
+/// A problem rendering a word in one of the fonts
+type RenderError = (&'static str, Error);
+
+fn in_font_a(error: Error) -> RenderError {
+    ("A", error)
+}
+
+fn in_font_b(error: Error) -> RenderError {
+    ("B", error)
+}
+
+/// Describe the problems rendering a word list, once however many words each affects
+///
+/// Glyphs without outlines are summarized into one message for each font.
+fn record_errors(errors: &mut Vec<String>, wordlist: &str, problems: Vec<RenderError>) {
+    for font in ["A", "B"] {
+        let mut missing = BTreeSet::new();
+        for (_, error) in problems.iter().filter(|(f, _)| *f == font) {
+            if let Error::MissingOutline(gid) = error {
+                missing.insert(gid.to_u32());
+            } else {
+                let message = format!("Font {}: {}", font, error);
+                if !errors.contains(&message) {
+                    errors.push(message);
+                }
+            }
+        }
+        if !missing.is_empty() {
+            let mut listed = missing
+                .iter()
+                .take(MAX_LISTED_GLYPHS)
+                .map(|gid| gid.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            if missing.len() > MAX_LISTED_GLYPHS {
+                listed.push_str(&format!(" and {} more", missing.len() - MAX_LISTED_GLYPHS));
+            }
+            errors.push(format!(
+                "Font {}: No outline for {} glyph(s) used in {}, so they were not compared (glyph IDs {})",
+                font,
+                missing.len(),
+                wordlist,
+                listed
+            ));
+        }
+    }
+}
+
+/// A word list to test, along with the properties needed to shape its words
+pub struct WordListJob<'a> {
+    /// The words to test
+    pub wordlist: &'a WordList,
+    /// The script to shape with; if `None`, segment properties are guessed
+    pub script: Option<Script>,
+    /// The language to shape with, if any
+    pub language: Option<&'a Language>,
+    /// The direction to shape with; if `None`, it is derived from the script
+    pub direction: Option<Direction>,
+    /// OpenType features to apply when shaping
+    pub features: &'a [Feature],
+}
+
+impl<'a> From<&'a WordList> for WordListJob<'a> {
+    fn from(wordlist: &'a WordList) -> Self {
+        WordListJob {
+            wordlist,
+            script: wordlist.script().and_then(|x| Script::from_str(x).ok()),
+            language: None,
+            direction: None,
+            features: &[],
+        }
+    }
+}
+
+impl<'a> From<&'a GeneratedWordList> for WordListJob<'a> {
+    fn from(gwl: &'a GeneratedWordList) -> Self {
+        WordListJob {
+            wordlist: &gwl.wordlist,
+            script: gwl.script,
+            language: None,
+            direction: None,
+            features: &[],
+        }
+    }
+}
+
+impl<'a> From<&'a LanguageWordList> for WordListJob<'a> {
+    fn from(lwl: &'a LanguageWordList) -> Self {
+        WordListJob {
+            wordlist: &lwl.wordlist,
+            script: lwl.script,
+            language: lwl.language.as_ref(),
+            direction: None,
+            features: &[],
+        }
+    }
+}
+
+impl<'a> From<&'a CustomWordList> for WordListJob<'a> {
+    fn from(cwl: &'a CustomWordList) -> Self {
+        WordListJob {
+            wordlist: &cwl.wordlist,
+            script: cwl.script,
+            language: cwl.language.as_ref(),
+            direction: cwl.direction,
+            features: &cwl.features,
+        }
+    }
 }
 
 /// Compare two fonts by rendering a list of words and comparing the images
 ///
 /// Word lists are gathered for all scripts which are supported by both fonts.
-/// The return value is a BTreeMap where each key is a script tag and the
-/// value is a list of  [Difference] objects.
+/// Scripts without a static word list get a generated one (see
+/// [wordlists::generate_wordlist]).
+/// If `by_language` is true, word lists are instead built from the sample
+/// texts of each language which can be rendered in both fonts, and shaped
+/// with that language's OpenType language system.
+/// `thresholds` overrides [DEFAULT_WORDS_THRESHOLD] for the scripts it lists,
+/// keyed by ISO 15924 tag (e.g. `Arab`).
+/// The return value is a BTreeMap where each key is a script tag (or
+/// language name) and the value is a list of [Difference] objects.
+/// Words which can't be rendered are left out, and the problems logged.
 pub fn test_font_words(
     font_a: &DFont,
     font_b: &DFont,
-    custom_inputs: &[WordList],
+    custom_inputs: &[CustomWordList],
+    by_language: bool,
+    thresholds: &HashMap<String, usize>,
+) -> BTreeMap<String, Vec<Difference>> {
+    let mut errors = vec![];
+    let differences = word_differences(
+        font_a,
+        font_b,
+        custom_inputs,
+        by_language,
+        thresholds,
+        &|_| {},
+        &mut errors,
+    );
+    for error in errors {
+        log::warn!("{}", error);
+    }
+    differences
+}
+
+/// As [test_font_words], reporting progress through a callback and
+/// problems rendering words in `errors`
+pub(crate) fn word_differences(
+    font_a: &DFont,
+    font_b: &DFont,
+    custom_inputs: &[CustomWordList],
+    by_language: bool,
+    thresholds: &HashMap<String, usize>,
+    progress: &ProgressCallback,
+    errors: &mut Vec<String>,
 ) -> BTreeMap<String, Vec<Difference>> {
     let mut map: BTreeMap<String, Vec<Difference>> = BTreeMap::new();
-    let mut jobs: Vec<&WordList> = vec![];
+    let mut jobs: Vec<WordListJob> = vec![];
+    let mut generated: Vec<GeneratedWordList> = vec![];
 
     let shared_codepoints = font_a
         .codepoints
@@ -71,28 +235,46 @@ pub fn test_font_words(
         .copied()
         .collect();
 
-    let supported_a = font_a.supported_scripts();
-    let supported_b = font_b.supported_scripts();
-
     // Create the jobs
-    for script in supported_a.intersection(&supported_b) {
-        if let Some(wordlist) = wordlists::get_wordlist(script) {
-            jobs.push(wordlist);
+    if by_language {
+        for language_wordlist in wordlists::get_language_wordlists(&shared_codepoints) {
+            jobs.push(language_wordlist.into());
         }
+    } else {
+        let supported_a = font_a.supported_scripts();
+        let supported_b = font_b.supported_scripts();
+        let scripts: Vec<&String> = supported_a.intersection(&supported_b).collect();
+        for script in scripts.iter() {
+            if let Some(wordlist) = wordlists::get_wordlist(script) {
+                jobs.push(wordlist.into());
+            }
+        }
+        generated.extend(wordlists::generate_wordlists(scripts, &shared_codepoints));
     }
-    jobs.extend(custom_inputs.iter());
+    jobs.extend(generated.iter().map(WordListJob::from));
+    jobs.extend(custom_inputs.iter().map(WordListJob::from));
     // Process the jobs
-    for job in jobs.iter_mut() {
+    for job in jobs.iter() {
+        let threshold = job
+            .script
+            .and_then(|script| thresholds.get(&script.tag().to_string()))
+            .copied()
+            .unwrap_or(DEFAULT_WORDS_THRESHOLD);
         let results = diff_many_words(
             font_a,
             font_b,
             DEFAULT_WORDS_FONT_SIZE,
             job,
             Some(&shared_codepoints),
-            DEFAULT_WORDS_THRESHOLD,
+            threshold,
+            progress,
+            errors,
         );
         if !results.is_empty() {
-            map.insert(job.name().to_string(), results);
+            // Sections of the same custom word list are reported together
+            let differences = map.entry(job.wordlist.name().to_string()).or_default();
+            differences.extend(results);
+            differences.sort_by_key(|x| -(x.differing_pixels as i32));
         }
     }
     map
@@ -108,6 +290,7 @@ impl From<Difference> for GlyphDiff {
                     .unwrap_or_default(),
                 unicode: format!("U+{:04X}", c as i32),
                 differing_pixels: diff.differing_pixels,
+                palette: diff.palette,
             }
         } else {
             GlyphDiff {
@@ -115,6 +298,7 @@ impl From<Difference> for GlyphDiff {
                 name: "".to_string(),
                 unicode: "".to_string(),
                 differing_pixels: 0,
+                palette: None,
             }
         }
     }
@@ -125,68 +309,54 @@ impl From<Difference> for GlyphDiff {
 /// Compare two fonts by rendering a list of words and comparing the images
 ///
 /// This function is parallelized and uses rayon to speed up the process.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn diff_many_words(
     font_a: &DFont,
     font_b: &DFont,
     font_size: f32,
-    wordlist: &WordList,
+    job: &WordListJob,
     shared_codepoints: Option<&HashSet<u32>>,
     threshold: usize,
+    progress: &ProgressCallback,
+    errors: &mut Vec<String>,
 ) -> Vec<Difference> {
-    let script = wordlist.script().and_then(|x| Script::from_str(x).ok());
-    let direction = script.and_then(direction_from_script);
-    let seen_glyphs = RwLock::new(HashSet::new());
-    let use_color = font_has_colr(font_a) || font_has_colr(font_b);
-
-    let differences: Vec<Option<Difference>> = if use_color {
-        let tl_a: ThreadLocal<RefCell<ColorRenderer>> = ThreadLocal::new();
-        let tl_b: ThreadLocal<RefCell<ColorRenderer>> = ThreadLocal::new();
-        wordlist
-            .par_iter()
-            .progress()
-            .filter(|word| {
-                shared_codepoints
-                    .as_ref()
-                    .is_none_or(|scp| word.chars().all(|c| scp.contains(&(c as u32))))
-            })
-            .map(|word| {
-                let renderer_a = tl_a.get_or(|| {
-                    RefCell::new(ColorRenderer::new(font_a, font_size, direction, script))
-                });
-                let renderer_b = tl_b.get_or(|| {
-                    RefCell::new(ColorRenderer::new(font_b, font_size, direction, script))
-                });
-
-                let (buffer_a, img_a) = renderer_a.borrow_mut().render_string(word)?;
-                if buffer_a
-                    .split('|')
-                    .all(|glyph| seen_glyphs.read().unwrap().contains(glyph))
-                {
-                    return None;
-                }
-                for glyph in buffer_a.split('|') {
-                    seen_glyphs.write().unwrap().insert(glyph.to_string());
-                }
-                let (buffer_b, img_b) = renderer_b.borrow_mut().render_string(word)?;
-                let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
-                let buffers_same = buffer_a == buffer_b;
+    let (wordlist, script, language, features) =
+        (job.wordlist, job.script, job.language, job.features);
+    let lang = language.map(language_label).unwrap_or_default();
+    let ot_features = features_label(features);
+    let direction = job
+        .direction
+        .or_else(|| script.and_then(direction_from_script));
+    let use_color = font_has_color_glyphs(font_a) || font_has_color_glyphs(font_b);
+    // Color glyphs are compared in each palette the fonts share
+    let palettes = if use_color {
+        shared_palettes(font_a, font_b)
+    } else {
+        1
+    };
+    progress(Progress::WordList {
+        name: wordlist.name(),
+        words: wordlist.len(),
+    });
 
-                Some(Difference {
-                    word: word.to_string(),
-                    buffer_a,
-                    buffer_b: if buffers_same { None } else { Some(buffer_b) },
-                    differing_pixels,
-                    ot_features: "".to_string(),
-                    lang: "".to_string(),
-                })
-            })
-            .collect()
+    let differences: Vec<Result<Option<Difference>, RenderError>> = if use_color {
+        diff_color_words(
+            font_a,
+            font_b,
+            font_size,
+            job,
+            shared_codepoints,
+            palettes,
+            threshold,
+            progress,
+        )
     } else {
+        let seen_glyphs = RwLock::new(HashSet::new());
         let tl_a: ThreadLocal<RefCell<Renderer>> = ThreadLocal::new();
         let tl_b: ThreadLocal<RefCell<Renderer>> = ThreadLocal::new();
         wordlist
             .par_iter()
-            .progress()
+            .inspect(|_| progress(Progress::Word))
             .filter(|word| {
                 shared_codepoints
                     .as_ref()
@@ -194,25 +364,43 @@ pub(crate) fn diff_many_words(
             })
             .map(|word| {
                 let renderer_a = tl_a
-                    .get_or(|| RefCell::new(Renderer::new(font_a, font_size, direction, script)));
+                    .get_or_try(|| {
+                        Renderer::new(font_a, font_size, direction, script, language, features)
+                            .map(RefCell::new)
+                    })
+                    .map_err(in_font_a)?;
                 let renderer_b = tl_b
-                    .get_or(|| RefCell::new(Renderer::new(font_b, font_size, direction, script)));
+                    .get_or_try(|| {
+                        Renderer::new(font_b, font_size, direction, script, language, features)
+                            .map(RefCell::new)
+                    })
+                    .map_err(in_font_b)?;
 
-                let (buffer_a, commands_a) =
-                    renderer_a.borrow_mut().string_to_positioned_glyphs(word)?;
+                let Some((buffer_a, commands_a)) = renderer_a
+                    .borrow_mut()
+                    .string_to_positioned_glyphs(word)
+                    .map_err(in_font_a)?
+                else {
+                    return Ok(None);
+                };
                 if buffer_a
                     .split('|')
                     .all(|glyph| seen_glyphs.read().unwrap().contains(glyph))
                 {
-                    return None;
+                    return Ok(None);
                 }
                 for glyph in buffer_a.split('|') {
                     seen_glyphs.write().unwrap().insert(glyph.to_string());
                 }
-                let (buffer_b, commands_b) =
-                    renderer_b.borrow_mut().string_to_positioned_glyphs(word)?;
+                let Some((buffer_b, commands_b)) = renderer_b
+                    .borrow_mut()
+                    .string_to_positioned_glyphs(word)
+                    .map_err(in_font_b)?
+                else {
+                    return Ok(None);
+                };
                 if commands_a == commands_b {
-                    return None;
+                    return Ok(None);
                 }
                 let img_a = renderer_a
                     .borrow_mut()
@@ -223,120 +411,389 @@ pub(crate) fn diff_many_words(
                 let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
                 let buffers_same = buffer_a == buffer_b;
 
-                Some(Difference {
+                Ok(Some(Difference {
                     word: word.to_string(),
                     buffer_a,
                     buffer_b: if buffers_same { None } else { Some(buffer_b) },
                     differing_pixels,
-                    ot_features: "".to_string(),
-                    lang: "".to_string(),
-                })
+                    ot_features: ot_features.clone(),
+                    lang: lang.clone(),
+                    palette: None,
+                }))
             })
             .collect()
     };
 
-    let mut diffs: Vec<Difference> = differences
-        .into_iter()
-        .flatten()
-        .filter(|diff| diff.differing_pixels > threshold)
-        .collect();
+    let mut diffs: Vec<Difference> = vec![];
+    let mut problems = vec![];
+    for difference in differences {
+        match difference {
+            Ok(Some(diff)) if diff.differing_pixels > threshold => diffs.push(diff),
+            Ok(_) => {}
+            Err(problem) => problems.push(problem),
+        }
+    }
+    record_errors(errors, wordlist.name(), problems);
     diffs.sort_by_key(|x| -(x.differing_pixels as i32));
     diffs
 }
 
+/// Compare the words of a job in fonts with color glyphs, drawing them
+/// with each of the first `palettes` CPAL palettes
+///
+/// Differences in the default palette are reported as usual. A word is
+/// only reported in another palette, and tagged with it, if it differs
+/// there by a different number of pixels, and either the palette's colors
+/// changed or the word doesn't differ in the default palette; glyphs which
+/// don't use the palette, and changes to the glyphs themselves, are
+/// reported once, with the default palette.
+#[cfg(not(target_family = "wasm"))]
+#[allow(clippy::too_many_arguments)]
+fn diff_color_words(
+    font_a: &DFont,
+    font_b: &DFont,
+    font_size: f32,
+    job: &WordListJob,
+    shared_codepoints: Option<&HashSet<u32>>,
+    palettes: u16,
+    threshold: usize,
+    progress: &ProgressCallback,
+) -> Vec<Result<Option<Difference>, RenderError>> {
+    let (wordlist, script, language, features) =
+        (job.wordlist, job.script, job.language, job.features);
+    let lang = language.map(language_label).unwrap_or_default();
+    let ot_features = features_label(features);
+    let direction = job
+        .direction
+        .or_else(|| script.and_then(direction_from_script));
+    let seen_glyphs = RwLock::new(HashSet::new());
+    let changed = changed_palettes(font_a, font_b);
+    // One renderer per palette, in each font
+    let renderers = |font| {
+        (0..palettes)
+            .map(|palette| {
+                ColorRenderer::new(font, font_size, direction, script, language, features)
+                    .map(|renderer| renderer.with_palette(palette))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(RefCell::new)
+    };
+    let tl_a: ThreadLocal<RefCell<Vec<ColorRenderer>>> = ThreadLocal::new();
+    let tl_b: ThreadLocal<RefCell<Vec<ColorRenderer>>> = ThreadLocal::new();
+    let compare = |word: &str| -> Result<Vec<Difference>, RenderError> {
+        let mut renderers_a = tl_a
+            .get_or_try(|| renderers(font_a))
+            .map_err(in_font_a)?
+            .borrow_mut();
+        let mut renderers_b = tl_b
+            .get_or_try(|| renderers(font_b))
+            .map_err(in_font_b)?
+            .borrow_mut();
+
+        let Some((buffer_a, img_a)) = renderers_a[0].render_string(word) else {
+            return Ok(vec![]);
+        };
+        if buffer_a
+            .split('|')
+            .all(|glyph| seen_glyphs.read().unwrap().contains(glyph))
+        {
+            return Ok(vec![]);
+        }
+        for glyph in buffer_a.split('|') {
+            seen_glyphs.write().unwrap().insert(glyph.to_string());
+        }
+        let Some((buffer_b, img_b)) = renderers_b[0].render_string(word) else {
+            return Ok(vec![]);
+        };
+        let default_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
+        let buffer_b = (buffer_a != buffer_b).then_some(buffer_b);
+        let difference = |differing_pixels, palette| Difference {
+            word: word.to_string(),
+            buffer_a: buffer_a.clone(),
+            buffer_b: buffer_b.clone(),
+            differing_pixels,
+            ot_features: ot_features.clone(),
+            lang: lang.clone(),
+            palette,
+        };
+
+        let mut differences = vec![difference(default_pixels, None)];
+        for palette in 1..palettes {
+            let index = palette as usize;
+            let (Some((_, img_a)), Some((_, img_b))) = (
+                renderers_a[index].render_string(word),
+                renderers_b[index].render_string(word),
+            ) else {
+                continue;
+            };
+            let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
+            if differing_pixels != default_pixels
+                && (default_pixels <= threshold || changed.contains(&palette))
+            {
+                differences.push(difference(differing_pixels, Some(palette)));
+            }
+        }
+        Ok(differences)
+    };
+
+    wordlist
+        .par_iter()
+        .inspect(|_| progress(Progress::Word))
+        .filter(|word| {
+            shared_codepoints
+                .as_ref()
+                .is_none_or(|scp| word.chars().all(|c| scp.contains(&(c as u32))))
+        })
+        .flat_map_iter(|word| match compare(word) {
+            Ok(differences) => differences.into_iter().map(|d| Ok(Some(d))).collect(),
+            Err(problem) => vec![Err(problem)],
+        })
+        .collect()
+}
+
 // A slow and simple version
 #[cfg(target_family = "wasm")]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn diff_many_words(
     font_a: &DFont,
     font_b: &DFont,
     font_size: f32,
-    wordlist: &WordList,
+    job: &WordListJob,
     shared_codepoints: Option<&HashSet<u32>>,
     threshold: usize,
+    progress: &ProgressCallback,
+    errors: &mut Vec<String>,
 ) -> Vec<Difference> {
-    let script = wordlist.script().and_then(|x| Script::from_str(x).ok());
-    let direction = script.and_then(|s| direction_from_script(s));
-    let use_color = font_has_colr(font_a) || font_has_colr(font_b);
+    let (wordlist, script, language, features) =
+        (job.wordlist, job.script, job.language, job.features);
+    let lang = language.map(language_label).unwrap_or_default();
+    let ot_features = features_label(features);
+    let direction = job
+        .direction
+        .or_else(|| script.and_then(|s| direction_from_script(s)));
+    let use_color = font_has_color_glyphs(font_a) || font_has_color_glyphs(font_b);
+    // Color glyphs are compared in each palette the fonts share
+    let palettes = if use_color {
+        shared_palettes(font_a, font_b)
+    } else {
+        1
+    };
+    progress(Progress::WordList {
+        name: wordlist.name(),
+        words: wordlist.len(),
+    });
     let mut seen_glyphs: HashSet<String> = HashSet::new();
     let mut differences: Vec<Difference> = vec![];
+    let mut problems = vec![];
 
     if use_color {
-        let mut renderer_a = ColorRenderer::new(font_a, font_size, direction, script);
-        let mut renderer_b = ColorRenderer::new(font_b, font_size, direction, script);
-
-        for word in wordlist.iter() {
-            if let Some(scp) = shared_codepoints {
-                if !word.chars().all(|c| scp.contains(&(c as u32))) {
-                    continue;
+        let changed = changed_palettes(font_a, font_b);
+        // One renderer per palette, in each font
+        let renderers = |font| {
+            (0..palettes)
+                .map(|palette| {
+                    ColorRenderer::new(font, font_size, direction, script, language, features)
+                        .map(|renderer| renderer.with_palette(palette))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let renderers = renderers(font_a)
+            .map_err(in_font_a)
+            .and_then(|a| renderers(font_b).map_err(in_font_b).map(|b| (a, b)));
+        match renderers {
+            Ok((mut renderers_a, mut renderers_b)) => {
+                for word in wordlist.iter() {
+                    progress(Progress::Word);
+                    if let Some(scp) = shared_codepoints {
+                        if !word.chars().all(|c| scp.contains(&(c as u32))) {
+                            continue;
+                        }
+                    }
+                    let Some((buffer_a, img_a)) = renderers_a[0].render_string(&word) else {
+                        continue;
+                    };
+                    if buffer_a.split('|').all(|glyph| seen_glyphs.contains(glyph)) {
+                        continue;
+                    }
+                    for glyph in buffer_a.split('|') {
+                        seen_glyphs.insert(glyph.to_string());
+                    }
+                    let Some((buffer_b, img_b)) = renderers_b[0].render_string(&word) else {
+                        continue;
+                    };
+                    let buffer_b = (buffer_a != buffer_b).then_some(buffer_b);
+                    let default_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
+                    // Other palettes only report what the default palette doesn't
+                    // (see diff_color_words)
+                    let mut found = vec![(default_pixels, None)];
+                    for palette in 1..palettes {
+                        let index = palette as usize;
+                        let (Some((_, img_a)), Some((_, img_b))) = (
+                            renderers_a[index].render_string(&word),
+                            renderers_b[index].render_string(&word),
+                        ) else {
+                            continue;
+                        };
+                        let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
+                        if differing_pixels != default_pixels
+                            && (default_pixels <= threshold || changed.contains(&palette))
+                        {
+                            found.push((differing_pixels, Some(palette)));
+                        }
+                    }
+                    for (differing_pixels, palette) in found {
+                        if differing_pixels > threshold {
+                            differences.push(Difference {
+                                word: word.to_string(),
+                                buffer_a: buffer_a.clone(),
+                                buffer_b: buffer_b.clone(),
+                                ot_features: ot_features.clone(),
+                                lang: lang.clone(),
+                                differing_pixels,
+                                palette,
+                            });
+                        }
+                    }
                 }
             }
-            let Some((buffer_a, img_a)) = renderer_a.render_string(&word) else {
-                continue;
-            };
-            if buffer_a.split('|').all(|glyph| seen_glyphs.contains(glyph)) {
-                continue;
-            }
-            for glyph in buffer_a.split('|') {
-                seen_glyphs.insert(glyph.to_string());
-            }
-            let Some((buffer_b, img_b)) = renderer_b.render_string(&word) else {
-                continue;
-            };
-            let buffers_same = buffer_a == buffer_b;
-            let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
-            if differing_pixels > threshold {
-                differences.push(Difference {
-                    word: word.to_string(),
-                    buffer_a,
-                    buffer_b: if buffers_same { None } else { Some(buffer_b) },
-                    ot_features: "".to_string(),
-                    lang: "".to_string(),
-                    differing_pixels,
-                });
+            Err(problem) => {
+                // No word can be rendered; count them all as done
+                problems.push(problem);
+                (0..wordlist.len()).for_each(|_| progress(Progress::Word));
             }
         }
     } else {
-        let mut renderer_a = Renderer::new(font_a, font_size, direction, script);
-        let mut renderer_b = Renderer::new(font_b, font_size, direction, script);
-
-        for word in wordlist.iter() {
-            if let Some(scp) = shared_codepoints {
-                if !word.chars().all(|c| scp.contains(&(c as u32))) {
-                    continue;
+        let renderers = Renderer::new(font_a, font_size, direction, script, language, features)
+            .map_err(in_font_a)
+            .and_then(|a| {
+                Renderer::new(font_b, font_size, direction, script, language, features)
+                    .map_err(in_font_b)
+                    .map(|b| (a, b))
+            });
+        match renderers {
+            Ok((mut renderer_a, mut renderer_b)) => {
+                for word in wordlist.iter() {
+                    progress(Progress::Word);
+                    if let Some(scp) = shared_codepoints {
+                        if !word.chars().all(|c| scp.contains(&(c as u32))) {
+                            continue;
+                        }
+                    }
+                    let (buffer_a, commands_a) = match renderer_a.string_to_positioned_glyphs(&word)
+                    {
+                        Ok(Some(rendered)) => rendered,
+                        Ok(None) => continue,
+                        Err(error) => {
+                            problems.push(in_font_a(error));
+                            continue;
+                        }
+                    };
+                    if buffer_a.split('|').all(|glyph| seen_glyphs.contains(glyph)) {
+                        continue;
+                    }
+                    for glyph in buffer_a.split('|') {
+                        seen_glyphs.insert(glyph.to_string());
+                    }
+                    let (buffer_b, commands_b) = match renderer_b.string_to_positioned_glyphs(&word)
+                    {
+                        Ok(Some(rendered)) => rendered,
+                        Ok(None) => continue,
+                        Err(error) => {
+                            problems.push(in_font_b(error));
+                            continue;
+                        }
+                    };
+                    if commands_a == commands_b {
+                        continue;
+                    }
+                    let buffers_same = buffer_a == buffer_b;
+                    let img_a = renderer_a.render_positioned_glyphs(&commands_a);
+                    let img_b = renderer_b.render_positioned_glyphs(&commands_b);
+                    let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
+                    if differing_pixels > threshold {
+                        differences.push(Difference {
+                            word: word.to_string(),
+                            buffer_a,
+                            buffer_b: if buffers_same { None } else { Some(buffer_b) },
+                            ot_features: ot_features.clone(),
+                            lang: lang.clone(),
+                            differing_pixels,
+                            palette: None,
+                        });
+                    }
                 }
             }
-            let Some((buffer_a, commands_a)) = renderer_a.string_to_positioned_glyphs(&word) else {
-                continue;
-            };
-            if buffer_a.split('|').all(|glyph| seen_glyphs.contains(glyph)) {
-                continue;
-            }
-            for glyph in buffer_a.split('|') {
-                seen_glyphs.insert(glyph.to_string());
-            }
-            let Some((buffer_b, commands_b)) = renderer_b.string_to_positioned_glyphs(&word) else {
-                continue;
-            };
-            if commands_a == commands_b {
-                continue;
-            }
-            let buffers_same = buffer_a == buffer_b;
-            let img_a = renderer_a.render_positioned_glyphs(&commands_a);
-            let img_b = renderer_b.render_positioned_glyphs(&commands_b);
-            let differing_pixels = count_differences(img_a, img_b, DEFAULT_GRAY_FUZZ);
-            if differing_pixels > threshold {
-                differences.push(Difference {
-                    word: word.to_string(),
-                    buffer_a,
-                    buffer_b: if buffers_same { None } else { Some(buffer_b) },
-                    ot_features: "".to_string(),
-                    lang: "".to_string(),
-                    differing_pixels,
-                });
+            Err(problem) => {
+                // No word can be rendered; count them all as done
+                problems.push(problem);
+                (0..wordlist.len()).for_each(|_| progress(Progress::Word));
             }
         }
     }
 
+    record_errors(errors, wordlist.name(), problems);
     differences.sort_by_key(|x| -(x.differing_pixels as i32));
     differences
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setting::parse_location;
+    use crate::{
+        palettes::tests::{with_color, with_counts, with_shared_palette},
+        test_fonts::nabla,
+    };
+    use wordlists::parse_custom_wordlist;
+
+    fn differences(font_a: &DFont, font_b: &DFont) -> Vec<Difference> {
+        let wordlist = parse_custom_wordlist("test", "hello\nworld").unwrap();
+        let mut errors = vec![];
+        let differences = diff_many_words(
+            font_a,
+            font_b,
+            DEFAULT_WORDS_FONT_SIZE,
+            &WordListJob::from(&wordlist[0]),
+            None,
+            DEFAULT_WORDS_THRESHOLD,
+            &|_| {},
+            &mut errors,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        differences
+    }
+
+    #[test]
+    fn differences_in_other_palettes_are_tagged() {
+        let font_a = with_counts(&nabla(), 2, 10);
+        let font_b = with_color(&font_a, 1, 0, [0, 0, 0xff, 0xff]);
+
+        let differences = differences(&DFont::new(&font_a).unwrap(), &DFont::new(&font_b).unwrap());
+        assert!(!differences.is_empty());
+        assert!(differences.iter().all(|d| d.palette == Some(1)));
+    }
+
+    #[test]
+    fn differences_in_every_palette_are_reported_once() {
+        // Both palettes use the same colors, so a change shows up in both
+        let font_a = with_shared_palette(&with_counts(&nabla(), 2, 10), 1, 0);
+        let font_b = with_color(&font_a, 0, 0, [0, 0, 0xff, 0xff]);
+
+        let differences = differences(&DFont::new(&font_a).unwrap(), &DFont::new(&font_b).unwrap());
+        assert!(!differences.is_empty());
+        assert!(differences.iter().all(|d| d.palette.is_none()));
+    }
+
+    #[test]
+    fn outline_differences_are_reported_once() {
+        // The palettes are the same, but the glyphs are drawn differently
+        let font_a = DFont::new(&with_counts(&nabla(), 2, 10)).unwrap();
+        let mut font_b = DFont::new(&with_counts(&nabla(), 2, 10)).unwrap();
+        font_b.location = parse_location("EDPT=200").unwrap();
+        font_b.normalize_location();
+
+        let differences = differences(&font_a, &font_b);
+        assert!(!differences.is_empty());
+        assert!(differences.iter().all(|d| d.palette.is_none()));
+    }
+}
@@ -1,6 +1,7 @@
 /// Turn some words into images
 use harfrust::{
-    Direction, Script, ShapePlan, ShaperData, ShaperInstance, UnicodeBuffer, Variation,
+    Direction, Feature, Language, Script, ShapePlan, ShaperData, ShaperInstance, UnicodeBuffer,
+    Variation,
 };
 use image::{DynamicImage, GrayImage, Luma};
 use skrifa::{instance::Size, raw::TableProvider, GlyphId, MetadataProvider};
@@ -10,33 +11,37 @@ use super::{
     cachedoutlines::CachedOutlineGlyphCollection,
     utils::{terrible_bounding_box, RecordingPen},
 };
-use crate::dfont::DFont;
+use crate::{dfont::DFont, error::Error};
 
 pub struct Renderer<'a> {
     shaper_data: ShaperData,
     scale: f32,
+    upem: u16,
     font: skrifa::FontRef<'a>,
     plan: Option<ShapePlan>,
     instance: ShaperInstance,
     outlines: CachedOutlineGlyphCollection<'a>,
+    direction: Option<Direction>,
+    language: Option<Language>,
+    features: Vec<Feature>,
 }
 
 impl<'a> Renderer<'a> {
     /// Create a new renderer for a font
     ///
     /// Direction and script are needed for correct shaping; no automatic detection is done.
+    /// If a language is given, its OpenType language system is used when shaping,
+    /// and any features are applied to the whole string.
     pub fn new(
         dfont: &'a DFont,
         font_size: f32,
         direction: Option<Direction>,
         script: Option<Script>,
-    ) -> Self {
-        let font = harfrust::FontRef::new(&dfont.backing).unwrap_or_else(|_| {
-            panic!(
-                "error constructing a Font from data for {:}",
-                dfont.family_name()
-            );
-        });
+        language: Option<&Language>,
+        features: &[Feature],
+    ) -> Result<Self, Error> {
+        let font = harfrust::FontRef::new(&dfont.backing)?;
+        let upem = font.head()?.units_per_em();
         let shaper_data = ShaperData::new(&font);
 
         // Convert our location into a structure that rustybuzz/harfruzz can use
@@ -52,7 +57,9 @@ impl<'a> Renderer<'a> {
 
         let plan = if let Some(direction) = direction {
             if script.is_some() {
-                Some(ShapePlan::new(&shaper, direction, script, None, &[]))
+                Some(ShapePlan::new(
+                    &shaper, direction, script, language, features,
+                ))
             } else {
                 None
             }
@@ -66,14 +73,18 @@ impl<'a> Renderer<'a> {
             location,
         );
 
-        Self {
+        Ok(Self {
             shaper_data,
             font,
             plan,
             instance,
+            direction,
+            language: language.cloned(),
+            features: features.to_vec(),
             scale: font_size,
+            upem,
             outlines,
-        }
+        })
     }
 
     /// Render a string to a series of commands
@@ -81,7 +92,12 @@ impl<'a> Renderer<'a> {
     /// The commands can be used to render the string to an image. This routine also returns a
     /// serialized buffer that can be used both for debugging purposes and also to detect
     /// glyph sequences which have been rendered already (which helps to speed up the comparison).
-    pub fn string_to_positioned_glyphs(&mut self, string: &str) -> Option<(String, Vec<Command>)> {
+    /// Nothing is returned if the string shapes to no glyphs, and an error if
+    /// one of the glyphs can't be drawn.
+    pub fn string_to_positioned_glyphs(
+        &mut self,
+        string: &str,
+    ) -> Result<Option<(String, Vec<Command>)>, Error> {
         let mut pen = RecordingPen::default();
 
         let mut buffer = UnicodeBuffer::new();
@@ -101,14 +117,19 @@ impl<'a> Renderer<'a> {
             if let Some(lang) = plan.language() {
                 buffer.set_language(lang.clone());
             }
-            shaper.shape_with_plan(plan, buffer, &[])
+            shaper.shape_with_plan(plan, buffer, &self.features)
         } else {
-            // Otherwise, we guess segment properties
+            // Otherwise, we guess segment properties, overriding any we were given
             buffer.guess_segment_properties();
-            shaper.shape(buffer, &[])
+            if let Some(direction) = self.direction {
+                buffer.set_direction(direction);
+            }
+            if let Some(language) = &self.language {
+                buffer.set_language(language.clone());
+            }
+            shaper.shape(buffer, &self.features)
         };
-        let upem = self.font.head().unwrap().units_per_em();
-        let factor = self.scale / upem as f32;
+        let factor = self.scale / self.upem as f32;
 
         let mut cursor = 0.0;
 
@@ -121,7 +142,7 @@ impl<'a> Renderer<'a> {
         for (position, info) in positions.iter().zip(infos) {
             pen.offset_x = cursor + (position.x_offset as f32 * factor);
             pen.offset_y = position.y_offset as f32 * factor;
-            self.outlines.draw(GlyphId::new(info.glyph_id), &mut pen);
+            self.outlines.draw(GlyphId::new(info.glyph_id), &mut pen)?;
             serialized_buffer.push_str(&format!("{}", info.glyph_id,));
             if position.x_offset != 0 || position.y_offset != 0 {
                 serialized_buffer
@@ -131,9 +152,9 @@ impl<'a> Renderer<'a> {
             cursor += position.x_advance as f32 * factor;
         }
         if serialized_buffer.is_empty() {
-            return None;
+            return Ok(None);
         }
-        Some((serialized_buffer, pen.buffer))
+        Ok(Some((serialized_buffer, pen.buffer)))
     }
 
     /// Render a series of commands to an image
@@ -204,15 +225,20 @@ mod tests {
     fn test_zeno_path() {
         let path = "NotoSansArabic-NewRegular.ttf";
         let data = std::fs::read(path).unwrap();
-        let font = DFont::new(&data);
+        let font = DFont::new(&data).unwrap();
         let mut renderer = Renderer::new(
             &font,
             40.0,
             Some(Direction::RightToLeft),
             Some(script::ARABIC),
-        );
-        let (_serialized_buffer, commands) =
-            renderer.string_to_positioned_glyphs("السلام عليكم").unwrap();
+            None,
+            &[],
+        )
+        .unwrap();
+        let (_serialized_buffer, commands) = renderer
+            .string_to_positioned_glyphs("السلام عليكم")
+            .unwrap()
+            .unwrap();
         let image = renderer.render_positioned_glyphs(&commands);
         image.save("test.png").unwrap();
     }
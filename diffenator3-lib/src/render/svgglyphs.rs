@@ -0,0 +1,62 @@
+/// Glyphs drawn with SVG documents, from the `SVG ` table.
+///
+/// Each glyph is looked up in its SVG document by its `glyph<ID>` element,
+/// rendered with `resvg` and returned as a tile which
+/// [`ColorRenderer`](super::colorrenderer::ColorRenderer) composites like
+/// any other colour glyph.
+use resvg::usvg::{self, Node};
+use skrifa::{raw::TableProvider, FontRef, GlyphId};
+use tiny_skia::{Pixmap, Transform};
+
+use super::colorrenderer::CachedColorGlyph;
+
+/// Returns true if the font has an `SVG ` table.
+pub(crate) fn has_svg_glyphs(font: &FontRef) -> bool {
+    font.svg().is_ok()
+}
+
+/// Render the SVG document of a glyph at `font_size` pixels per em.
+///
+/// Returns `None` if the glyph has no SVG document, or it can't be parsed,
+/// so it can be drawn some other way.
+pub(super) fn render_svg_glyph(
+    font: &FontRef,
+    glyph_id: GlyphId,
+    font_size: f32,
+    upem: u16,
+) -> Option<CachedColorGlyph> {
+    let document = font.svg().ok()?.glyph_data(glyph_id).ok()??;
+    let tree = usvg::Tree::from_data(document, &usvg::Options::default())
+        .map_err(|e| log::debug!("Couldn't parse SVG for glyph {}: {}", glyph_id, e))
+        .ok()?;
+    let node = tree.node_by_id(&format!("glyph{}", glyph_id.to_u32()))?;
+
+    // SVG glyphs are drawn in font units, with the origin on the baseline
+    // and y increasing downwards
+    let factor = font_size / upem as f32;
+    let bbox = node.abs_layer_bounding_box()?;
+    let mut pixmap = Pixmap::new(
+        (bbox.width() * factor).ceil().max(1.0) as u32,
+        (bbox.height() * factor).ceil().max(1.0) as u32,
+    )?;
+
+    // resvg draws the node with its own transform, but not its parents', and
+    // moves it by its (absolute) bounding box first; so put the parents'
+    // transforms in, and take that move back out
+    let own = match node {
+        Node::Group(group) => group.transform(),
+        _ => Transform::identity(),
+    };
+    let parents = node.abs_transform().pre_concat(own.invert()?);
+    let transform = Transform::from_scale(factor, factor)
+        .pre_translate(-bbox.x(), -bbox.y())
+        .pre_concat(parents)
+        .pre_translate(bbox.x(), bbox.y());
+    resvg::render_node(node, transform, &mut pixmap.as_mut())?;
+
+    Some(CachedColorGlyph {
+        pixmap,
+        bearing_x: bbox.x() * factor,
+        bearing_y: -bbox.y() * factor,
+    })
+}
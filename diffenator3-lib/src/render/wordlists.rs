@@ -1,7 +1,545 @@
-use harfrust::{script, Direction, Script};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use google_fonts_languages::{LanguageProto, SampleTextProto, LANGUAGES};
+use harfrust::{script, Direction, Feature, Language, Script};
+use indexmap::IndexSet;
+use lazy_static::lazy_static;
 use static_lang_word_lists::WordList;
+use ucd::{Codepoint, UnicodeCategory};
+
+/// Maximum number of words in a generated word list
+const MAX_GENERATED_WORDS: usize = 5000;
+
+/// A word list built from the sample text of a single language
+///
+/// Unlike the per-script lists, these know which language they are written
+/// in, so they can be shaped with the right OpenType language system and
+/// exercise `locl` and other language-specific lookups.
+pub struct LanguageWordList {
+    /// The google-fonts-languages identifier, e.g. `ur_Arab`
+    pub id: String,
+    /// The script of the sample text
+    pub script: Option<Script>,
+    /// The language of the sample text, used to select the OpenType language system
+    pub language: Option<Language>,
+    /// The words of the sample text
+    pub wordlist: WordList,
+}
+
+lazy_static! {
+    static ref LANGUAGE_WORDLISTS: Vec<LanguageWordList> = LANGUAGES
+        .values()
+        .filter_map(|lang| {
+            let sample = lang.sample_text.as_ref().map(longest_sampletext)?;
+            let words: IndexSet<&str> = sample.split_whitespace().collect();
+            if words.is_empty() {
+                return None;
+            }
+            let id = lang.id().to_string();
+            Some(LanguageWordList {
+                script: Script::from_str(lang.script()).ok(),
+                language: Language::from_str(lang.language()).ok(),
+                wordlist: WordList::define(format!("{} ({})", lang.name(), id), words),
+                id,
+            })
+        })
+        .collect();
+    /// Languages, keyed on the ISO 15924 code of the script they are written in
+    static ref SCRIPT_LANGUAGES: HashMap<&'static str, Vec<&'static LanguageProto>> = {
+        let mut map: HashMap<&str, Vec<&LanguageProto>> = HashMap::new();
+        for lang in LANGUAGES.values() {
+            map.entry(lang.script()).or_default().push(lang);
+        }
+        map
+    };
+}
 
-pub(crate) fn get_wordlist(script: &str) -> Option<&WordList> {
+/// Return the longest sample text available for a language
+pub fn longest_sampletext(st: &SampleTextProto) -> &str {
+    if let Some(text) = &st.specimen_16 {
+        return text;
+    }
+    if let Some(text) = &st.specimen_21 {
+        return text;
+    }
+    if let Some(text) = &st.specimen_32 {
+        return text;
+    }
+    if let Some(text) = &st.specimen_36 {
+        return text;
+    }
+    if let Some(text) = &st.specimen_48 {
+        return text;
+    }
+    if let Some(text) = &st.tester {
+        return text;
+    }
+    ""
+}
+
+/// Return the per-language word lists which can be rendered with the given codepoints
+///
+/// A language is included if at least one word of its sample text is fully
+/// covered by the codepoints.
+pub(crate) fn get_language_wordlists(
+    codepoints: &HashSet<u32>,
+) -> impl Iterator<Item = &'static LanguageWordList> + '_ {
+    LANGUAGE_WORDLISTS.iter().filter(|lwl| {
+        lwl.wordlist
+            .iter()
+            .any(|word| word.chars().all(|c| codepoints.contains(&(c as u32))))
+    })
+}
+
+/// A word list generated for a script which has no static word list
+pub struct GeneratedWordList {
+    /// The script of the words, if known
+    pub script: Option<Script>,
+    /// The generated words
+    pub wordlist: WordList,
+}
+
+/// The ISO 15924 code of a Unicode script name, as used by
+/// [crate::dfont::DFont::supported_scripts]
+fn iso15924_code(script_name: &str) -> Option<&'static str> {
+    let code = match script_name {
+        "Adlam" => "Adlm",
+        "Ahom" => "Ahom",
+        "AnatolianHieroglyphs" => "Hluw",
+        "Arabic" => "Arab",
+        "Armenian" => "Armn",
+        "Avestan" => "Avst",
+        "Balinese" => "Bali",
+        "Bamum" => "Bamu",
+        "BassaVah" => "Bass",
+        "Batak" => "Batk",
+        "Bengali" => "Beng",
+        "Bhaiksuki" => "Bhks",
+        "Bopomofo" => "Bopo",
+        "Brahmi" => "Brah",
+        "Braille" => "Brai",
+        "Buginese" => "Bugi",
+        "Buhid" => "Buhd",
+        "CanadianAboriginal" => "Cans",
+        "Carian" => "Cari",
+        "CaucasianAlbanian" => "Aghb",
+        "Chakma" => "Cakm",
+        "Cham" => "Cham",
+        "Cherokee" => "Cher",
+        "Common" => "Zyyy",
+        "Coptic" => "Copt",
+        "Cuneiform" => "Xsux",
+        "Cypriot" => "Cprt",
+        "Cyrillic" => "Cyrl",
+        "Deseret" => "Dsrt",
+        "Devanagari" => "Deva",
+        "Duployan" => "Dupl",
+        "EgyptianHieroglyphs" => "Egyp",
+        "Elbasan" => "Elba",
+        "Ethiopic" => "Ethi",
+        "Georgian" => "Geor",
+        "Glagolitic" => "Glag",
+        "Gothic" => "Goth",
+        "Grantha" => "Gran",
+        "Greek" => "Grek",
+        "Gujarati" => "Gujr",
+        "Gurmukhi" => "Guru",
+        "Han" => "Hani",
+        "Hangul" => "Hang",
+        "Hanunoo" => "Hano",
+        "Hatran" => "Hatr",
+        "Hebrew" => "Hebr",
+        "Hiragana" => "Hira",
+        "ImperialAramaic" => "Armi",
+        "Inherited" => "Zinh",
+        "InscriptionalPahlavi" => "Phli",
+        "InscriptionalParthian" => "Prti",
+        "Javanese" => "Java",
+        "Kaithi" => "Kthi",
+        "Kannada" => "Knda",
+        "Katakana" => "Kana",
+        "KatakanaOrHiragana" => "Hrkt",
+        "KayahLi" => "Kali",
+        "Kharoshthi" => "Khar",
+        "Khmer" => "Khmr",
+        "Khojki" => "Khoj",
+        "Khudawadi" => "Sind",
+        "Lao" => "Laoo",
+        "Latin" => "Latn",
+        "Lepcha" => "Lepc",
+        "Limbu" => "Limb",
+        "LinearA" => "Lina",
+        "LinearB" => "Linb",
+        "Lisu" => "Lisu",
+        "Lycian" => "Lyci",
+        "Lydian" => "Lydi",
+        "Mahajani" => "Mahj",
+        "Malayalam" => "Mlym",
+        "Mandaic" => "Mand",
+        "Manichaean" => "Mani",
+        "Marchen" => "Marc",
+        "MeeteiMayek" => "Mtei",
+        "MendeKikakui" => "Mend",
+        "MeroiticCursive" => "Merc",
+        "MeroiticHieroglyphs" => "Mero",
+        "Miao" => "Plrd",
+        "Modi" => "Modi",
+        "Mongolian" => "Mong",
+        "Mro" => "Mroo",
+        "Multani" => "Mult",
+        "Myanmar" => "Mymr",
+        "Nabataean" => "Nbat",
+        "NewTaiLue" => "Talu",
+        "Newa" => "Newa",
+        "Nko" => "Nkoo",
+        "Ogham" => "Ogam",
+        "OlChiki" => "Olck",
+        "OldHungarian" => "Hung",
+        "OldItalic" => "Ital",
+        "OldNorthArabian" => "Narb",
+        "OldPermic" => "Perm",
+        "OldPersian" => "Xpeo",
+        "OldSouthArabian" => "Sarb",
+        "OldTurkic" => "Orkh",
+        "Oriya" => "Orya",
+        "Osage" => "Osge",
+        "Osmanya" => "Osma",
+        "PahawhHmong" => "Hmng",
+        "Palmyrene" => "Palm",
+        "PauCinHau" => "Pauc",
+        "PhagsPa" => "Phag",
+        "Phoenician" => "Phnx",
+        "PsalterPahlavi" => "Phlp",
+        "Rejang" => "Rjng",
+        "Runic" => "Runr",
+        "Samaritan" => "Samr",
+        "Saurashtra" => "Saur",
+        "Sharada" => "Shrd",
+        "Shavian" => "Shaw",
+        "Siddham" => "Sidd",
+        "SignWriting" => "Sgnw",
+        "Sinhala" => "Sinh",
+        "SoraSompeng" => "Sora",
+        "Sundanese" => "Sund",
+        "SylotiNagri" => "Sylo",
+        "Syriac" => "Syrc",
+        "Tagalog" => "Tglg",
+        "Tagbanwa" => "Tagb",
+        "TaiLe" => "Tale",
+        "TaiTham" => "Lana",
+        "TaiViet" => "Tavt",
+        "Takri" => "Takr",
+        "Tamil" => "Taml",
+        "Tangut" => "Tang",
+        "Telugu" => "Telu",
+        "Thaana" => "Thaa",
+        "Thai" => "Thai",
+        "Tibetan" => "Tibt",
+        "Tifinagh" => "Tfng",
+        "Tirhuta" => "Tirh",
+        "Ugaritic" => "Ugar",
+        "Vai" => "Vaii",
+        "WarangCiti" => "Wara",
+        "Yi" => "Yiii",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Split a space-separated exemplar character string into clusters,
+/// removing braces around multi-character clusters and dotted circles
+/// used as mark carriers.
+fn exemplar_clusters(exemplars: &str) -> Vec<String> {
+    exemplars
+        .split_whitespace()
+        .map(|cluster| {
+            cluster
+                .chars()
+                .filter(|&c| c != '{' && c != '}' && c != '\u{25CC}')
+                .collect::<String>()
+        })
+        .filter(|cluster| !cluster.is_empty())
+        .collect()
+}
+
+/// Combine every base with every mark, as well as each base on its own
+fn combine_bases_and_marks(bases: &[String], marks: &[String]) -> Vec<String> {
+    bases
+        .iter()
+        .flat_map(|base| {
+            std::iter::once(base.clone())
+                .chain(marks.iter().map(move |mark| format!("{base}{mark}")))
+        })
+        .take(MAX_GENERATED_WORDS)
+        .collect()
+}
+
+/// Scripts which are shared between writing systems rather than being one,
+/// so have no languages of their own to take words from
+const PSEUDO_SCRIPTS: [&str; 3] = ["Common", "Inherited", "Unknown"];
+
+/// Generate word lists for those of the given scripts which have no static word list
+///
+/// Pseudo-scripts such as `Common` are skipped; their characters are
+/// already covered by the glyph test.
+pub(crate) fn generate_wordlists<'a>(
+    scripts: impl IntoIterator<Item = &'a String>,
+    codepoints: &HashSet<u32>,
+) -> Vec<GeneratedWordList> {
+    scripts
+        .into_iter()
+        .filter(|script| !PSEUDO_SCRIPTS.contains(&script.as_str()))
+        .filter(|script| get_wordlist(script).is_none())
+        .filter_map(|script| generate_wordlist(script, codepoints))
+        .collect()
+}
+
+/// Generate a word list for a script which has no static word list
+///
+/// In order of preference, the words are taken from the sample texts of
+/// languages written in the script; from combinations of the base and mark
+/// exemplar characters of those languages; or from combinations of the
+/// letters and marks of the script which are encoded in both fonts.
+/// Only words which can be rendered with the given codepoints are kept.
+pub(crate) fn generate_wordlist(
+    script_name: &str,
+    codepoints: &HashSet<u32>,
+) -> Option<GeneratedWordList> {
+    let renderable = |word: &String| word.chars().all(|c| codepoints.contains(&(c as u32)));
+    let code = iso15924_code(script_name);
+    let languages: &[&LanguageProto] = code
+        .and_then(|code| SCRIPT_LANGUAGES.get(code))
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let script = code.and_then(|code| Script::from_str(code).ok());
+    let name = format!("generated_{}", script_name.to_lowercase());
+
+    // Sample texts
+    let words: IndexSet<String> = languages
+        .iter()
+        .filter_map(|lang| lang.sample_text.as_ref().map(longest_sampletext))
+        .flat_map(|sample| sample.split_whitespace().map(String::from))
+        .filter(renderable)
+        .take(MAX_GENERATED_WORDS)
+        .collect();
+    if !words.is_empty() {
+        return Some(GeneratedWordList {
+            script,
+            wordlist: WordList::define(name, words),
+        });
+    }
+
+    // Orthographies
+    let mut words: IndexSet<String> = IndexSet::new();
+    for lang in languages.iter() {
+        let Some(exemplars) = lang.exemplar_chars.as_ref() else {
+            continue;
+        };
+        let bases = exemplar_clusters(exemplars.base.as_deref().unwrap_or_default());
+        let marks = exemplar_clusters(exemplars.marks.as_deref().unwrap_or_default());
+        words.extend(
+            combine_bases_and_marks(&bases, &marks)
+                .into_iter()
+                .filter(renderable),
+        );
+    }
+    if !words.is_empty() {
+        return Some(GeneratedWordList {
+            script,
+            wordlist: WordList::define(name, words.into_iter().take(MAX_GENERATED_WORDS)),
+        });
+    }
+
+    // Base and mark characters of the script
+    let mut script_chars: Vec<char> = codepoints
+        .iter()
+        .filter_map(|&cp| char::from_u32(cp))
+        .filter(|c| c.script().map(|s| format!("{:?}", s)).as_deref() == Some(script_name))
+        .collect();
+    script_chars.sort();
+    let is_mark = |c: &char| {
+        matches!(
+            c.category(),
+            UnicodeCategory::NonspacingMark
+                | UnicodeCategory::SpacingMark
+                | UnicodeCategory::EnclosingMark
+        )
+    };
+    let bases: Vec<String> = script_chars
+        .iter()
+        .filter(|c| c.is_alphabetic() && !is_mark(c))
+        .map(|c| c.to_string())
+        .collect();
+    let marks: Vec<String> = script_chars
+        .iter()
+        .filter(|c| is_mark(c))
+        .map(|c| c.to_string())
+        .collect();
+    if bases.is_empty() {
+        return None;
+    }
+    Some(GeneratedWordList {
+        script,
+        wordlist: WordList::define(name, combine_bases_and_marks(&bases, &marks)),
+    })
+}
+
+/// A section of a custom word list, along with the shaping properties set by its directives
+pub struct CustomWordList {
+    /// The words in this section
+    pub wordlist: WordList,
+    /// The script to shape with, if set by a `#script:` directive
+    pub script: Option<Script>,
+    /// The language to shape with, if set by a `#lang:` directive
+    pub language: Option<Language>,
+    /// The direction to shape with, if set by a `#dir:` directive
+    pub direction: Option<Direction>,
+    /// The OpenType features to apply, as set by a `#features:` directive
+    pub features: Vec<Feature>,
+}
+
+impl From<WordList> for CustomWordList {
+    fn from(wordlist: WordList) -> Self {
+        CustomWordList {
+            wordlist,
+            script: None,
+            language: None,
+            direction: None,
+            features: vec![],
+        }
+    }
+}
+
+/// Parse a language given either as a BCP47 tag (`sr`) or as an
+/// OpenType language system tag (`SRB`)
+///
+/// OpenType tags are recognised by being in upper case.
+pub fn parse_language(language: &str) -> Result<Language, String> {
+    let language = language.trim();
+    let bcp47 = if language.chars().all(|c| c.is_ascii_uppercase() || c == ' ') {
+        format!("x-hbot{}", language.trim_end())
+    } else {
+        language.to_string()
+    };
+    Language::from_str(&bcp47).map_err(|e| format!("Couldn't parse language '{}': {}", language, e))
+}
+
+/// A human-readable representation of a language, undoing [parse_language]
+pub fn language_label(language: &Language) -> String {
+    match language.as_str().strip_prefix("x-hbot") {
+        Some(ot_tag) => ot_tag.to_uppercase(),
+        None => language.as_str().to_string(),
+    }
+}
+
+/// A human-readable representation of a list of features, e.g. `+smcp,-liga`
+pub fn features_label(features: &[Feature]) -> String {
+    features
+        .iter()
+        .map(|f| match f.value {
+            0 => format!("-{}", f.tag),
+            1 => format!("+{}", f.tag),
+            v => format!("{}={}", f.tag, v),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Parse a custom word list
+///
+/// Each line is a word (or phrase) to test. Lines starting with `#` are
+/// comments, except for the following directives, which set the shaping
+/// properties of all following lines until changed:
+///
+/// * `#features: +smcp,-liga` - OpenType features to apply
+/// * `#lang: SRB` - language, as an OpenType tag or a BCP47 tag
+/// * `#script: Cyrl` - script, as an ISO 15924 tag
+/// * `#dir: rtl` - text direction
+///
+/// A directive with an empty value resets that property. The file is split
+/// into one section per run of lines with the same properties.
+pub fn parse_custom_wordlist(name: &str, data: &str) -> Result<Vec<CustomWordList>, String> {
+    #[derive(Clone, Default, PartialEq)]
+    struct Directives {
+        script: Option<Script>,
+        language: Option<Language>,
+        direction: Option<Direction>,
+        features: Vec<Feature>,
+    }
+    let mut directives = Directives::default();
+    let mut sections: Vec<(Directives, Vec<String>)> = vec![];
+
+    for (lineno, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix('#') else {
+            match sections.last_mut() {
+                Some((section_directives, words)) if *section_directives == directives => {
+                    words.push(line.to_string())
+                }
+                _ => sections.push((directives.clone(), vec![line.to_string()])),
+            }
+            continue;
+        };
+        let Some((key, value)) = comment.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let error = |e: &str| format!("line {}: {}", lineno + 1, e);
+        match key.trim() {
+            "features" => {
+                directives.features = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .map(|f| {
+                        Feature::from_str(f)
+                            .map_err(|_| error(&format!("couldn't parse feature '{}'", f)))
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "lang" | "language" => {
+                directives.language = if value.is_empty() {
+                    None
+                } else {
+                    Some(parse_language(value).map_err(|e| error(&e))?)
+                }
+            }
+            "script" => {
+                directives.script = if value.is_empty() {
+                    None
+                } else {
+                    Some(Script::from_str(value).map_err(error)?)
+                }
+            }
+            "dir" | "direction" => {
+                directives.direction = if value.is_empty() {
+                    None
+                } else {
+                    Some(Direction::from_str(value).map_err(error)?)
+                }
+            }
+            // Any other comment
+            _ => {}
+        }
+    }
+    Ok(sections
+        .into_iter()
+        .map(|(directives, words)| CustomWordList {
+            wordlist: WordList::define(name, words),
+            script: directives.script,
+            language: directives.language,
+            direction: directives.direction,
+            features: directives.features,
+        })
+        .collect())
+}
+
+pub(crate) fn get_wordlist(script: &str) -> Option<&'static WordList> {
     let wl = match script {
         "Adlam" => &static_lang_word_lists::DIFFENATOR_ADLAM,
         "Arabic" => &static_lang_word_lists::DIFFENATOR_ARABIC,
@@ -138,3 +676,89 @@ pub fn direction_from_script(script: Script) -> Option<Direction> {
             _ => Some(Direction::LeftToRight),
         }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exemplar_clusters() {
+        assert_eq!(
+            exemplar_clusters("a b {ch} \u{25CC}\u{0301}"),
+            vec!["a", "b", "ch", "\u{0301}"]
+        );
+    }
+
+    #[test]
+    fn test_combine_bases_and_marks() {
+        let bases = vec!["a".to_string(), "b".to_string()];
+        let marks = vec!["\u{0301}".to_string()];
+        assert_eq!(
+            combine_bases_and_marks(&bases, &marks),
+            vec!["a", "a\u{0301}", "b", "b\u{0301}"]
+        );
+    }
+
+    #[test]
+    fn test_generate_wordlist_needs_bases() {
+        // Combining marks alone can't make a word
+        let codepoints: HashSet<u32> = (0x0300..0x0310).collect();
+        assert!(generate_wordlist("Inherited", &codepoints).is_none());
+    }
+
+    #[test]
+    fn test_generate_wordlist_from_languages() {
+        assert_eq!(iso15924_code("OlChiki"), Some("Olck"));
+        assert_eq!(iso15924_code("Klingon"), None);
+        let codepoints: HashSet<u32> = ('a'..='z').chain(['A', ' ']).map(|c| c as u32).collect();
+        let generated = generate_wordlist("Latin", &codepoints).unwrap();
+        assert_eq!(generated.script, Some(script::LATIN));
+        assert!(!generated.wordlist.is_empty());
+    }
+
+    #[test]
+    fn test_generate_wordlists_skips_common() {
+        let codepoints: HashSet<u32> = ('a'..='z')
+            .chain(['µ', 'ˆ', 'ˇ', ' '])
+            .map(|c| c as u32)
+            .collect();
+        let scripts: HashSet<String> = codepoints
+            .iter()
+            .filter_map(|&cp| char::from_u32(cp).and_then(|c| c.script()))
+            .map(|s| format!("{:?}", s))
+            .collect();
+        assert!(scripts.contains("Common"));
+        // Left to itself, the generator would make a list of the Common letters
+        assert!(generate_wordlist("Common", &codepoints)
+            .is_some_and(|g| g.wordlist.name() == "generated_common"));
+        let generated = generate_wordlists(&scripts, &codepoints);
+        assert!(!generated
+            .iter()
+            .any(|g| g.wordlist.name() == "generated_common"));
+    }
+
+    #[test]
+    fn test_parse_custom_wordlist() {
+        let data = "hello\n# a comment\n#features: +smcp,-liga\n#lang: SRB\nworld\nagain\n\n#features:\nbye\n";
+        let sections = parse_custom_wordlist("custom", data).unwrap();
+        assert_eq!(sections.len(), 3);
+        assert_eq!(
+            sections[0].wordlist.iter().collect::<Vec<_>>(),
+            vec!["hello"]
+        );
+        assert!(sections[0].features.is_empty());
+        assert!(sections[0].language.is_none());
+
+        assert_eq!(sections[1].wordlist.len(), 2);
+        assert_eq!(features_label(&sections[1].features), "+smcp,-liga");
+        assert_eq!(
+            sections[1].language.as_ref().map(language_label).as_deref(),
+            Some("SRB")
+        );
+
+        assert!(sections[2].features.is_empty());
+        assert!(sections[2].language.is_some());
+
+        assert!(parse_custom_wordlist("custom", "#dir: sideways").is_err());
+    }
+}
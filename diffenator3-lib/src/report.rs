@@ -0,0 +1,443 @@
+//! The results of comparing two fonts
+//!
+//! A [Report] is what a [crate::comparison::Comparison] produces; it is
+//! serialized to JSON by the command line tool's `--json` option, and can
+//! be read back with [Report::load].
+use crate::{
+    languages::LanguageDiff,
+    structs::{
+        CmapDiff, Difference, GlyphDiff, GlyphDossier, GroupedDifference, GroupedDifferences,
+        InstanceChange, InterpolationCheck, MetricDiff, PaletteChange,
+    },
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+#[derive(Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+#[serde(default)]
+pub struct LocationResult {
+    /// Name of the location in designspace (named instance, or stringified coordinates)
+    pub location: String,
+    /// Coordinates of the location in designspace
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub coords: HashMap<String, f32>,
+    /// The instance of the first font compared, if the named instance
+    /// was matched with a differently named one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_a: Option<String>,
+    /// The instance of the second font compared, likewise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_b: Option<String>,
+    /// An error message, if something went wrong
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Problems rendering some of the glyphs or words; the rest were still compared
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Differences between glyphs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub glyphs: Vec<GlyphDiff>,
+    /// Differences between words
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub words: BTreeMap<String, Vec<Difference>>,
+    /// Differences between font-wide or glyph metrics
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<MetricDiff>,
+}
+
+impl LocationResult {
+    pub fn is_some(&self) -> bool {
+        self.error.is_some()
+            || !self.errors.is_empty()
+            || !self.glyphs.is_empty()
+            || !self.words.is_empty()
+            || !self.metrics.is_empty()
+    }
+
+    pub fn from_error(location: String, error: String) -> Self {
+        LocationResult {
+            location,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Version of the [Report] format; bumped when it changes incompatibly
+pub const REPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+#[serde(default)]
+pub struct Report {
+    /// Version of the report format, [REPORT_VERSION] when written by this diffenator3
+    #[serde(default)]
+    pub version: u32,
+    /// Path to the first font, for a comparison of two fonts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_a: Option<String>,
+    /// Path to the second font, for a comparison of two fonts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_b: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kerns: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cmap_diff: Option<CmapDiff>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<LocationResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<BTreeMap<String, LanguageDiff>>,
+    /// Named instances which were added, removed, renamed or moved
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<InstanceChange>,
+    /// CPAL palette entries whose colors changed, when glyphs or words are compared
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub palettes: Vec<PaletteChange>,
+    /// Glyphs which kink between masters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpolation: Option<InterpolationCheck>,
+    /// The glyph and word differences of all locations, merged; only
+    /// filled in by [Report::group]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grouped: Option<GroupedDifferences>,
+    /// Every change to each glyph; only filled in by
+    /// [crate::dossier::glyph_dossiers]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dossiers: Vec<GlyphDossier>,
+    /// Tests which could not be run at all
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Number of known differences left out of the report, by ignore rule
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub suppressed: BTreeMap<String, usize>,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report {
+            version: REPORT_VERSION,
+            font_a: None,
+            font_b: None,
+            tables: None,
+            kerns: None,
+            cmap_diff: None,
+            locations: vec![],
+            languages: None,
+            instances: vec![],
+            palettes: vec![],
+            interpolation: None,
+            grouped: None,
+            dossiers: vec![],
+            errors: vec![],
+            suppressed: BTreeMap::new(),
+        }
+    }
+}
+
+impl Report {
+    /// Write the report to a JSON file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Couldn't serialize report: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+    }
+
+    /// Read a report written by [Report::save], or by `diffenator3 --json`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let report: Report = serde_json::from_str(&contents)
+            .map_err(|e| format!("Couldn't parse {}: {}", path.display(), e))?;
+        if report.version != REPORT_VERSION {
+            return Err(format!(
+                "{} is a version {} report, but this version of diffenator3 reads version {}",
+                path.display(),
+                report.version,
+                REPORT_VERSION
+            ));
+        }
+        Ok(report)
+    }
+
+    /// Merge the glyph and word differences which appear at several
+    /// locations, into [Report::grouped]
+    ///
+    /// Differences are merged if they are of the same glyph, or of the same
+    /// word from the same script's list with the same features and
+    /// language, drawn with the same palette. Each lists the locations
+    /// where it appears and the range of differing pixels. The most
+    /// different come first; words are also kept together by script.
+    pub fn group(&mut self) {
+        let mut glyphs: IndexMap<(&str, Option<u16>), GroupedDifference> = IndexMap::new();
+        let mut words: IndexMap<(&str, &str, &str, &str, Option<u16>), GroupedDifference> =
+            IndexMap::new();
+        for location in self.locations.iter() {
+            for glyph in location.glyphs.iter() {
+                glyphs
+                    .entry((&glyph.string, glyph.palette))
+                    .or_insert_with(|| GroupedDifference {
+                        string: glyph.string.clone(),
+                        name: format!("{} {}", glyph.unicode, glyph.name)
+                            .trim_end()
+                            .to_string(),
+                        palette: glyph.palette,
+                        ..Default::default()
+                    })
+                    .add(&location.location, glyph.differing_pixels);
+            }
+            for (script, differences) in location.words.iter() {
+                for difference in differences.iter() {
+                    words
+                        .entry((
+                            script,
+                            &difference.word,
+                            &difference.ot_features,
+                            &difference.lang,
+                            difference.palette,
+                        ))
+                        .or_insert_with(|| GroupedDifference {
+                            string: difference.word.clone(),
+                            script: script.clone(),
+                            ot_features: difference.ot_features.clone(),
+                            lang: difference.lang.clone(),
+                            palette: difference.palette,
+                            ..Default::default()
+                        })
+                        .add(&location.location, difference.differing_pixels);
+                }
+            }
+        }
+        let mut glyphs: Vec<GroupedDifference> = glyphs.into_values().collect();
+        glyphs.sort_by_key(|glyph| std::cmp::Reverse(glyph.max_pixels));
+        let mut words: Vec<GroupedDifference> = words.into_values().collect();
+        words.sort_by(|a, b| {
+            a.script
+                .cmp(&b.script)
+                .then(b.max_pixels.cmp(&a.max_pixels))
+        });
+        self.grouped = Some(GroupedDifferences { glyphs, words });
+    }
+
+    /// Count the differences of each kind, for summarizing a batch
+    pub fn summary(&self) -> ReportSummary {
+        let object_len = |v: &Option<serde_json::Value>| {
+            v.as_ref()
+                .and_then(|v| v.as_object())
+                .map_or(0, |o| o.len())
+        };
+        ReportSummary {
+            tables: object_len(&self.tables),
+            kerns: object_len(&self.kerns),
+            missing_glyphs: self.cmap_diff.as_ref().map_or(0, |c| c.missing.len()),
+            new_glyphs: self.cmap_diff.as_ref().map_or(0, |c| c.new.len()),
+            glyphs: self.locations.iter().map(|l| l.glyphs.len()).sum(),
+            words: self
+                .locations
+                .iter()
+                .flat_map(|l| l.words.values())
+                .map(|w| w.len())
+                .sum(),
+            metrics: self.locations.iter().map(|l| l.metrics.len()).sum(),
+            languages: self.languages.as_ref().map_or(0, |langs| {
+                langs.values().filter(|l| l.level_a != l.level_b).count()
+            }),
+            instances: self.instances.len(),
+            palettes: self.palettes.len(),
+            interpolation: self.interpolation.as_ref().map_or(0, |i| {
+                i.font_b.iter().filter(|kink| kink.introduced).count()
+            }),
+            errors: self.locations.iter().filter(|l| l.error.is_some()).count(),
+        }
+    }
+}
+
+impl GroupedDifference {
+    fn add(&mut self, location: &str, pixels: usize) {
+        if self.locations.is_empty() {
+            self.min_pixels = pixels;
+        }
+        self.locations.push(location.to_string());
+        self.min_pixels = self.min_pixels.min(pixels);
+        self.max_pixels = self.max_pixels.max(pixels);
+    }
+}
+
+impl std::fmt::Display for GroupedDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.string)?;
+        if !self.name.is_empty() {
+            write!(f, " ({})", self.name)?;
+        }
+        if !self.ot_features.is_empty() {
+            write!(f, " with {}", self.ot_features)?;
+        }
+        if !self.lang.is_empty() {
+            write!(f, " in {}", self.lang)?;
+        }
+        if let Some(palette) = self.palette {
+            write!(f, " in palette {}", palette)?;
+        }
+        if self.min_pixels == self.max_pixels {
+            write!(f, " differs by {} pixels", self.max_pixels)?;
+        } else {
+            write!(
+                f,
+                " differs by {}-{} pixels",
+                self.min_pixels, self.max_pixels
+            )?;
+        }
+        write!(f, " at {}", self.locations.join(", "))
+    }
+}
+
+/// Number of differences of each kind found when comparing two fonts
+#[derive(Serialize, Default)]
+pub struct ReportSummary {
+    /// Tables with differences
+    pub tables: usize,
+    /// Kerning pairs with differences
+    pub kerns: usize,
+    /// Encoded glyphs removed
+    pub missing_glyphs: usize,
+    /// Encoded glyphs added
+    pub new_glyphs: usize,
+    /// Glyph image differences, across all locations
+    pub glyphs: usize,
+    /// Word image differences, across all locations
+    pub words: usize,
+    /// Metric differences, across all locations
+    pub metrics: usize,
+    /// Languages whose support level changed
+    pub languages: usize,
+    /// Named instances which were added, removed, renamed or moved
+    pub instances: usize,
+    /// CPAL palette entries whose colors changed, when glyphs or words are compared
+    pub palettes: usize,
+    /// Glyphs which kink between masters in the second font but not the first
+    pub interpolation: usize,
+    /// Locations which could not be tested
+    pub errors: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::{Difference, GlyphDiff};
+
+    fn glyph(string: &str, differing_pixels: usize, palette: Option<u16>) -> GlyphDiff {
+        GlyphDiff {
+            string: string.to_string(),
+            name: "LATIN SMALL LETTER".to_string(),
+            unicode: "U+0000".to_string(),
+            differing_pixels,
+            palette,
+        }
+    }
+
+    fn word(word: &str, differing_pixels: usize, ot_features: &str) -> Difference {
+        Difference {
+            word: word.to_string(),
+            buffer_a: String::new(),
+            buffer_b: None,
+            differing_pixels,
+            ot_features: ot_features.to_string(),
+            lang: String::new(),
+            palette: None,
+        }
+    }
+
+    fn location(
+        name: &str,
+        glyphs: Vec<GlyphDiff>,
+        words: Vec<(&str, Vec<Difference>)>,
+    ) -> LocationResult {
+        LocationResult {
+            location: name.to_string(),
+            glyphs,
+            words: words
+                .into_iter()
+                .map(|(script, words)| (script.to_string(), words))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn grouped_report() -> GroupedDifferences {
+        let mut report = Report {
+            locations: vec![
+                location(
+                    "wght=100",
+                    vec![glyph("a", 30, None), glyph("b", 5, None)],
+                    vec![("Latin", vec![word("abc", 20, ""), word("abc", 8, "+smcp")])],
+                ),
+                location(
+                    "wght=900",
+                    vec![glyph("a", 10, None), glyph("a", 50, Some(2))],
+                    vec![
+                        ("Latin", vec![word("abc", 40, "")]),
+                        ("Arabic", vec![word("سلام", 12, "")]),
+                    ],
+                ),
+            ],
+            ..Default::default()
+        };
+        report.group();
+        report.grouped.unwrap()
+    }
+
+    #[test]
+    fn group_merges_across_locations() {
+        let grouped = grouped_report();
+        let a = grouped
+            .glyphs
+            .iter()
+            .find(|g| g.string == "a" && g.palette.is_none())
+            .unwrap();
+        assert_eq!(a.locations, vec!["wght=100", "wght=900"]);
+        assert_eq!((a.min_pixels, a.max_pixels), (10, 30));
+        let b = grouped.glyphs.iter().find(|g| g.string == "b").unwrap();
+        assert_eq!(b.locations, vec!["wght=100"]);
+        assert_eq!((b.min_pixels, b.max_pixels), (5, 5));
+        assert_eq!(b.name, "U+0000 LATIN SMALL LETTER");
+    }
+
+    #[test]
+    fn group_keeps_palettes_and_features_apart() {
+        let grouped = grouped_report();
+        assert_eq!(grouped.glyphs.len(), 3);
+        let palette = grouped
+            .glyphs
+            .iter()
+            .find(|g| g.palette == Some(2))
+            .unwrap();
+        assert_eq!(palette.locations, vec!["wght=900"]);
+        let abc: Vec<(&str, usize, usize)> = grouped
+            .words
+            .iter()
+            .filter(|w| w.string == "abc")
+            .map(|w| (w.ot_features.as_str(), w.min_pixels, w.max_pixels))
+            .collect();
+        assert_eq!(abc, vec![("", 20, 40), ("+smcp", 8, 8)]);
+    }
+
+    #[test]
+    fn group_puts_the_most_different_first() {
+        let grouped = grouped_report();
+        let glyphs: Vec<usize> = grouped.glyphs.iter().map(|g| g.max_pixels).collect();
+        assert_eq!(glyphs, vec![50, 30, 5]);
+        // Words stay together by script
+        let words: Vec<(&str, usize)> = grouped
+            .words
+            .iter()
+            .map(|w| (w.script.as_str(), w.max_pixels))
+            .collect();
+        assert_eq!(words, vec![("Arabic", 12), ("Latin", 40), ("Latin", 8)]);
+    }
+}
@@ -0,0 +1,422 @@
+//! Adaptive sampling of the designspace
+//!
+//! Testing a fixed grid of locations grows exponentially with the number of
+//! axes, and can still miss a bad region between the grid points. Instead,
+//! [sample] starts from the default location, the masters and named
+//! instances of both fonts and the ends of each axis, and measures how far
+//! apart the two fonts' outlines are at each one. It then repeatedly bisects
+//! the span between two sampled points where the outlines differ the most, so
+//! that further samples land where the fonts diverge. Comparing outlines is
+//! much cheaper than rendering, so several locations are probed for each one
+//! which is finally tested.
+use crate::{dfont::DFont, setting::AxisMap};
+use skrifa::{
+    instance::{Location, Size},
+    outline::{DrawSettings, OutlinePen},
+    setting::VariationSetting,
+    MetadataProvider, OutlineGlyph, Tag,
+};
+use std::collections::BTreeSet;
+
+/// The extents of an axis in user coordinates, as (min, default, max)
+pub(crate) type AxisExtents = (Tag, (f32, f32, f32));
+
+/// How many locations are probed, beyond the starting points, for each
+/// location finally tested
+const PROBES_PER_LOCATION: usize = 4;
+/// Spans shorter than this, in normalized coordinates, are not bisected
+const MIN_SPAN: f32 = 0.05;
+/// Locations chosen for testing are kept at least this far apart, in
+/// normalized coordinates, while there are others to choose from
+const MIN_SPACING: f32 = 0.25;
+/// Outlines are compared for at most this many encoded glyphs, spread
+/// across the range of shared codepoints
+const MAX_GLYPHS: usize = 500;
+
+/// Choose up to `budget` locations where the fonts' outlines differ the most
+///
+/// The locations are returned with a value for every axis in `axes`,
+/// roughly worst first.
+pub(crate) fn sample(
+    font_a: &DFont,
+    font_b: &DFont,
+    axes: &[AxisExtents],
+    budget: usize,
+    axis_map: &AxisMap,
+) -> Vec<Vec<VariationSetting>> {
+    let sampler = Sampler::new(font_a, font_b, axes, axis_map);
+    let mut probes: Vec<Probe> = vec![];
+    for coords in seeds(font_a, font_b, axes) {
+        if !probes
+            .iter()
+            .any(|p| distance(&p.coords, &coords) < MIN_SPAN / 2.0)
+        {
+            probes.push(sampler.probe(coords));
+        }
+    }
+
+    let mut spans = initial_spans(&probes);
+    let max_probes = probes.len() + budget * PROBES_PER_LOCATION;
+    while probes.len() < max_probes {
+        // Bisect the span where the outlines differ the most, giving
+        // longer spans the benefit of the doubt
+        let Some((index, _)) = spans
+            .iter()
+            .enumerate()
+            .map(|(index, &(a, b))| {
+                let length = distance(&probes[a].coords, &probes[b].coords);
+                let score = probes[a].score.max(probes[b].score);
+                (index, (score * length, length))
+            })
+            .filter(|(_, (_, length))| *length >= MIN_SPAN)
+            .max_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal))
+        else {
+            break;
+        };
+        let (a, b) = spans.swap_remove(index);
+        let midpoint = probes[a]
+            .coords
+            .iter()
+            .zip(probes[b].coords.iter())
+            .map(|(x, y)| (x + y) / 2.0)
+            .collect();
+        probes.push(sampler.probe(midpoint));
+        let m = probes.len() - 1;
+        spans.push((a, m));
+        spans.push((m, b));
+    }
+
+    // Stable, so that the seeds come first where scores are equal
+    probes.sort_by(|x, y| {
+        y.score
+            .partial_cmp(&x.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    // Prefer locations which aren't close to one already chosen, then
+    // fill up the budget with the rest
+    let mut chosen: Vec<Probe> = vec![];
+    let mut rest = vec![];
+    for probe in probes {
+        if chosen.len() < budget
+            && chosen
+                .iter()
+                .all(|c| distance(&c.coords, &probe.coords) >= MIN_SPACING)
+        {
+            chosen.push(probe);
+        } else {
+            rest.push(probe);
+        }
+    }
+    let spare = budget.saturating_sub(chosen.len());
+    chosen.extend(rest.into_iter().take(spare));
+    chosen
+        .into_iter()
+        .map(|probe| denormalize(&probe.coords, axes))
+        .collect()
+}
+
+/// A sampled location, in normalized coordinates, and how different the
+/// outlines are there
+struct Probe {
+    coords: Vec<f32>,
+    score: f32,
+}
+
+/// Compares the outlines of glyphs encoded in both fonts
+struct Sampler<'a> {
+    font_a: &'a DFont,
+    font_b: &'a DFont,
+    axes: &'a [AxisExtents],
+    axis_map: &'a AxisMap,
+    glyphs: Vec<(OutlineGlyph<'a>, OutlineGlyph<'a>)>,
+}
+
+impl<'a> Sampler<'a> {
+    fn new(
+        font_a: &'a DFont,
+        font_b: &'a DFont,
+        axes: &'a [AxisExtents],
+        axis_map: &'a AxisMap,
+    ) -> Self {
+        let codepoints: BTreeSet<u32> = font_a
+            .codepoints
+            .intersection(&font_b.codepoints)
+            .copied()
+            .collect();
+        let step = codepoints.len().div_ceil(MAX_GLYPHS).max(1);
+        let (charmap_a, charmap_b) = (font_a.fontref().charmap(), font_b.fontref().charmap());
+        let (outlines_a, outlines_b) = (
+            font_a.fontref().outline_glyphs(),
+            font_b.fontref().outline_glyphs(),
+        );
+        let glyphs = codepoints
+            .into_iter()
+            .step_by(step)
+            .filter_map(|cp| {
+                let glyph_a = outlines_a.get(charmap_a.map(cp)?)?;
+                let glyph_b = outlines_b.get(charmap_b.map(cp)?)?;
+                Some((glyph_a, glyph_b))
+            })
+            .collect();
+        Sampler {
+            font_a,
+            font_b,
+            axes,
+            axis_map,
+            glyphs,
+        }
+    }
+
+    /// Measure the outline differences at a location
+    ///
+    /// The score is the mean distance, in font units, by which the points
+    /// of each glyph have moved. Glyphs whose outlines have a different
+    /// structure in each font can't be compared point by point, and so are
+    /// left out.
+    fn probe(&self, coords: Vec<f32>) -> Probe {
+        let location = denormalize(&coords, self.axes);
+        let location_a = self.font_a.fontref().axes().location(&location);
+        let mut location_b = self.axis_map.translate(&location);
+        for pin in self.axis_map.pins.iter() {
+            if !location_b.iter().any(|v| v.selector == pin.selector) {
+                location_b.push(*pin);
+            }
+        }
+        let location_b = self.font_b.fontref().axes().location(&location_b);
+        let mut total = 0.0;
+        let mut compared = 0;
+        for (glyph_a, glyph_b) in self.glyphs.iter() {
+            let (Some(points_a), Some(points_b)) =
+                (points(glyph_a, &location_a), points(glyph_b, &location_b))
+            else {
+                continue;
+            };
+            if points_a.is_empty() || points_a.len() != points_b.len() {
+                continue;
+            }
+            let moved: f32 = points_a
+                .iter()
+                .zip(points_b.iter())
+                .map(|(a, b)| (a - b).abs())
+                .sum();
+            total += moved / points_a.len() as f32;
+            compared += 1;
+        }
+        let score = if compared > 0 {
+            total / compared as f32
+        } else {
+            0.0
+        };
+        Probe { coords, score }
+    }
+}
+
+/// Collects the coordinates of every point in an outline
+#[derive(Default)]
+pub(crate) struct PointPen(Vec<f32>);
+
+impl OutlinePen for PointPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.extend([x, y]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.extend([x, y]);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0.extend([cx0, cy0, x, y]);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.extend([cx0, cy0, cx1, cy1, x, y]);
+    }
+
+    fn close(&mut self) {}
+}
+
+/// The coordinates of every point in an outline, as x, y pairs in one list
+pub(crate) fn points(glyph: &OutlineGlyph, location: &Location) -> Option<Vec<f32>> {
+    let mut pen = PointPen::default();
+    glyph
+        .draw(DrawSettings::unhinted(Size::unscaled(), location), &mut pen)
+        .ok()?;
+    Some(pen.0)
+}
+
+/// The default location, the ends of each axis, and the masters and named
+/// instances of both fonts, in normalized coordinates
+fn seeds(font_a: &DFont, font_b: &DFont, axes: &[AxisExtents]) -> Vec<Vec<f32>> {
+    let default = vec![0.0; axes.len()];
+    let mut seeds = vec![default.clone()];
+    for index in 0..axes.len() {
+        for end in [-1.0, 1.0] {
+            let mut coords = default.clone();
+            coords[index] = end;
+            seeds.push(coords);
+        }
+    }
+    for font in [font_a, font_b] {
+        for master in font.masters().unwrap_or_default() {
+            seeds.push(normalize(&master, axes));
+        }
+        for (_name, location) in font.instance_locations() {
+            seeds.push(normalize(&location, axes));
+        }
+    }
+    seeds
+}
+
+/// Spans from each seed to the default location, and to its nearest other seed
+fn initial_spans(probes: &[Probe]) -> Vec<(usize, usize)> {
+    let mut spans = BTreeSet::new();
+    for (index, probe) in probes.iter().enumerate().skip(1) {
+        spans.insert((0, index));
+        let nearest = probes
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(other, _)| *other != index)
+            .map(|(other, p)| (other, distance(&probe.coords, &p.coords)))
+            .min_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some((other, _)) = nearest {
+            spans.insert((index.min(other), index.max(other)));
+        }
+    }
+    spans.into_iter().collect()
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Map a user-space location onto -1..1 for each axis, with 0 at the
+/// default; axes which aren't given stay at the default
+fn normalize(location: &[VariationSetting], axes: &[AxisExtents]) -> Vec<f32> {
+    axes.iter()
+        .map(|(tag, (min, default, max))| {
+            let Some(value) = location
+                .iter()
+                .find(|v| v.selector == *tag)
+                .map(|v| v.value)
+            else {
+                return 0.0;
+            };
+            let normalized = if value < *default && default > min {
+                (value - default) / (default - min)
+            } else if value > *default && max > default {
+                (value - default) / (max - default)
+            } else {
+                0.0
+            };
+            normalized.clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+/// Map normalized coordinates back to user space, rounded to two decimal places
+fn denormalize(coords: &[f32], axes: &[AxisExtents]) -> Vec<VariationSetting> {
+    axes.iter()
+        .zip(coords.iter())
+        .map(|((tag, (min, default, max)), coord)| {
+            let value = if *coord < 0.0 {
+                default + coord * (default - min)
+            } else {
+                default + coord * (max - default)
+            };
+            VariationSetting::new(*tag, (value * 100.0).round() / 100.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::nabla;
+
+    fn axes() -> Vec<AxisExtents> {
+        vec![
+            (Tag::new(b"wght"), (100.0, 400.0, 900.0)),
+            (Tag::new(b"wdth"), (75.0, 100.0, 100.0)),
+        ]
+    }
+
+    fn probe(coords: &[f32]) -> Probe {
+        Probe {
+            coords: coords.to_vec(),
+            score: 0.0,
+        }
+    }
+
+    #[test]
+    fn normalize_round_trips() {
+        let location = vec![
+            VariationSetting::new(Tag::new(b"wght"), 250.0),
+            VariationSetting::new(Tag::new(b"wdth"), 87.5),
+        ];
+        let coords = normalize(&location, &axes());
+        assert_eq!(coords, vec![-0.5, -0.5]);
+        assert_eq!(denormalize(&coords, &axes()), location);
+
+        let location = vec![
+            VariationSetting::new(Tag::new(b"wght"), 650.0),
+            VariationSetting::new(Tag::new(b"wdth"), 100.0),
+        ];
+        let coords = normalize(&location, &axes());
+        assert_eq!(coords, vec![0.5, 0.0]);
+        assert_eq!(denormalize(&coords, &axes()), location);
+    }
+
+    #[test]
+    fn normalize_clamps_and_defaults_missing_axes() {
+        let location = vec![VariationSetting::new(Tag::new(b"wght"), 1000.0)];
+        assert_eq!(normalize(&location, &axes()), vec![1.0, 0.0]);
+        // An axis which can't go above its default stays there
+        let location = vec![VariationSetting::new(Tag::new(b"wdth"), 125.0)];
+        assert_eq!(normalize(&location, &axes()), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn denormalize_rounds() {
+        let location = denormalize(&[1.0 / 3.0, -1.0 / 3.0], &axes());
+        assert_eq!(location[0].value, 566.67);
+        assert_eq!(location[1].value, 91.67);
+    }
+
+    #[test]
+    fn spans_reach_the_default_and_the_nearest_seed() {
+        let probes = [
+            probe(&[0.0, 0.0]),
+            probe(&[1.0, 0.0]),
+            probe(&[-1.0, 0.0]),
+            probe(&[0.9, 0.0]),
+        ];
+        assert_eq!(
+            initial_spans(&probes),
+            vec![(0, 1), (0, 2), (0, 3), (1, 3), (2, 3)]
+        );
+        assert!(initial_spans(&probes[..1]).is_empty());
+    }
+
+    #[test]
+    fn sample_stays_within_budget() {
+        let data = nabla();
+        let font = DFont::new(&data).unwrap();
+        let axes = vec![
+            (Tag::new(b"EDPT"), (0.0, 100.0, 200.0)),
+            (Tag::new(b"EHLT"), (0.0, 12.0, 24.0)),
+        ];
+        for budget in [1, 3, 20] {
+            let locations = sample(&font, &font, &axes, budget, &AxisMap::default());
+            assert_eq!(locations.len(), budget);
+            for location in locations {
+                let tags: Vec<Tag> = location.iter().map(|v| v.selector).collect();
+                assert_eq!(tags, vec![Tag::new(b"EDPT"), Tag::new(b"EHLT")]);
+            }
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::fmt::{Display, Formatter};
 
-use skrifa::setting::VariationSetting;
+use skrifa::{setting::VariationSetting, MetadataProvider, Tag};
 
-use crate::dfont::DFont;
+use crate::{dfont::DFont, instances::counterpart};
 
 /// A position across both fonts to test; could be an
 /// instance, could be a location
@@ -11,7 +11,12 @@ use crate::dfont::DFont;
 pub enum Setting {
     Instance(String),
     Location(Vec<VariationSetting>),
+    /// A location with a name, e.g. from the `STAT` table
+    Named(String, Vec<VariationSetting>),
     Default,
+    /// A different position in each font, e.g. to compare two locations
+    /// within the same variable font
+    Split(Box<Setting>, Box<Setting>),
 }
 
 impl Eq for Setting {}
@@ -38,6 +43,18 @@ pub fn parse_location(variations: &str) -> Result<Vec<VariationSetting>, String>
     Ok(settings)
 }
 
+/// Parse either a location in the form `axis=123,other=456` or the name
+/// of an instance
+pub fn parse_setting(setting: &str) -> Result<Setting, String> {
+    if setting.contains('=') {
+        Ok(Setting::Location(parse_location(setting)?))
+    } else if setting.is_empty() {
+        Err("No location or instance given".to_string())
+    } else {
+        Ok(Setting::Instance(setting.to_string()))
+    }
+}
+
 impl Display for Setting {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,7 +67,11 @@ impl Display for Setting {
                     .join(",");
                 write!(f, "{}", loc)
             }
+            Setting::Named(name, loc) => {
+                write!(f, "{} ({})", name, Setting::Location(loc.clone()))
+            }
             Setting::Default => write!(f, "default location"),
+            Setting::Split(a, b) => write!(f, "{} against {}", a, b),
         }
     }
 }
@@ -61,23 +82,65 @@ impl Setting {
     pub fn from_setting(location: Vec<VariationSetting>) -> Self {
         Setting::Location(location)
     }
+    /// Move both fonts to this location
+    ///
+    /// Static fonts stay as they are, so a static font can be compared with
+    /// an instance of a variable font; but two static fonts have no instances.
+    ///
+    /// A [Setting::Split] moves each font to its own position.
     pub fn set_on_fonts(&self, font_a: &mut DFont, font_b: &mut DFont) -> Result<(), String> {
+        self.set_on_fonts_mapped(font_a, font_b, &AxisMap::default())
+    }
+
+    /// Move both fonts to this location, translating it for the second font
+    /// with an [AxisMap]
+    ///
+    /// Locations (including the default) are given in the first font's
+    /// axes; instances and the two halves of a [Setting::Split] are not
+    /// translated.
+    pub fn set_on_fonts_mapped(
+        &self,
+        font_a: &mut DFont,
+        font_b: &mut DFont,
+        axis_map: &AxisMap,
+    ) -> Result<(), String> {
+        let any_variable = font_a.is_variable() || font_b.is_variable();
         match self {
-            Setting::Instance(inst) => {
-                font_a
-                    .set_instance(inst)
-                    .map_err(|_e| format!("Old font does not contain instance '{}'", inst))?;
-                font_b
-                    .set_instance(inst)
-                    .map_err(|_e| format!("New font does not contain instance '{}'", inst))?;
+            Setting::Location(loc) | Setting::Named(_, loc) => {
+                axis_map
+                    .pinned(font_a, loc.clone())
+                    .set_on_font(font_a, "Old")?;
+                axis_map
+                    .pinned(font_b, axis_map.translate(loc))
+                    .set_on_font(font_b, "New")?;
             }
-            Setting::Location(loc) => {
-                font_a.location = loc.clone();
-                font_a.normalize_location();
-                font_b.location = loc.clone();
-                font_b.normalize_location();
+            Setting::Default if !axis_map.pins.is_empty() => {
+                axis_map.pinned(font_a, vec![]).set_on_font(font_a, "Old")?;
+                axis_map.pinned(font_b, vec![]).set_on_font(font_b, "New")?;
+            }
+            Setting::Instance(inst) if font_a.is_variable() && font_b.is_variable() => {
+                let (name_a, name_b) = self
+                    .counterparts(font_a, font_b)
+                    .unwrap_or_else(|| (inst.clone(), inst.clone()));
+                Setting::Instance(name_a).set_on_font(font_a, "Old")?;
+                Setting::Instance(name_b).set_on_font(font_b, "New")?;
+            }
+            Setting::Instance(_) => {
+                if font_a.is_variable() || !any_variable {
+                    self.set_on_font(font_a, "Old")?;
+                }
+                if font_b.is_variable() || !any_variable {
+                    self.set_on_font(font_b, "New")?;
+                }
+            }
+            Setting::Split(setting_a, setting_b) => {
+                setting_a.set_on_font(font_a, "Old")?;
+                setting_b.set_on_font(font_b, "New")?;
+            }
+            _ => {
+                self.set_on_font(font_a, "Old")?;
+                self.set_on_font(font_b, "New")?;
             }
-            Setting::Default => {}
         }
         log::debug!("Font A location is: {:?}", font_a.location);
         log::debug!("Font B location is: {:?}", font_b.location);
@@ -92,15 +155,170 @@ impl Setting {
         Ok(())
     }
 
+    /// The instances of each font which an instance setting is compared
+    /// at, if either isn't the named instance itself
+    ///
+    /// An instance which was renamed or moved in one font is compared with
+    /// its counterpart in the other (see [counterpart]).
+    pub fn counterparts(&self, font_a: &DFont, font_b: &DFont) -> Option<(String, String)> {
+        let Setting::Instance(inst) = self else {
+            return None;
+        };
+        if !font_a.is_variable() || !font_b.is_variable() {
+            return None;
+        }
+        let name_a = counterpart(font_b, font_a, inst).unwrap_or_else(|| inst.clone());
+        let name_b = counterpart(font_a, font_b, inst).unwrap_or_else(|| inst.clone());
+        (name_a != *inst || name_b != *inst).then_some((name_a, name_b))
+    }
+
+    /// Move one font to this position; `which` names the font in errors
+    fn set_on_font(&self, font: &mut DFont, which: &str) -> Result<(), String> {
+        match self {
+            Setting::Instance(inst) => font
+                .set_instance(inst)
+                .map_err(|_e| format!("{} font does not contain instance '{}'", which, inst)),
+            Setting::Location(loc) | Setting::Named(_, loc) => {
+                font.location = loc.clone();
+                font.normalize_location();
+                Ok(())
+            }
+            Setting::Default => Ok(()),
+            Setting::Split(..) => Err(format!(
+                "{} font can only be at one position, not {}",
+                which, self
+            )),
+        }
+    }
+
     pub fn name(&self) -> String {
         match self {
-            Setting::Instance(inst) => inst.clone(),
+            Setting::Instance(inst) | Setting::Named(inst, _) => inst.clone(),
             Setting::Location(loc) => loc
                 .iter()
                 .map(|vs| format!("{}={}", vs.selector, vs.value))
                 .collect::<Vec<String>>()
                 .join(","),
             Setting::Default => "Default".to_string(),
+            Setting::Split(a, b) => format!("{} vs {}", a.name(), b.name()),
         }
     }
 }
+
+/// How to move the second font to a location given in the first font's
+/// axes, for fonts whose axes differ
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AxisMap {
+    /// Axes of the first font which appear differently in the second
+    pub mappings: Vec<AxisMapping>,
+    /// Values for axes which only one font has, used instead of the axis
+    /// default wherever a location doesn't mention them
+    pub pins: Vec<VariationSetting>,
+}
+
+/// An axis of the first font, and how it appears in the second
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisMapping {
+    /// The axis in the first font
+    pub from: Tag,
+    /// The axis in the second font, which may be `from` itself
+    pub to: Tag,
+    /// A range of values in the first font, and the range in the second
+    /// font which it corresponds to
+    pub range: Option<((f32, f32), (f32, f32))>,
+}
+
+/// Parse an axis mapping in the form `FROM[:MIN:MAX]=TO[:MIN:MAX]`, e.g.
+/// `XOPQ=XOPA` or `wdth:75:100=wdth:62.5:100`
+pub fn parse_axis_mapping(mapping: &str) -> Result<AxisMapping, String> {
+    let (from, to) = mapping
+        .split_once('=')
+        .ok_or_else(|| format!("Expected FROM=TO in axis mapping {}", mapping))?;
+    let side = |side: &str| -> Result<(Tag, Option<(f32, f32)>), String> {
+        let mut parts = side.split(':');
+        let tag = parts.next().unwrap_or_default();
+        let tag = Tag::new_checked(tag.as_bytes())
+            .map_err(|_| format!("Couldn't parse axis tag {}", tag))?;
+        let range: Vec<f32> = parts
+            .map(|v| {
+                v.parse::<f32>()
+                    .map_err(|_| format!("Couldn't parse value {}", v))
+            })
+            .collect::<Result<_, _>>()?;
+        match range.as_slice() {
+            [] => Ok((tag, None)),
+            [min, max] if min < max => Ok((tag, Some((*min, *max)))),
+            _ => Err(format!("Expected {}:MIN:MAX in axis mapping", tag)),
+        }
+    };
+    let ((from, range_a), (to, range_b)) = (side(from)?, side(to)?);
+    let range = match (range_a, range_b) {
+        (Some(a), Some(b)) => Some((a, b)),
+        (None, None) => None,
+        _ => return Err(format!("Give a range for both axes in {}", mapping)),
+    };
+    Ok(AxisMapping { from, to, range })
+}
+
+impl AxisMap {
+    /// Translate a location in the first font's axes into the second font's
+    pub fn translate(&self, location: &[VariationSetting]) -> Vec<VariationSetting> {
+        location
+            .iter()
+            .map(|setting| {
+                let Some(mapping) = self.mappings.iter().find(|m| m.from == setting.selector)
+                else {
+                    return *setting;
+                };
+                let value = match mapping.range {
+                    Some(((min_a, max_a), (min_b, max_b))) => {
+                        min_b + (setting.value - min_a) / (max_a - min_a) * (max_b - min_b)
+                    }
+                    None => setting.value,
+                };
+                VariationSetting::new(mapping.to, value)
+            })
+            .collect()
+    }
+
+    /// Translate a location in the second font's axes back into the
+    /// first font's, undoing [AxisMap::translate]
+    pub fn untranslate(&self, location: &[VariationSetting]) -> Vec<VariationSetting> {
+        location
+            .iter()
+            .map(|setting| {
+                let Some(mapping) = self.mappings.iter().find(|m| m.to == setting.selector) else {
+                    return *setting;
+                };
+                let value = match mapping.range {
+                    Some(((min_a, max_a), (min_b, max_b))) => {
+                        min_a + (setting.value - min_b) / (max_b - min_b) * (max_a - min_a)
+                    }
+                    None => setting.value,
+                };
+                VariationSetting::new(mapping.from, value)
+            })
+            .collect()
+    }
+
+    /// Whether an axis of the second font is given its value by this map,
+    /// rather than by the location being tested
+    pub fn covers(&self, tag: Tag) -> bool {
+        self.mappings.iter().any(|m| m.to == tag && m.from != tag)
+            || self.pins.iter().any(|p| p.selector == tag)
+    }
+
+    /// A location for `font`, with the pinned axes which the font has and
+    /// the location doesn't mention
+    fn pinned(&self, font: &DFont, mut location: Vec<VariationSetting>) -> Setting {
+        let axes = font.fontref().axes();
+        for pin in self.pins.iter() {
+            if axes.get_by_tag(pin.selector).is_some()
+                && !location.iter().any(|v| v.selector == pin.selector)
+            {
+                location.push(*pin);
+            }
+        }
+        Setting::Location(location)
+    }
+}
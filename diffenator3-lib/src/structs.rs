@@ -1,7 +1,7 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// Represents a difference between two renderings, whether words or glyphs
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct Difference {
     /// The text string which was rendered
@@ -14,14 +14,17 @@ pub struct Difference {
     /// The number of differing pixels
     pub differing_pixels: usize,
     /// The OpenType features applied to the text
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub ot_features: String,
     /// The OpenType language tag applied to the text
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub lang: String,
+    /// The CPAL palette the text was drawn with, if not the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<u16>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct EncodedGlyph {
     /// The character, as a string
@@ -31,17 +34,17 @@ pub struct EncodedGlyph {
     pub name: Option<String>,
 }
 /// Represents changes to the cmap table - added or removed glyphs
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct CmapDiff {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub missing: Vec<EncodedGlyph>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub new: Vec<EncodedGlyph>,
 }
 
 /// Represents a difference between two encoded glyphs
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct GlyphDiff {
     /// The string representation of the glyph
@@ -52,7 +55,213 @@ pub struct GlyphDiff {
     pub unicode: String,
     /// The number of differing pixels
     pub differing_pixels: usize,
+    /// The CPAL palette the glyph was drawn with, if not the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<u16>,
+}
+
+/// Represents a metric which differs between the fonts, font-wide or for one glyph
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct MetricDiff {
+    /// The name of the metric, e.g. "ascender" or "advance width"
+    pub metric: String,
+    /// The character whose glyph has this metric, for per-glyph metrics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glyph: Option<String>,
+    /// The value in the first font, if it has this metric
+    pub value_a: Option<f32>,
+    /// The value in the second font, if it has this metric
+    pub value_b: Option<f32>,
+}
+
+/// A glyph whose outline doesn't interpolate linearly between two masters
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct InterpolationKink {
+    /// The character whose glyph has the kink
+    pub glyph: String,
+    /// Where the outline strays furthest, in user space
+    pub location: String,
+    /// How far a point strays from where its neighbouring samples put it, in font units
+    pub deviation: f32,
+    /// Whether the glyph only kinks in the second font
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub introduced: bool,
+}
+
+/// Glyphs which kink between masters, in each font
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct InterpolationCheck {
+    pub font_a: Vec<InterpolationKink>,
+    pub font_b: Vec<InterpolationKink>,
+}
+
+/// A glyph or word which differs at one or more locations, with its
+/// differences at each location merged
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+#[serde(default)]
+pub struct GroupedDifference {
+    /// The glyph or word
+    pub string: String,
+    /// The Unicode codepoint and name, for a glyph
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// The script whose word list the word came from, for a word
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub script: String,
+    /// The OpenType features applied to the word
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub ot_features: String,
+    /// The OpenType language tag applied to the word
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub lang: String,
+    /// The CPAL palette it was drawn with, if not the default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<u16>,
+    /// The locations where it differs, in the order they were tested
+    pub locations: Vec<String>,
+    /// The fewest differing pixels at any of the locations
+    pub min_pixels: usize,
+    /// The most differing pixels at any of the locations
+    pub max_pixels: usize,
+}
+
+/// The glyph and word differences of a report, merged across locations
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct GroupedDifferences {
+    pub glyphs: Vec<GroupedDifference>,
+    pub words: Vec<GroupedDifference>,
+}
+
+/// What sort of change to a glyph a [GlyphChange] is
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphChangeKind {
+    /// Advance width or side bearing, from `hmtx` or `vmtx`
+    Metrics,
+    /// Bounding box at the default location
+    Bounds,
+    /// Outline at the default location
+    Outline,
+    /// Glyph class or mark attachment class, from `GDEF`
+    Gdef,
+    /// Mark attachment anchors, from `GPOS`
+    Anchors,
+    /// Other positioning, from `GPOS`
+    Positioning,
+    /// Kerning pairs
+    Kerning,
+    /// Substitution rules the glyph is part of, from `GSUB`
+    Substitution,
+    /// Codepoints mapped to the glyph, from `cmap`
+    Encoding,
+}
+
+/// One change to a glyph
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct GlyphChange {
+    pub kind: GlyphChangeKind,
+    /// Where the change is, e.g. `hmtx/ccedilla/width`
+    pub path: String,
+    /// The value in the first font, or the parts of it which mention the glyph
+    pub value_a: serde_json::Value,
+    /// The value in the second font, or the parts of it which mention the glyph
+    pub value_b: serde_json::Value,
+}
+
+/// How much the image of a glyph differs at a location
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct GlyphRender {
+    pub location: String,
+    pub differing_pixels: usize,
+}
+
+/// Every change to one glyph, gathered from across a report
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct GlyphDossier {
+    /// The name of the glyph
+    pub glyph: String,
+    /// The character the glyph is encoded as, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string: Option<String>,
+    /// The codepoint the glyph is encoded as, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unicode: Option<String>,
+    /// Changes in the font's tables, kerning and outlines
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<GlyphChange>,
+    /// Differences in the glyph's image, at each location where it differs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renders: Vec<GlyphRender>,
+}
+
+/// How a named instance differs between the fonts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceChangeKind {
+    /// Only the second font has the instance
+    Added,
+    /// Only the first font has the instance
+    Removed,
+    /// The instance has a different name in the second font
+    Renamed,
+    /// The instance is at a different location in the second font
+    Moved,
+}
+
+/// A named instance which was added, removed, renamed or moved
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct InstanceChange {
+    pub change: InstanceChangeKind,
+    /// The name of the instance in the first font
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_a: Option<String>,
+    /// The name of the instance in the second font
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_b: Option<String>,
+    /// The location of the instance in the first font, in user space
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_a: Option<String>,
+    /// The location of the instance in the second font, in user space
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_b: Option<String>,
+}
+
+/// A color which differs between the fonts' CPAL palettes
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct PaletteChange {
+    /// The index of the palette
+    pub palette: u16,
+    /// The index of the entry within the palette
+    pub entry: u16,
+    /// The color in the first font as `#RRGGBBAA`, if it has the entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_a: Option<String>,
+    /// The color in the second font as `#RRGGBBAA`, if it has the entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_b: Option<String>,
 }
 
 #[cfg(feature = "typescript")]
-pub type Api = (Difference, GlyphDiff, CmapDiff);
+pub type Api = (
+    Difference,
+    GlyphDiff,
+    CmapDiff,
+    MetricDiff,
+    InterpolationCheck,
+    InstanceChange,
+    GroupedDifferences,
+    GlyphDossier,
+    PaletteChange,
+);
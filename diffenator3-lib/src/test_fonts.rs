@@ -0,0 +1,36 @@
+//! Test fonts, and ways to change them for tests
+use read_fonts::{types::Tag, FontRef};
+
+/// A COLRv1 variable font, with seven palettes of ten entries each and
+/// one named instance
+pub(crate) fn nabla() -> Vec<u8> {
+    std::fs::read("test-data/Nabla-subset.ttf").expect("missing test font")
+}
+
+/// Where a table starts in the font
+pub(crate) fn table_offset(data: &[u8], tag: &[u8; 4]) -> usize {
+    FontRef::new(data)
+        .unwrap()
+        .table_directory
+        .table_records()
+        .iter()
+        .find(|record| record.tag() == Tag::new(tag))
+        .expect("font has the table")
+        .offset() as usize
+}
+
+pub(crate) fn get_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+pub(crate) fn set_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+pub(crate) fn get_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+pub(crate) fn set_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
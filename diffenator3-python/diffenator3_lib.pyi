@@ -0,0 +1,47 @@
+from os import PathLike
+from typing import Any, Dict, List, Optional, Union
+
+Font = Union[bytes, str, PathLike]
+
+def font_to_json(font: Font) -> Dict[str, Any]: ...
+def table_diff(
+    font_a: Font, font_b: Font, max_changes: int = 128, no_match: bool = False
+) -> Optional[Dict[str, Any]]: ...
+def kern_diff(
+    font_a: Font, font_b: Font, max_changes: int = 128, no_match: bool = False
+) -> Optional[Dict[str, Any]]: ...
+def test_font_words(
+    font_a: Font,
+    font_b: Font,
+    by_language: bool = False,
+    thresholds: Optional[Dict[str, int]] = None,
+    location: Optional[str] = None,
+) -> Dict[str, List[Difference]]: ...
+def diff_languages(font_a: Font, font_b: Font) -> Dict[str, LanguageDiff]: ...
+
+class EncodedGlyph:
+    string: str
+    name: Optional[str]
+
+class CmapDiff:
+    missing: List[EncodedGlyph]
+    new: List[EncodedGlyph]
+    def __init__(self, font_a: Font, font_b: Font) -> None: ...
+    def __bool__(self) -> bool: ...
+
+class Difference:
+    word: str
+    buffer_a: str
+    buffer_b: Optional[str]
+    differing_pixels: int
+    ot_features: str
+    lang: str
+    palette: Optional[int]
+
+class LanguageDiff:
+    level_a: str
+    score_a: float
+    fixes_a: int
+    level_b: str
+    score_b: float
+    fixes_b: int
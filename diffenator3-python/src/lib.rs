@@ -0,0 +1,350 @@
+//! Python bindings for the diffenator3 comparison library
+//!
+//! Fonts can be passed to any of these functions either as `bytes` or as a
+//! path to a font file. Table and kern diffs are returned as plain
+//! dictionaries in the same shape as diffenator3's JSON output (or `None`,
+//! if the fonts don't differ); rendering and language results are returned
+//! as read-only objects mirroring the Rust structures.
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use diffenator3_lib::{
+    dfont::DFont,
+    languages,
+    render::{self, encodedglyphs},
+    setting::parse_setting,
+    structs, Error,
+};
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    pybacked::PyBackedBytes,
+};
+use pythonize::pythonize;
+
+/// A font, given either as its binary contents or as a path to a file
+#[derive(FromPyObject)]
+enum FontSource {
+    Bytes(PyBackedBytes),
+    Path(PathBuf),
+}
+
+impl FontSource {
+    fn load(&self) -> PyResult<DFont> {
+        match self {
+            FontSource::Bytes(bytes) => DFont::new(bytes).map_err(to_py_err),
+            FontSource::Path(path) => {
+                let data = std::fs::read(path).map_err(|source| {
+                    to_py_err(Error::Io {
+                        path: path.clone(),
+                        source,
+                    })
+                })?;
+                DFont::new(&data).map_err(to_py_err)
+            }
+        }
+    }
+}
+
+fn to_py_err(error: Error) -> PyErr {
+    match error {
+        Error::Io { .. } => PyOSError::new_err(error.to_string()),
+        _ => PyValueError::new_err(error.to_string()),
+    }
+}
+
+/// Serialize the tables of a font to a dictionary
+#[pyfunction]
+fn font_to_json<'py>(py: Python<'py>, font: FontSource) -> PyResult<Bound<'py, PyAny>> {
+    let font = font.load()?;
+    let value = py.detach(|| ttj::font_to_json(&font.fontref(), None));
+    Ok(pythonize(py, &value)?)
+}
+
+/// Compare the tables of two fonts, returning a dictionary of differences
+#[pyfunction]
+#[pyo3(signature = (font_a, font_b, max_changes = 128, no_match = false))]
+fn table_diff<'py>(
+    py: Python<'py>,
+    font_a: FontSource,
+    font_b: FontSource,
+    max_changes: usize,
+    no_match: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let (font_a, font_b) = (font_a.load()?, font_b.load()?);
+    let value =
+        py.detach(|| ttj::table_diff(&font_a.fontref(), &font_b.fontref(), max_changes, no_match));
+    Ok(pythonize(py, &value)?)
+}
+
+/// Compare the kerning of two fonts, returning a dictionary of differences
+#[pyfunction]
+#[pyo3(signature = (font_a, font_b, max_changes = 128, no_match = false))]
+fn kern_diff<'py>(
+    py: Python<'py>,
+    font_a: FontSource,
+    font_b: FontSource,
+    max_changes: usize,
+    no_match: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let (font_a, font_b) = (font_a.load()?, font_b.load()?);
+    let value =
+        py.detach(|| ttj::kern_diff(&font_a.fontref(), &font_b.fontref(), max_changes, no_match));
+    Ok(pythonize(py, &value)?)
+}
+
+/// An encoded character, with its Unicode name if known
+#[pyclass(frozen, get_all, skip_from_py_object, module = "diffenator3_lib")]
+#[derive(Clone)]
+struct EncodedGlyph {
+    string: String,
+    name: Option<String>,
+}
+
+#[pymethods]
+impl EncodedGlyph {
+    fn __repr__(&self) -> String {
+        format!(
+            "EncodedGlyph(string={:?}, name={:?})",
+            self.string, self.name
+        )
+    }
+}
+
+impl From<structs::EncodedGlyph> for EncodedGlyph {
+    fn from(glyph: structs::EncodedGlyph) -> Self {
+        Self {
+            string: glyph.string,
+            name: glyph.name,
+        }
+    }
+}
+
+/// Characters which were removed from (`missing`) or added to (`new`) the cmap
+#[pyclass(frozen, get_all, skip_from_py_object, module = "diffenator3_lib")]
+struct CmapDiff {
+    missing: Vec<EncodedGlyph>,
+    new: Vec<EncodedGlyph>,
+}
+
+#[pymethods]
+impl CmapDiff {
+    #[new]
+    fn new(font_a: FontSource, font_b: FontSource) -> PyResult<Self> {
+        let diff = encodedglyphs::CmapDiff::new(&font_a.load()?, &font_b.load()?);
+        Ok(Self {
+            missing: diff.missing.into_iter().map(Into::into).collect(),
+            new: diff.new.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    fn __bool__(&self) -> bool {
+        !self.missing.is_empty() || !self.new.is_empty()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CmapDiff(missing={} glyph(s), new={} glyph(s))",
+            self.missing.len(),
+            self.new.len()
+        )
+    }
+}
+
+/// A word which renders differently in the two fonts
+#[pyclass(frozen, get_all, skip_from_py_object, module = "diffenator3_lib")]
+#[derive(Clone)]
+struct Difference {
+    word: String,
+    buffer_a: String,
+    buffer_b: Option<String>,
+    differing_pixels: usize,
+    ot_features: String,
+    lang: String,
+    palette: Option<u16>,
+}
+
+#[pymethods]
+impl Difference {
+    fn __repr__(&self) -> String {
+        format!(
+            "Difference(word={:?}, differing_pixels={})",
+            self.word, self.differing_pixels
+        )
+    }
+}
+
+impl From<structs::Difference> for Difference {
+    fn from(difference: structs::Difference) -> Self {
+        Self {
+            word: difference.word,
+            buffer_a: difference.buffer_a,
+            buffer_b: difference.buffer_b,
+            differing_pixels: difference.differing_pixels,
+            ot_features: difference.ot_features,
+            lang: difference.lang,
+            palette: difference.palette,
+        }
+    }
+}
+
+/// Render words in both fonts and return those which differ
+///
+/// The result maps each script tag (or language name, if `by_language` is
+/// true) to a list of `Difference` objects. `location` is either a named
+/// instance or a variation location such as `"wght=700,wdth=80"`.
+#[pyfunction]
+#[pyo3(signature = (font_a, font_b, by_language = false, thresholds = None, location = None))]
+fn test_font_words(
+    py: Python<'_>,
+    font_a: FontSource,
+    font_b: FontSource,
+    by_language: bool,
+    thresholds: Option<HashMap<String, usize>>,
+    location: Option<String>,
+) -> PyResult<BTreeMap<String, Vec<Difference>>> {
+    let (mut font_a, mut font_b) = (font_a.load()?, font_b.load()?);
+    if let Some(location) = location {
+        parse_setting(&location)
+            .map_err(PyValueError::new_err)?
+            .set_on_fonts(&mut font_a, &mut font_b)
+            .map_err(PyValueError::new_err)?;
+    }
+    let thresholds = thresholds.unwrap_or_default();
+    let differences =
+        py.detach(|| render::test_font_words(&font_a, &font_b, &[], by_language, &thresholds));
+    Ok(differences
+        .into_iter()
+        .map(|(key, words)| (key, words.into_iter().map(Into::into).collect()))
+        .collect())
+}
+
+/// How well each font supports a language
+#[pyclass(frozen, get_all, skip_from_py_object, module = "diffenator3_lib")]
+#[derive(Clone)]
+struct LanguageDiff {
+    level_a: String,
+    score_a: f32,
+    fixes_a: usize,
+    level_b: String,
+    score_b: f32,
+    fixes_b: usize,
+}
+
+#[pymethods]
+impl LanguageDiff {
+    fn __repr__(&self) -> String {
+        format!(
+            "LanguageDiff(level_a={:?}, level_b={:?})",
+            self.level_a, self.level_b
+        )
+    }
+}
+
+impl From<languages::LanguageDiff> for LanguageDiff {
+    fn from(diff: languages::LanguageDiff) -> Self {
+        Self {
+            level_a: diff.level_a,
+            score_a: diff.score_a,
+            fixes_a: diff.fixes_a,
+            level_b: diff.level_b,
+            score_b: diff.score_b,
+            fixes_b: diff.fixes_b,
+        }
+    }
+}
+
+/// Check the language support of both fonts, keyed by language name
+#[pyfunction]
+fn diff_languages(
+    py: Python<'_>,
+    font_a: FontSource,
+    font_b: FontSource,
+) -> PyResult<BTreeMap<String, LanguageDiff>> {
+    let (font_a, font_b) = (font_a.load()?, font_b.load()?);
+    let languages = py
+        .detach(|| languages::diff_languages(&font_a, &font_b))
+        .map_err(to_py_err)?;
+    Ok(languages
+        .into_iter()
+        .map(|(name, diff)| (name, diff.into()))
+        .collect())
+}
+
+#[pymodule]
+#[pyo3(name = "diffenator3_lib")]
+fn bindings(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(font_to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(table_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(kern_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(test_font_words, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_languages, m)?)?;
+    m.add_class::<CmapDiff>()?;
+    m.add_class::<EncodedGlyph>()?;
+    m.add_class::<Difference>()?;
+    m.add_class::<LanguageDiff>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::{PyBytes, PyDict};
+
+    const NABLA: &str = "../diffenator3-lib/test-data/Nabla-subset.ttf";
+    const CBDT: &str = "../diffenator3-lib/test-data/cbdt.ttf";
+
+    fn with_module(test: impl FnOnce(Python<'_>, Bound<'_, PyModule>) -> PyResult<()>) {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "diffenator3_lib")?;
+            bindings(&module)?;
+            test(py, module)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn table_diff_returns_a_dictionary() {
+        with_module(|py, module| {
+            let table_diff = module.getattr("table_diff")?;
+            // Identical fonts have no differences at all
+            assert!(table_diff.call1((NABLA, NABLA))?.is_none());
+            assert!(module
+                .getattr("kern_diff")?
+                .call1((NABLA, NABLA))?
+                .is_none());
+
+            let bytes = PyBytes::new(py, &std::fs::read(NABLA)?);
+            let different = table_diff.call1((bytes, CBDT))?;
+            assert!(different.cast::<PyDict>()?.len() > 0);
+
+            let error = table_diff.call1(("missing.ttf", NABLA)).unwrap_err();
+            assert!(error.is_instance_of::<PyOSError>(py));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn cmap_diff_lists_changed_characters() {
+        with_module(|_py, module| {
+            let cmap_diff = module.getattr("CmapDiff")?;
+            let same = cmap_diff.call1((NABLA, NABLA))?;
+            assert!(!same.is_truthy()?);
+
+            let different = cmap_diff.call1((NABLA, CBDT))?;
+            assert!(different.is_truthy()?);
+            let missing = different.getattr("missing")?;
+            assert!(missing.len()? > 0);
+            let string: String = missing.get_item(0)?.getattr("string")?.extract()?;
+            assert_eq!(string.chars().count(), 1);
+            assert!(different
+                .repr()?
+                .to_string()
+                .starts_with("CmapDiff(missing="));
+            Ok(())
+        });
+    }
+}
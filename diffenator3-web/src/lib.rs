@@ -9,7 +9,10 @@ use wasm_bindgen::JsValue;
 
 use wasm_bindgen::prelude::*;
 extern crate console_error_panic_hook;
-use std::{collections::BTreeMap, panic};
+use std::{
+    collections::{BTreeMap, HashMap},
+    panic,
+};
 
 use shaperglot::{Checker, Languages, SupportLevel};
 
@@ -47,6 +50,22 @@ pub fn lang_diff(font_a: &DFont, font_b: &DFont) -> serde_json::Value {
     serde_json::to_value(&supported).expect("Failed to serialize language support")
 }
 
+/// Parse both fonts, or describe why one couldn't be parsed
+fn load_fonts(font_a: &[u8], font_b: &[u8]) -> Result<(DFont, DFont), String> {
+    let f_a = DFont::new(font_a).map_err(|e| format!("Font A: {}", e))?;
+    let f_b = DFont::new(font_b).map_err(|e| format!("Font B: {}", e))?;
+    Ok((f_a, f_b))
+}
+
+/// Tell a callback that its fonts couldn't be compared
+fn send_error(f: &js_sys::Function, error: String) {
+    f.call1(
+        &JsValue::NULL,
+        &JsValue::from_str(&json!({ "error": error }).to_string()),
+    )
+    .unwrap();
+}
+
 #[wasm_bindgen]
 pub fn debugging() {
     panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -54,7 +73,11 @@ pub fn debugging() {
 
 #[wasm_bindgen]
 pub fn axes(font_a: &[u8], font_b: &[u8]) -> String {
-    let (axes, instances) = shared_axes(&DFont::new(font_a), &DFont::new(font_b));
+    let (f_a, f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return json!({ "error": error }).to_string(),
+    };
+    let (axes, instances) = shared_axes(&f_a, &f_b);
     serde_json::to_string(&json!({
         "axes": axes,
         "instances": instances
@@ -64,8 +87,10 @@ pub fn axes(font_a: &[u8], font_b: &[u8]) -> String {
 
 #[wasm_bindgen]
 pub fn diff_tables(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
-    let f_a = DFont::new(font_a);
-    let f_b = DFont::new(font_b);
+    let (f_a, f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
 
     let val = json!({
         "tables": table_diff(&f_a.fontref(), &f_b.fontref(), 128, true)
@@ -79,8 +104,10 @@ pub fn diff_tables(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
 
 #[wasm_bindgen]
 pub fn diff_kerns(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
-    let f_a = DFont::new(font_a);
-    let f_b = DFont::new(font_b);
+    let (f_a, f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
 
     let val = json!({
         "kerns": kern_diff(&f_a.fontref(), &f_b.fontref(), 1000, true)
@@ -94,8 +121,10 @@ pub fn diff_kerns(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
 
 #[wasm_bindgen]
 pub fn modified_glyphs(font_a: &[u8], font_b: &[u8], location: &str, f: &js_sys::Function) {
-    let mut f_a = DFont::new(font_a);
-    let mut f_b = DFont::new(font_b);
+    let (mut f_a, mut f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
     let _hack = f_a.set_location(location);
     let _hack = f_b.set_location(location);
 
@@ -111,8 +140,10 @@ pub fn modified_glyphs(font_a: &[u8], font_b: &[u8], location: &str, f: &js_sys:
 
 #[wasm_bindgen]
 pub fn cmap_diff(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
-    let f_a = DFont::new(font_a);
-    let f_b = DFont::new(font_b);
+    let (f_a, f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
     let val = json!({
         "cmap_diff": CmapDiff::new(&f_a, &f_b)
     });
@@ -131,19 +162,21 @@ pub fn diff_words(
     location: &str,
     f: &js_sys::Function,
 ) {
-    let mut f_a = DFont::new(font_a);
-    let mut f_b = DFont::new(font_b);
+    let (mut f_a, mut f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
     let _hack = f_a.set_location(location);
     let _hack = f_b.set_location(location);
 
     let custom_word_diff = if !custom_words.is_empty() {
-        vec![WordList::define("Custom words".to_string(), custom_words)]
+        vec![WordList::define("Custom words".to_string(), custom_words).into()]
     } else {
         vec![]
     };
 
     let val = json!({
-        "words": test_font_words(&f_a, &f_b, &custom_word_diff)
+        "words": test_font_words(&f_a, &f_b, &custom_word_diff, false, &HashMap::new())
     });
     f.call1(
         &JsValue::NULL,
@@ -154,8 +187,10 @@ pub fn diff_words(
 
 #[wasm_bindgen]
 pub fn diff_languages(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
-    let f_a = DFont::new(font_a);
-    let f_b = DFont::new(font_b);
+    let (f_a, f_b) = match load_fonts(font_a, font_b) {
+        Ok(fonts) => fonts,
+        Err(error) => return send_error(f, error),
+    };
 
     let val = json!({
         "languages": lang_diff(&f_a, &f_b)
@@ -169,8 +204,10 @@ pub fn diff_languages(font_a: &[u8], font_b: &[u8], f: &js_sys::Function) {
 
 #[wasm_bindgen]
 pub fn font_to_json(font_a: &[u8]) -> JsValue {
-    let f_a = DFont::new(font_a);
-    let val = underlying_font_to_json(&f_a.fontref(), None);
+    let val = match DFont::new(font_a) {
+        Ok(f_a) => underlying_font_to_json(&f_a.fontref(), None),
+        Err(e) => json!({ "error": e.to_string() }),
+    };
     serde_wasm_bindgen::to_value(&val).unwrap_or_else(|e| {
         let obj = js_sys::Object::new();
         let _ = js_sys::Reflect::set(&obj, &"error".into(), &e.to_string().into());
@@ -39,9 +39,9 @@ struct Args {
 fn main() {
     let args = Args::parse();
     let data_a = std::fs::read(&args.font1).expect("Can't read font file");
-    let mut dfont_a = diffenator3_lib::dfont::DFont::new(&data_a);
+    let mut dfont_a = diffenator3_lib::dfont::DFont::new(&data_a).expect("Can't parse font");
     let data_b = std::fs::read(&args.font2).expect("Can't read font file");
-    let mut dfont_b = diffenator3_lib::dfont::DFont::new(&data_b);
+    let mut dfont_b = diffenator3_lib::dfont::DFont::new(&data_b).expect("Can't parse font");
 
     if let Some(location) = args.location {
         let loc = parse_location(&location).expect("Couldn't parse location");
@@ -53,18 +53,24 @@ fn main() {
     let script_tag = harfrust::Script::from(&args.script);
     let direction = wordlists::get_script_direction(&args.script);
 
-    let mut renderer_a = Renderer::new(&dfont_a, args.size, direction, script_tag);
-    let mut renderer_b = Renderer::new(&dfont_b, args.size, direction, script_tag);
-    let (serialized_buffer_a, commands) =
-        renderer_a.string_to_positioned_glyphs(&args.text).unwrap();
+    let mut renderer_a = Renderer::new(&dfont_a, args.size, direction, script_tag, None, &[])
+        .expect("Couldn't set up renderer");
+    let mut renderer_b = Renderer::new(&dfont_b, args.size, direction, script_tag, None, &[])
+        .expect("Couldn't set up renderer");
+    let (serialized_buffer_a, commands) = renderer_a
+        .string_to_positioned_glyphs(&args.text)
+        .unwrap()
+        .unwrap();
     let image_a = renderer_a.render_positioned_glyphs(&commands);
     if args.verbose {
         println!("Commands A: {}", to_svg(commands));
     }
     println!("Buffer A: {}", serialized_buffer_a);
 
-    let (serialized_buffer_b, commands) =
-        renderer_b.string_to_positioned_glyphs(&args.text).unwrap();
+    let (serialized_buffer_b, commands) = renderer_b
+        .string_to_positioned_glyphs(&args.text)
+        .unwrap()
+        .unwrap();
     let image_b = renderer_b.render_positioned_glyphs(&commands);
     if args.verbose {
         println!("Commands B: {}", to_svg(commands));
@@ -0,0 +1,51 @@
+//! Errors which can occur while serializing a font
+use read_fonts::ReadError;
+use std::fmt;
+
+/// Something in a font which could not be serialized
+///
+/// Errors in a single table or subtable are recorded in the serialized
+/// output (as a "Could not parse" string) rather than stopping the rest of
+/// the font from being serialized.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The font data could not be read
+    Read(ReadError),
+    /// Class 0 of a class definition was needed, but there was no coverage
+    /// table to work out which glyphs it contains
+    ClassZeroWithoutCoverage,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read(e) => write!(f, "{}", e),
+            Error::ClassZeroWithoutCoverage => {
+                write!(
+                    f,
+                    "class 0 requested from a ClassDef with no coverage table"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read(e) => Some(e),
+            Error::ClassZeroWithoutCoverage => None,
+        }
+    }
+}
+
+impl From<ReadError> for Error {
+    fn from(e: ReadError) -> Self {
+        Error::Read(e)
+    }
+}
+
+/// How a table or subtable which could not be serialized appears in the output
+pub(crate) fn unparsable(error: impl fmt::Display) -> serde_json::Value {
+    serde_json::Value::String(format!("Could not parse: {}", error))
+}
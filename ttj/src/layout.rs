@@ -17,7 +17,11 @@ use read_fonts::{
 use serde_json::{Map, Value};
 use skrifa::GlyphId16;
 
-use super::{context::SerializationContext, namemap::NameMap};
+use super::{
+    context::SerializationContext,
+    error::{unparsable, Error},
+    namemap::NameMap,
+};
 
 pub(crate) fn serialize_gpos_table(context: &SerializationContext) -> Value {
     let mut map = Map::new();
@@ -158,15 +162,14 @@ pub trait SerializeLookup {
     fn serialize_lookup(&self, context: &SerializationContext) -> Value;
 }
 pub trait SerializeSubtable {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError>;
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error>;
 }
 
 macro_rules! serialize_it {
     ($subtables: ident, $context: ident) => {
         $subtables
             .iter()
-            .flatten()
-            .map(|st| st.serialize_subtable($context))
+            .map(|st| st.map_err(Error::from)?.serialize_subtable($context))
             .collect()
     };
 }
@@ -188,7 +191,7 @@ impl SerializeLookup for PositionLookup<'_> {
             return Value::Array(
                 serialized_tables
                     .into_iter()
-                    .map(|x| x.unwrap_or_default())
+                    .map(|x| x.unwrap_or_else(unparsable))
                     .collect(),
             );
         }
@@ -213,7 +216,7 @@ impl SerializeLookup for SubstitutionLookup<'_> {
             return Value::Array(
                 serialized_tables
                     .into_iter()
-                    .map(|x| x.unwrap_or_default())
+                    .map(|x| x.unwrap_or_else(unparsable))
                     .collect(),
             );
         }
@@ -336,7 +339,7 @@ impl ChainRule {
     }
 }
 impl SerializeSubtable for SequenceContext<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         let rules = match self {
             SequenceContext::Format1(f1) => serialize_sequence_f1(f1),
@@ -459,7 +462,7 @@ fn serialize_sequence_f3(
 }
 
 impl SerializeSubtable for ChainedSequenceContext<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         let rules = match self {
             ChainedSequenceContext::Format1(f1) => serialize_chain_sequence_f1(f1),
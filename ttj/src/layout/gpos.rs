@@ -1,16 +1,13 @@
 use super::{variable_scalars::SerializeValueRecordLike, SerializeSubtable};
-use crate::{context::SerializationContext, monkeypatching::MonkeyPatchClassDef};
-use read_fonts::{
-    tables::gpos::{
-        CursivePosFormat1, MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1, PairPos,
-        SinglePos,
-    },
-    ReadError,
+use crate::{context::SerializationContext, error::Error, monkeypatching::MonkeyPatchClassDef};
+use read_fonts::tables::gpos::{
+    CursivePosFormat1, MarkBasePosFormat1, MarkLigPosFormat1, MarkMarkPosFormat1, PairPos,
+    SinglePos,
 };
 use serde_json::{json, Map, Value};
 
 impl SerializeSubtable for SinglePos<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "single".into());
         let coverage = match self {
@@ -37,7 +34,7 @@ impl SerializeSubtable for SinglePos<'_> {
 }
 
 impl SerializeSubtable for PairPos<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "pair".into());
         match self {
@@ -78,7 +75,7 @@ impl SerializeSubtable for PairPos<'_> {
                 let mut kerns = Map::new();
                 for left_class in 0..s.class1_count() {
                     let mut left_class_glyphs =
-                        class1.class_glyphs(left_class, Some(s.coverage()?));
+                        class1.class_glyphs(left_class, Some(s.coverage()?))?;
                     left_class_glyphs.sort();
                     classes.insert(
                         format!("@CLASS_L_{}", left_class),
@@ -91,7 +88,7 @@ impl SerializeSubtable for PairPos<'_> {
                     );
                 }
                 for right_class in 1..s.class2_count() {
-                    let mut right_class_glyphs = class2.class_glyphs(right_class, None);
+                    let mut right_class_glyphs = class2.class_glyphs(right_class, None)?;
                     right_class_glyphs.sort();
                     classes.insert(
                         format!("@CLASS_R_{}", right_class),
@@ -147,7 +144,7 @@ impl SerializeSubtable for PairPos<'_> {
 }
 
 impl SerializeSubtable for CursivePosFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "cursive".into());
         for (glyph_id, record) in self.coverage()?.iter().zip(self.entry_exit_record()) {
@@ -175,7 +172,7 @@ impl SerializeSubtable for CursivePosFormat1<'_> {
 }
 
 impl SerializeSubtable for MarkBasePosFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "mark_to_base".into());
         let mark_array = self.mark_array()?;
@@ -235,7 +232,7 @@ impl SerializeSubtable for MarkBasePosFormat1<'_> {
 }
 
 impl SerializeSubtable for MarkLigPosFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "mark_to_lig".into());
         let mark_array = self.mark_array()?;
@@ -302,7 +299,7 @@ impl SerializeSubtable for MarkLigPosFormat1<'_> {
 }
 
 impl SerializeSubtable for MarkMarkPosFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "mark_to_mark".into());
         let mark_array = self.mark1_array()?;
@@ -1,20 +1,17 @@
 use super::SerializeSubtable;
-use crate::context::SerializationContext;
-use read_fonts::{
-    tables::{
-        gsub::{
-            AlternateSubstFormat1, LigatureSubstFormat1, MultipleSubstFormat1,
-            ReverseChainSingleSubstFormat1, SingleSubst,
-        },
-        varc::CoverageTable,
+use crate::{context::SerializationContext, error::Error};
+use read_fonts::tables::{
+    gsub::{
+        AlternateSubstFormat1, LigatureSubstFormat1, MultipleSubstFormat1,
+        ReverseChainSingleSubstFormat1, SingleSubst,
     },
-    ReadError,
+    varc::CoverageTable,
 };
 use serde_json::{Map, Value};
 use skrifa::GlyphId16;
 
 impl SerializeSubtable for SingleSubst<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "single".into());
         let coverage = match self {
@@ -45,7 +42,7 @@ impl SerializeSubtable for SingleSubst<'_> {
 }
 
 impl SerializeSubtable for MultipleSubstFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "multiple".into());
         let coverage = self.coverage()?;
@@ -62,7 +59,7 @@ impl SerializeSubtable for MultipleSubstFormat1<'_> {
 }
 
 impl SerializeSubtable for AlternateSubstFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "alternate".into());
         let coverage = self.coverage()?;
@@ -79,7 +76,7 @@ impl SerializeSubtable for AlternateSubstFormat1<'_> {
 }
 
 impl SerializeSubtable for LigatureSubstFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "ligature".into());
         let coverage = self.coverage()?;
@@ -107,7 +104,7 @@ impl SerializeSubtable for LigatureSubstFormat1<'_> {
 }
 
 impl SerializeSubtable for ReverseChainSingleSubstFormat1<'_> {
-    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, ReadError> {
+    fn serialize_subtable(&self, context: &SerializationContext) -> Result<Value, Error> {
         let mut map = Map::new();
         map.insert("type".to_string(), "reverse".into());
         let coverage_to_array = |coverage: CoverageTable<'_>| {
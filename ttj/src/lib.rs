@@ -1,5 +1,6 @@
 /// Convert a font to a serialized JSON representation
 pub mod context;
+pub mod error;
 mod gdef;
 pub mod jsondiff;
 mod layout;
@@ -7,7 +8,7 @@ pub mod monkeypatching;
 pub mod namemap;
 mod serializefont;
 
-use crate::{jsondiff::diff, serializefont::ToValue};
+use crate::{error::unparsable, jsondiff::diff, serializefont::ToValue};
 use context::SerializationContext;
 use namemap::NameMap;
 use read_fonts::{traversal::SomeTable, FontRef, TableProvider};
@@ -53,7 +54,11 @@ fn serialize_hmtx_table<'a>(font: &impl TableProvider<'a>, names: &NameMap) -> V
     if let Ok(hmtx) = font.hmtx() {
         let widths = hmtx.h_metrics();
         let long_metrics = widths.len();
-        for gid in 0..font.maxp().unwrap().num_glyphs() {
+        let num_glyphs = font
+            .maxp()
+            .map(|maxp| maxp.num_glyphs())
+            .unwrap_or(long_metrics as u16);
+        for gid in 0..num_glyphs {
             let name = names.get(gid);
             if gid < (long_metrics as u16) {
                 if let Some((width, lsb)) = widths
@@ -96,11 +101,6 @@ pub fn font_to_json(font: &FontRef, glyphmap: Option<&NameMap>) -> Value {
         &NameMap::new(font)
     };
     let mut map = Map::new();
-    // A serialization context bundles up all the information we need to serialize a font
-    let context = SerializationContext::new(font, glyphmap.clone()).unwrap_or_else(|_| {
-        panic!("Could not create serialization context for font");
-    });
-
     // Some tables are serialized by using read_font's traversal feature; typically those which
     // are just a set of fields and values (or are so complicated we haven't yet been bothered
     // to write our own serializers for them...)
@@ -135,19 +135,26 @@ pub fn font_to_json(font: &FontRef, glyphmap: Option<&NameMap>) -> Value {
                 )
             }),
         };
-        map.insert(
-            key,
-            value.unwrap_or_else(|_| Value::String("Could not parse".to_string())),
-        );
+        map.insert(key, value.unwrap_or_else(unparsable));
     }
 
     // Other tables require a bit of massaging to produce information which makes sense to diff.
     map.insert("name".to_string(), serialize_name_table(font));
     map.insert("cmap".to_string(), serialize_cmap_table(font, glyphmap));
     map.insert("hmtx".to_string(), serialize_hmtx_table(font, glyphmap));
-    map.insert("GDEF".to_string(), gdef::serialize_gdef_table(&context));
-    map.insert("GPOS".to_string(), layout::serialize_gpos_table(&context));
-    map.insert("GSUB".to_string(), layout::serialize_gsub_table(&context));
+    // A serialization context bundles up all the information we need to serialize a font
+    match SerializationContext::new(font, glyphmap.clone()) {
+        Ok(context) => {
+            map.insert("GDEF".to_string(), gdef::serialize_gdef_table(&context));
+            map.insert("GPOS".to_string(), layout::serialize_gpos_table(&context));
+            map.insert("GSUB".to_string(), layout::serialize_gsub_table(&context));
+        }
+        Err(e) => {
+            for tag in ["GDEF", "GPOS", "GSUB"] {
+                map.insert(tag.to_string(), unparsable(&e));
+            }
+        }
+    }
     Value::Object(map)
 }
 
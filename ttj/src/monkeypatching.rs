@@ -7,6 +7,8 @@ use read_fonts::{
 };
 use skrifa::{setting::VariationSetting, FontRef, GlyphId16};
 
+use crate::error::Error;
+
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
@@ -49,27 +51,34 @@ impl DenormalizeLocation for FontRef<'_> {
 
 pub trait MonkeyPatchClassDef {
     /// Return a list of glyphs in this class
-    fn class_glyphs(&self, class: u16, coverage: Option<CoverageTable>) -> Vec<GlyphId16>;
+    ///
+    /// Class 0 holds every glyph not in another class, so working out its
+    /// glyphs needs the coverage table of the subtable using the ClassDef.
+    fn class_glyphs(
+        &self,
+        class: u16,
+        coverage: Option<CoverageTable>,
+    ) -> Result<Vec<GlyphId16>, Error>;
 }
 
 impl MonkeyPatchClassDef for ClassDef<'_> {
-    fn class_glyphs(&self, class: u16, coverage: Option<CoverageTable>) -> Vec<GlyphId16> {
+    fn class_glyphs(
+        &self,
+        class: u16,
+        coverage: Option<CoverageTable>,
+    ) -> Result<Vec<GlyphId16>, Error> {
         if class == 0 {
-            // let coverage_map = coverage.unwrap().coverage_map();
-            if let Some(coverage) = coverage {
-                let all_glyphs: BTreeSet<GlyphId16> = coverage.iter().collect();
-                let in_a_class: BTreeSet<GlyphId16> =
-                    self.iter().map(|(gid, _a_class)| gid).collect();
-                // Remove all the glyphs in assigned class
-                all_glyphs.difference(&in_a_class).copied().collect()
-            } else {
-                panic!("ClassDef has no coverage table and class=0 was requested");
-            }
+            let coverage = coverage.ok_or(Error::ClassZeroWithoutCoverage)?;
+            let all_glyphs: BTreeSet<GlyphId16> = coverage.iter().collect();
+            let in_a_class: BTreeSet<GlyphId16> = self.iter().map(|(gid, _a_class)| gid).collect();
+            // Remove all the glyphs in assigned class
+            Ok(all_glyphs.difference(&in_a_class).copied().collect())
         } else {
-            self.iter()
+            Ok(self
+                .iter()
                 .filter(move |&(_gid, their_class)| their_class == class)
                 .map(|(gid, _)| gid)
-                .collect()
+                .collect())
         }
     }
 }
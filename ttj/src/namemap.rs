@@ -117,16 +117,16 @@ fn reverse_cmap(font: &FontRef) -> Option<HashMap<GlyphId16, u32>> {
         .encoding_records()
         .iter()
         .filter(is_unicode)
-        .map(|rec| rec.subtable(offset_data).unwrap())
+        .filter_map(|rec| rec.subtable(offset_data).ok())
     {
         match subtable {
             CmapSubtable::Format4(subtable) => subtable
                 .iter()
-                .map(|(unicode, gid)| (unicode, GlyphId16::try_from(gid).unwrap()))
+                .filter_map(|(unicode, gid)| Some((unicode, GlyphId16::try_from(gid).ok()?)))
                 .for_each(&mut add_to_map),
             CmapSubtable::Format12(subtable) => subtable
                 .iter()
-                .map(|(unicode, gid)| (unicode, GlyphId16::try_from(gid).unwrap()))
+                .filter_map(|(unicode, gid)| Some((unicode, GlyphId16::try_from(gid).ok()?)))
                 .for_each(&mut add_to_map),
             _ => (),
         }